@@ -58,7 +58,8 @@ pub enum EventType {
 }
 
 /// Holds information about expected axis range and deadzone.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
 pub struct AxisInfo {
     pub min: i32,
     pub max: i32,
@@ -82,6 +83,7 @@ pub struct AxisInfo {
 /// };
 /// ```
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
 pub enum PowerInfo {
     /// Failed to determine power status.
     Unknown,
@@ -170,6 +172,12 @@ impl Gamepad {
         *self.inner.uuid().as_bytes()
     }
 
+    /// Returns unique identifier of the device (usually serial number or MAC address), when
+    /// available.
+    pub fn uniq(&self) -> Option<&str> {
+        self.inner.uniq()
+    }
+
     /// Returns the vendor ID, as assigned by the USB-IF, when available.
     pub fn vendor_id(&self) -> Option<u16> {
         self.inner.vendor_id()
@@ -190,6 +198,15 @@ impl Gamepad {
         self.inner.is_ff_supported()
     }
 
+    /// Lights player indicator LED number `player` (counted from 1) and turns off the others, or
+    /// turns off all of them if `player` is `None`.
+    ///
+    /// Returns `false` if device doesn't have player LEDs or they couldn't be changed, for example
+    /// because of missing permissions. Currently only implemented on Linux.
+    pub fn set_player_leds(&self, player: Option<u8>) -> bool {
+        self.inner.set_player_leds(player)
+    }
+
     /// Creates `FfDevice` corresponding to this gamepad.
     pub fn ff_device(&self) -> Option<FfDevice> {
         self.inner.ff_device().map(|inner| FfDevice { inner })
@@ -53,6 +53,10 @@ impl Gamepad {
         Uuid::nil()
     }
 
+    pub fn uniq(&self) -> Option<&str> {
+        None
+    }
+
     pub fn vendor_id(&self) -> Option<u16> {
         None
     }
@@ -65,6 +69,10 @@ impl Gamepad {
         PowerInfo::Unknown
     }
 
+    pub fn set_player_leds(&self, _player: Option<u8>) -> bool {
+        false
+    }
+
     pub fn is_ff_supported(&self) -> bool {
         false
     }
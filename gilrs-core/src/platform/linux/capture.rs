@@ -0,0 +1,452 @@
+// Copyright 2016-2018 Mateusz Sieczko and other GilRs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Capturing and replaying raw evdev devices.
+//!
+//! Capture is a text file similar to the one created by `evemu-record`:
+//!
+//! ```text
+//! # gilrs evdev capture
+//! N: <device name>
+//! U: <unique identifier>
+//! I: <bustype> <vendor> <product> <version>
+//! B: <event type> <capability bits as hex bytes…>
+//! A: <axis> <min> <max> <fuzz> <flat> <resolution> [<value>]
+//! E: <sec>.<usec> <type> <code> <value>
+//! K: <key state as hex bytes…>
+//! X: <axis> <value>
+//! ```
+//!
+//! Types, codes, axes and IDs are hexadecimal, everything else is decimal. `K:` and `X:` lines are
+//! gilrs extension – they store state of device that was read after `SYN_DROPPED`, so replayed
+//! device can resynchronize the same way as the real one. Text after `#` is ignored.
+
+use super::gamepad::{Descriptor, ABS_MAX, EV_ABS, EV_FF, EV_KEY, KEY_MAX};
+use super::ioctl::{input_absinfo, input_event, input_id};
+use crate::utils;
+
+use std::collections::VecDeque;
+use std::error;
+use std::fmt::{self, Display, Formatter};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// Writes descriptor, events and state snapshots of single device.
+#[derive(Debug)]
+pub struct Capture {
+    out: BufWriter<File>,
+}
+
+impl Capture {
+    /// Starts capture of device if `GILRS_EVDEV_CAPTURE` environment variable is set to directory
+    /// where captures should be saved.
+    pub fn from_env(desc: &Descriptor, devpath: &str) -> Option<Self> {
+        let dir = std::env::var_os("GILRS_EVDEV_CAPTURE")?;
+        let devname = Path::new(devpath)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("device");
+        let secs = utils::time_now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let path = Path::new(&dir).join(format!("{}-{}.evemu", devname, secs));
+
+        let result = fs::create_dir_all(&dir)
+            .and_then(|_| File::create(&path))
+            .and_then(|file| {
+                let mut capture = Capture {
+                    out: BufWriter::new(file),
+                };
+                writeln!(capture.out, "# gilrs evdev capture")?;
+                writeln!(capture.out, "# Captured from: {}", devpath)?;
+                write_descriptor(&mut capture.out, desc)?;
+                capture.out.flush()?;
+
+                Ok(capture)
+            });
+
+        match result {
+            Ok(capture) => {
+                info!("Capturing {} to {:?}", devpath, path);
+                Some(capture)
+            }
+            Err(e) => {
+                error!("Failed to create capture {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    pub fn event(&mut self, ev: &input_event) -> io::Result<()> {
+        writeln!(
+            self.out,
+            "E: {}.{:06} {:04x} {:04x} {}",
+            ev.time.tv_sec, ev.time.tv_usec, ev.type_, ev.code, ev.value
+        )?;
+        // Flush on every report, so capture is useful even if application crashes.
+        if ev.type_ == 0 {
+            self.out.flush()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn state(&mut self, key_bits: &[u8], abs_values: &[(u16, i32)]) -> io::Result<()> {
+        writeln!(self.out, "K: {}", hex_bytes(key_bits))?;
+        for &(code, value) in abs_values {
+            writeln!(self.out, "X: {:02x} {}", code, value)?;
+        }
+
+        self.out.flush()
+    }
+}
+
+fn write_descriptor(out: &mut dyn Write, desc: &Descriptor) -> io::Result<()> {
+    let iid = desc.input_id;
+    writeln!(out, "N: {}", desc.name)?;
+    if let Some(ref uniq) = desc.uniq {
+        writeln!(out, "U: {}", uniq)?;
+    }
+    writeln!(
+        out,
+        "I: {:04x} {:04x} {:04x} {:04x}",
+        iid.bustype, iid.vendor, iid.product, iid.version
+    )?;
+    writeln!(out, "B: {:02x} {}", EV_KEY, hex_bytes(&desc.key_bits))?;
+    writeln!(out, "B: {:02x} {}", EV_ABS, hex_bytes(&desc.abs_bits))?;
+    writeln!(out, "B: {:02x} {}", EV_FF, hex_bytes(&desc.ff_bits))?;
+    for &(code, ref info) in &desc.absinfo {
+        writeln!(
+            out,
+            "A: {:02x} {} {} {} {} {} {}",
+            code, info.minimum, info.maximum, info.fuzz, info.flat, info.resolution, info.value
+        )?;
+    }
+
+    Ok(())
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(bytes.len() * 3);
+    for (i, b) in bytes.iter().enumerate() {
+        if i > 0 {
+            s.push(' ');
+        }
+        s.push_str(&format!("{:02x}", b));
+    }
+
+    s
+}
+
+#[derive(Debug)]
+enum Line {
+    Event(input_event),
+    Keys(Vec<u8>),
+    Abs(u16, i32),
+}
+
+/// Source of events for replayed device.
+#[derive(Debug)]
+pub struct Replay {
+    lines: VecDeque<Line>,
+    key_bits: [u8; KEY_MAX as usize / 8 + 1],
+    abs_values: [i32; ABS_MAX as usize + 1],
+}
+
+impl Replay {
+    pub fn open(path: &Path) -> Result<(Descriptor, Self), Error> {
+        let file = File::open(path).map_err(Error::Io)?;
+
+        Self::parse(BufReader::new(file))
+    }
+
+    pub fn parse<R: BufRead>(input: R) -> Result<(Descriptor, Self), Error> {
+        let mut desc = Descriptor {
+            name: String::new(),
+            uniq: None,
+            input_id: input_id {
+                bustype: 0,
+                vendor: 0,
+                product: 0,
+                version: 0,
+            },
+            key_bits: [0; KEY_MAX as usize / 8 + 1],
+            abs_bits: [0; ABS_MAX as usize / 8 + 1],
+            ff_bits: Default::default(),
+            absinfo: Vec::new(),
+        };
+        let mut replay = Replay {
+            lines: VecDeque::new(),
+            key_bits: [0; KEY_MAX as usize / 8 + 1],
+            abs_values: [0; ABS_MAX as usize + 1],
+        };
+        // Offsets of next byte in capability bits, evemu splits them into multiple lines.
+        let (mut key_off, mut abs_off, mut ff_off) = (0, 0, 0);
+
+        for (n, line) in input.lines().enumerate() {
+            let line = line.map_err(Error::Io)?;
+            let err = |msg| Error::Parse { line: n + 1, msg };
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (kind, rest) = line.split_once(':').ok_or(err("missing line type"))?;
+            let rest = rest.trim();
+            let mut fields = rest.split_whitespace();
+
+            match kind {
+                "N" => desc.name = rest.to_owned(),
+                "U" => desc.uniq = Some(rest.to_owned()).filter(|u| !u.is_empty()),
+                "I" => {
+                    let mut next = || parse_hex(fields.next()).ok_or(err("invalid device ID"));
+                    desc.input_id = input_id {
+                        bustype: next()?,
+                        vendor: next()?,
+                        product: next()?,
+                        version: next()?,
+                    };
+                }
+                "B" => {
+                    let ty = parse_hex(fields.next()).ok_or(err("invalid event type"))?;
+                    let (bits, off): (&mut [u8], _) = match ty {
+                        EV_KEY => (&mut desc.key_bits, &mut key_off),
+                        EV_ABS => (&mut desc.abs_bits, &mut abs_off),
+                        EV_FF => (&mut desc.ff_bits, &mut ff_off),
+                        _ => continue,
+                    };
+                    for field in fields {
+                        let byte = parse_hex::<u8>(Some(field)).ok_or(err("invalid bits"))?;
+                        if let Some(b) = bits.get_mut(*off) {
+                            *b = byte;
+                        }
+                        *off += 1;
+                    }
+                }
+                "A" => {
+                    let code = parse_hex(fields.next()).ok_or(err("invalid axis"))?;
+                    let mut next = || parse_dec(fields.next()).ok_or(err("invalid axis info"));
+                    let mut info = input_absinfo {
+                        minimum: next()?,
+                        maximum: next()?,
+                        fuzz: next()?,
+                        flat: next()?,
+                        resolution: next()?,
+                        ..Default::default()
+                    };
+                    info.value = next().unwrap_or(0);
+                    if code > ABS_MAX {
+                        return Err(err("invalid axis"));
+                    }
+                    replay.abs_values[code as usize] = info.value;
+                    desc.absinfo.push((code, info));
+                }
+                "E" => {
+                    let (sec, usec) = fields
+                        .next()
+                        .and_then(|t| t.split_once('.'))
+                        .and_then(|(s, us)| Some((s.parse::<i64>().ok()?, us.parse::<i64>().ok()?)))
+                        .ok_or(err("invalid event time"))?;
+                    let mut ev = input_event {
+                        type_: parse_hex(fields.next()).ok_or(err("invalid event type"))?,
+                        code: parse_hex(fields.next()).ok_or(err("invalid event code"))?,
+                        value: parse_dec(fields.next()).ok_or(err("invalid event value"))?,
+                        ..Default::default()
+                    };
+                    ev.time.tv_sec = sec as _;
+                    ev.time.tv_usec = usec as _;
+                    replay.lines.push_back(Line::Event(ev));
+                }
+                "K" => {
+                    let bits = fields
+                        .map(|f| parse_hex(Some(f)))
+                        .collect::<Option<Vec<u8>>>()
+                        .ok_or(err("invalid key state"))?;
+                    replay.lines.push_back(Line::Keys(bits));
+                }
+                "X" => {
+                    let code = parse_hex(fields.next()).ok_or(err("invalid axis"))?;
+                    let value = parse_dec(fields.next()).ok_or(err("invalid axis value"))?;
+                    if code > ABS_MAX {
+                        return Err(err("invalid axis"));
+                    }
+                    replay.lines.push_back(Line::Abs(code, value));
+                }
+                // Other evemu lines (properties, LEDs, …) are not used by gilrs.
+                _ => (),
+            }
+        }
+
+        Ok((desc, replay))
+    }
+
+    /// Returns next recorded event. Device state is updated the same way as kernel does it.
+    pub fn next_event(&mut self) -> Option<input_event> {
+        self.apply_state();
+
+        match self.lines.pop_front()? {
+            Line::Event(ev) => {
+                match ev.type_ {
+                    EV_KEY if ev.code <= KEY_MAX => {
+                        let byte = &mut self.key_bits[ev.code as usize / 8];
+                        let mask = 1 << (ev.code % 8);
+                        if ev.value == 0 {
+                            *byte &= !mask;
+                        } else {
+                            *byte |= mask;
+                        }
+                    }
+                    EV_ABS if ev.code <= ABS_MAX => self.abs_values[ev.code as usize] = ev.value,
+                    _ => (),
+                }
+
+                Some(ev)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns state of all keys, like `EVIOCGKEY`.
+    pub fn key_state(&mut self, buf: &mut [u8]) {
+        self.apply_state();
+        let len = buf.len().min(self.key_bits.len());
+        buf[..len].copy_from_slice(&self.key_bits[..len]);
+    }
+
+    /// Returns value of axis, like `EVIOCGABS`.
+    pub fn abs_value(&mut self, code: u16) -> i32 {
+        self.apply_state();
+        self.abs_values.get(code as usize).cloned().unwrap_or(0)
+    }
+
+    // Applies state captured after SYN_DROPPED. Events that were dropped by kernel are not in
+    // capture, so their effect on state is only visible here.
+    fn apply_state(&mut self) {
+        loop {
+            match self.lines.front() {
+                Some(Line::Keys(bits)) => {
+                    let len = bits.len().min(self.key_bits.len());
+                    self.key_bits = [0; KEY_MAX as usize / 8 + 1];
+                    self.key_bits[..len].copy_from_slice(&bits[..len]);
+                }
+                Some(&Line::Abs(code, value)) => self.abs_values[code as usize] = value,
+                _ => return,
+            }
+            self.lines.pop_front();
+        }
+    }
+}
+
+fn parse_hex<T: TryFrom<u32>>(s: Option<&str>) -> Option<T> {
+    u32::from_str_radix(s?, 16)
+        .ok()
+        .and_then(|v| T::try_from(v).ok())
+}
+
+fn parse_dec(s: Option<&str>) -> Option<i32> {
+    s?.parse().ok()
+}
+
+/// Error returned when capture can not be replayed.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Parse { line: usize, msg: &'static str },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "failed to read evdev capture: {}", e),
+            Error::Parse { line, msg } => {
+                write!(f, "invalid evdev capture, line {}: {}", line, msg)
+            }
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAPTURE: &str = "\
+# gilrs evdev capture
+N: Test Pad
+U: 00:11:22:33:44:55
+I: 0003 045e 028e 0114
+B: 01 00 00 00 00 00 00 00 00
+B: 01 00 00 00 00 00 00 00 00
+B: 01 00 00 00 00 00 00 00 00
+B: 01 00 00 00 00 00 00 00 00
+B: 01 00 00 00 00 00 00 ff 7f
+B: 03 03 00 00 00 00 00 00 00
+A: 00 -32768 32767 16 128 0
+A: 01 -32768 32767 16 128 0 100
+E: 0.000010 0001 0130 1 # BTN_SOUTH
+E: 0.000010 0000 0000 0
+K: 00 00
+X: 01 -5
+E: 0.000020 0003 0000 42
+";
+
+    #[test]
+    fn parse_and_write() {
+        let (desc, mut replay) = Replay::parse(CAPTURE.as_bytes()).unwrap();
+        assert_eq!(desc.name, "Test Pad");
+        assert_eq!(desc.uniq.as_deref(), Some("00:11:22:33:44:55"));
+        assert_eq!(desc.input_id.vendor, 0x045e);
+        assert_eq!(desc.key_bits[38], 0xff);
+        assert_eq!(desc.abs_bits[0], 0x03);
+        assert_eq!(desc.absinfo.len(), 2);
+        assert_eq!(desc.absinfo[1].1.flat, 128);
+        assert_eq!(replay.abs_value(1), 100);
+
+        let ev = replay.next_event().unwrap();
+        assert_eq!((ev.type_, ev.code, ev.value), (EV_KEY, 0x130, 1));
+        assert_eq!(ev.time.tv_usec, 10);
+        let mut keys = [0u8; KEY_MAX as usize / 8 + 1];
+        replay.key_state(&mut keys);
+        assert!(utils::test_bit(0x130, &keys));
+
+        // Snapshot is applied before state is queried after SYN_REPORT.
+        replay.next_event().unwrap();
+        replay.key_state(&mut keys);
+        assert!(!utils::test_bit(0x130, &keys));
+        assert_eq!(replay.abs_value(1), -5);
+
+        assert_eq!(replay.next_event().unwrap().value, 42);
+        assert!(replay.next_event().is_none());
+
+        let mut out = Vec::new();
+        write_descriptor(&mut out, &desc).unwrap();
+        let (desc2, _) = Replay::parse(&out[..]).unwrap();
+        assert_eq!(desc2.name, desc.name);
+        assert_eq!(desc2.uniq, desc.uniq);
+        assert_eq!(desc2.key_bits[..], desc.key_bits[..]);
+        assert_eq!(desc2.absinfo, desc.absinfo);
+    }
+
+    #[test]
+    fn invalid_line() {
+        match Replay::parse("N: x\nE: 1 0001\n".as_bytes()) {
+            Err(Error::Parse { line: 2, .. }) => (),
+            r => panic!("unexpected result: {:?}", r.map(|_| ())),
+        }
+    }
+}
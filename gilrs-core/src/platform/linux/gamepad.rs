@@ -5,9 +5,10 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+use super::capture::{Capture, Replay};
 use super::ff::Device as FfDevice;
 use super::ioctl;
-use super::ioctl::{input_absinfo, input_event};
+use super::ioctl::{input_absinfo, input_event, input_id};
 use super::udev::*;
 use crate::utils;
 use crate::{AxisInfo, Event, EventType};
@@ -27,15 +28,18 @@ use std::ffi::OsStr;
 use std::ffi::{CStr, CString};
 use std::fmt::{Display, Formatter, Result as FmtResult};
 use std::fs::File;
+use std::io;
 use std::mem::{self, MaybeUninit};
 use std::ops::Index;
 use std::os::raw::c_char;
 use std::os::unix::ffi::OsStrExt;
-use std::os::unix::io::{BorrowedFd, RawFd};
+use std::os::unix::io::{AsRawFd, BorrowedFd, RawFd};
 use std::path::{Path, PathBuf};
 use std::str;
 use std::sync::mpsc;
 use std::sync::mpsc::{Receiver, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 const HOTPLUG_DATA: u64 = u64::MAX;
@@ -47,18 +51,26 @@ pub struct Gilrs {
     hotplug_rx: Receiver<HotplugEvent>,
     to_check: VecDeque<usize>,
     discovery_backend: DiscoveryBackend,
+    join_handle: Option<JoinHandle<()>>,
+    stop_event: Option<Arc<EventFd>>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum DiscoveryBackend {
     Udev,
     Inotify,
+    // Gamepads are recreated from captures listed in `GILRS_EVDEV_REPLAY`.
+    Replay,
 }
 
 const INPUT_DIR_PATH: &str = "/dev/input";
 
 impl Gilrs {
     pub(crate) fn new() -> Result<Self, PlatformError> {
+        if let Some(paths) = std::env::var_os("GILRS_EVDEV_REPLAY") {
+            return Self::replay(&std::env::split_paths(&paths).collect::<Vec<_>>());
+        }
+
         let mut gamepads = Vec::new();
         let epoll = Epoll::new(EpollCreateFlags::empty())
             .map_err(|e| errno_to_platform_error(e, "creating epoll fd"))?;
@@ -72,6 +84,11 @@ impl Gilrs {
             )
             .map_err(|e| errno_to_platform_error(e, "adding evevntfd do epoll"))?;
 
+        let stop_event = EventFd::from_value_and_flags(0, EfdFlags::EFD_NONBLOCK)
+            .map_err(|e| errno_to_platform_error(e, "creating eventfd"))?;
+        let stop_event = Arc::new(stop_event);
+        let thread_stop_event = stop_event.clone();
+
         if Path::new("/.flatpak-info").exists() || std::env::var("GILRS_DISABLE_UDEV").is_ok() {
             log::debug!("Looks like we're in an environment without udev. Falling back to inotify");
             let (hotplug_tx, hotplug_rx) = mpsc::channel();
@@ -109,14 +126,20 @@ impl Gilrs {
                 }
             }
 
-            std::thread::Builder::new()
+            let join_handle = std::thread::Builder::new()
                 .name("gilrs".to_owned())
                 .spawn(move || {
                     let mut buffer = [0u8; 1024];
                     debug!("Started gilrs inotify thread");
                     loop {
-                        let events = match inotify.read_events_blocking(&mut buffer) {
+                        if !wait_readable(inotify.as_raw_fd(), &thread_stop_event) {
+                            debug!("Stopping gilrs inotify thread");
+                            return;
+                        }
+
+                        let events = match inotify.read_events(&mut buffer) {
                             Ok(events) => events,
+                            Err(err) if err.kind() == io::ErrorKind::WouldBlock => continue,
                             Err(err) => {
                                 error!("Failed to check for changes to joysticks: {err}");
                                 return;
@@ -136,6 +159,8 @@ impl Gilrs {
                 hotplug_rx,
                 to_check: VecDeque::new(),
                 discovery_backend: DiscoveryBackend::Inotify,
+                join_handle: Some(join_handle),
+                stop_event: Some(stop_event),
             });
         }
         let udev = match Udev::new() {
@@ -173,7 +198,7 @@ impl Gilrs {
         }
 
         let (hotplug_tx, hotplug_rx) = mpsc::channel();
-        std::thread::Builder::new()
+        let join_handle = std::thread::Builder::new()
             .name("gilrs".to_owned())
             .spawn(move || {
                 let udev = match Udev::new() {
@@ -192,7 +217,7 @@ impl Gilrs {
                     }
                 };
 
-                handle_hotplug(hotplug_tx, monitor, hotplug_event)
+                handle_hotplug(hotplug_tx, monitor, hotplug_event, &thread_stop_event)
             })
             .expect("failed to spawn thread");
 
@@ -202,6 +227,38 @@ impl Gilrs {
             hotplug_rx,
             to_check: VecDeque::new(),
             discovery_backend: DiscoveryBackend::Udev,
+            join_handle: Some(join_handle),
+            stop_event: Some(stop_event),
+        })
+    }
+
+    /// Creates context with gamepads recreated from evdev captures instead of real devices.
+    fn replay(paths: &[PathBuf]) -> Result<Self, PlatformError> {
+        let mut gamepads = Vec::new();
+        for path in paths {
+            let (desc, replay) =
+                Replay::open(path).map_err(|e| PlatformError::Other(Box::new(e)))?;
+            match Gamepad::replayed(path.to_string_lossy().into_owned(), &desc, replay) {
+                Some(gamepad) => gamepads.push(gamepad),
+                None => warn!(
+                    "{:?} doesn't have at least 1 button and 2 axes, ignoring.",
+                    path
+                ),
+            }
+        }
+
+        let epoll = Epoll::new(EpollCreateFlags::empty())
+            .map_err(|e| errno_to_platform_error(e, "creating epoll fd"))?;
+        let (_, hotplug_rx) = mpsc::channel();
+
+        Ok(Gilrs {
+            gamepads,
+            epoll,
+            hotplug_rx,
+            to_check: VecDeque::new(),
+            discovery_backend: DiscoveryBackend::Replay,
+            join_handle: None,
+            stop_event: None,
         })
     }
 
@@ -214,6 +271,10 @@ impl Gilrs {
     }
 
     fn next_event_impl(&mut self, timeout: Option<Duration>) -> Option<Event> {
+        if self.discovery_backend == DiscoveryBackend::Replay {
+            return self.next_replayed_event();
+        }
+
         let mut check_hotplug = false;
 
         if self.to_check.is_empty() {
@@ -286,6 +347,28 @@ impl Gilrs {
         None
     }
 
+    /// Captures are replayed one after another, as fast as possible. Gamepad is disconnected when
+    /// its capture ends.
+    fn next_replayed_event(&mut self) -> Option<Event> {
+        let (idx, gamepad) = self
+            .gamepads
+            .iter_mut()
+            .enumerate()
+            .find(|(_, gp)| gp.is_connected)?;
+
+        match gamepad.event() {
+            Some((event, time)) => Some(Event {
+                id: idx,
+                event,
+                time,
+            }),
+            None => {
+                gamepad.disconnect();
+                Some(Event::new(idx, EventType::Disconnected))
+            }
+        }
+    }
+
     pub fn gamepad(&self, id: usize) -> Option<&Gamepad> {
         self.gamepads.get(id)
     }
@@ -354,6 +437,21 @@ impl Gilrs {
     }
 }
 
+impl Drop for Gilrs {
+    fn drop(&mut self) {
+        if let Some(stop_event) = self.stop_event.take() {
+            if let Err(e) = stop_event.write(1) {
+                warn!("Failed to send stop signal to hot plug thread: {e}");
+            }
+        }
+        if let Some(join_handle) = self.join_handle.take() {
+            if let Err(e) = join_handle.join() {
+                warn!("Failed to join hot plug thread: {e:?}");
+            }
+        }
+    }
+}
+
 enum HotplugEvent {
     New { devpath: CString, syspath: PathBuf },
     Removed(String),
@@ -411,10 +509,7 @@ fn handle_inotify(
 }
 
 fn get_gamepad_path(name: &str) -> Option<(PathBuf, PathBuf)> {
-    let event_id = match name.strip_prefix("event") {
-        Some(event_id) => event_id,
-        None => return None,
-    };
+    let event_id = name.strip_prefix("event")?;
     if event_id.is_empty()
         || event_id
             .chars()
@@ -428,10 +523,38 @@ fn get_gamepad_path(name: &str) -> Option<(PathBuf, PathBuf)> {
     Some((gamepad_path, syspath))
 }
 
-fn handle_hotplug(sender: Sender<HotplugEvent>, monitor: Monitor, event: EventFd) {
+/// Blocks until `fd` can be read. Returns `false` if `stop` was signaled or polling failed.
+fn wait_readable(fd: RawFd, stop: &EventFd) -> bool {
+    let mut fds = [
+        c::pollfd {
+            fd,
+            events: c::POLLIN,
+            revents: 0,
+        },
+        c::pollfd {
+            fd: stop.as_raw_fd(),
+            events: c::POLLIN,
+            revents: 0,
+        },
+    ];
+
+    loop {
+        match unsafe { c::poll(fds.as_mut_ptr(), fds.len() as c::nfds_t, -1) } {
+            -1 if Errno::last() == Errno::EINTR => continue,
+            -1 => {
+                error!("Failed to poll hot plug events: {}", Errno::last());
+                return false;
+            }
+            _ => return fds[1].revents == 0,
+        }
+    }
+}
+
+fn handle_hotplug(sender: Sender<HotplugEvent>, monitor: Monitor, event: EventFd, stop: &EventFd) {
     loop {
-        if !monitor.wait_hotplug_available() {
-            continue;
+        if !wait_readable(monitor.fd(), stop) {
+            debug!("Stopping gilrs hot plug thread");
+            break;
         }
 
         let dev = monitor.device();
@@ -499,33 +622,88 @@ struct AxesInfo {
 }
 
 impl AxesInfo {
-    fn new(fd: i32) -> Self {
+    fn new(desc: &Descriptor) -> Self {
         let mut map = VecMap::new();
 
+        for &(code, ref info) in &desc.absinfo {
+            map.insert(
+                code as usize,
+                AxisInfo {
+                    min: info.minimum,
+                    max: info.maximum,
+                    deadzone: Some(info.flat as u32),
+                },
+            );
+        }
+
+        AxesInfo { info: map }
+    }
+}
+
+/// Everything that gilrs reads from device when opening it. Can be read from real device or from
+/// capture.
+#[derive(Debug, Clone)]
+pub(super) struct Descriptor {
+    pub name: String,
+    pub uniq: Option<String>,
+    pub input_id: input_id,
+    pub key_bits: [u8; KEY_MAX as usize / 8 + 1],
+    pub abs_bits: [u8; ABS_MAX as usize / 8 + 1],
+    pub ff_bits: [u8; FF_MAX as usize / 8 + 1],
+    pub absinfo: Vec<(u16, input_absinfo)>,
+}
+
+impl Descriptor {
+    fn from_fd(fd: RawFd) -> Option<Self> {
+        let input_id = Gamepad::get_input_id(fd)?;
+        let name = Gamepad::get_name(fd).unwrap_or_else(|| {
+            error!("Failed to get name of device with fd {}", fd);
+            "Unknown".into()
+        });
+
+        let uniq = Gamepad::get_uniq(fd);
+
+        let mut desc = Descriptor {
+            name,
+            uniq,
+            input_id,
+            key_bits: [0; KEY_MAX as usize / 8 + 1],
+            abs_bits: [0; ABS_MAX as usize / 8 + 1],
+            ff_bits: [0; FF_MAX as usize / 8 + 1],
+            absinfo: Vec::new(),
+        };
+
         unsafe {
-            let mut abs_bits = [0u8; (ABS_MAX / 8) as usize + 1];
+            ioctl::eviocgbit(
+                fd,
+                u32::from(EV_KEY),
+                desc.key_bits.len() as i32,
+                desc.key_bits.as_mut_ptr(),
+            );
             ioctl::eviocgbit(
                 fd,
                 u32::from(EV_ABS),
-                abs_bits.len() as i32,
-                abs_bits.as_mut_ptr(),
+                desc.abs_bits.len() as i32,
+                desc.abs_bits.as_mut_ptr(),
             );
+            if ioctl::eviocgbit(
+                fd,
+                u32::from(EV_FF),
+                desc.ff_bits.len() as i32,
+                desc.ff_bits.as_mut_ptr(),
+            ) < 0
+            {
+                desc.ff_bits = [0; FF_MAX as usize / 8 + 1];
+            }
 
-            for axis in Gamepad::find_axes(&abs_bits) {
+            for axis in Gamepad::find_axes(&desc.abs_bits) {
                 let mut info = input_absinfo::default();
                 ioctl::eviocgabs(fd, u32::from(axis.code), &mut info);
-                map.insert(
-                    axis.code as usize,
-                    AxisInfo {
-                        min: info.minimum,
-                        max: info.maximum,
-                        deadzone: Some(info.flat as u32),
-                    },
-                );
+                desc.absinfo.push((axis.code, info));
             }
         }
 
-        AxesInfo { info: map }
+        Some(desc)
     }
 }
 
@@ -544,14 +722,21 @@ pub struct Gamepad {
     ff_supported: bool,
     devpath: String,
     name: String,
+    uniq: Option<String>,
     uuid: Uuid,
     vendor_id: u16,
     product_id: u16,
     bt_capacity_fd: RawFd,
     bt_status_fd: RawFd,
+    player_leds: Vec<PathBuf>,
     axes_values: VecMap<i32>,
     buttons_values: VecMap<bool>,
+    // Events generated by `compare_state()`, returned before anything else.
     events: Vec<input_event>,
+    // Events that were read from device, but not processed yet.
+    pending: VecDeque<input_event>,
+    replay: Option<Replay>,
+    capture: Option<Capture>,
     axes: Vec<EvCode>,
     buttons: Vec<EvCode>,
     is_connected: bool,
@@ -577,8 +762,8 @@ impl Gamepad {
             return None;
         }
 
-        let input_id = match Self::get_input_id(fd) {
-            Some(input_id) => input_id,
+        let desc = match Descriptor::from_fd(fd) {
+            Some(desc) => desc,
             None => {
                 error!("Failed to get id of device {:?}", path);
                 unsafe {
@@ -588,35 +773,12 @@ impl Gamepad {
             }
         };
 
-        let name = Self::get_name(fd).unwrap_or_else(|| {
-            error!("Failed to get name of device {:?}", path);
-            "Unknown".into()
-        });
-
-        let axesi = AxesInfo::new(fd);
-        let ff_supported = Self::test_ff(fd);
         let (cap, status) = Self::battery_fd(syspath);
-
-        let mut gamepad = Gamepad {
-            fd,
-            axes_info: axesi,
-            ff_supported,
-            devpath: path.to_string_lossy().into_owned(),
-            name,
-            uuid: create_uuid(input_id),
-            vendor_id: input_id.vendor,
-            product_id: input_id.product,
-            bt_capacity_fd: cap,
-            bt_status_fd: status,
-            axes_values: VecMap::new(),
-            buttons_values: VecMap::new(),
-            events: Vec::new(),
-            axes: Vec::new(),
-            buttons: Vec::new(),
-            is_connected: true,
-        };
-
-        gamepad.collect_axes_and_buttons();
+        let mut gamepad = Gamepad::new(fd, path.to_string_lossy().into_owned(), &desc);
+        gamepad.ff_supported = Self::test_ff(&desc.ff_bits);
+        gamepad.bt_capacity_fd = cap;
+        gamepad.bt_status_fd = status;
+        gamepad.player_leds = Self::player_leds(syspath);
 
         if !gamepad.is_gamepad() {
             log!(
@@ -641,35 +803,63 @@ impl Gamepad {
             gamepad.axes_info
         );
 
+        gamepad.capture = Capture::from_env(&desc, &gamepad.devpath);
+
         Some(gamepad)
     }
 
+    /// Recreates gamepad from capture.
+    fn replayed(devpath: String, desc: &Descriptor, replay: Replay) -> Option<Gamepad> {
+        let mut gamepad = Gamepad::new(-1, devpath, desc);
+        // There is no device that could play force feedback effects.
+        gamepad.ff_supported = false;
+        gamepad.replay = Some(replay);
+
+        if gamepad.is_gamepad() {
+            Some(gamepad)
+        } else {
+            None
+        }
+    }
+
+    fn new(fd: RawFd, devpath: String, desc: &Descriptor) -> Gamepad {
+        let mut gamepad = Gamepad {
+            fd,
+            axes_info: AxesInfo::new(desc),
+            ff_supported: false,
+            devpath,
+            name: desc.name.clone(),
+            uniq: desc.uniq.clone(),
+            uuid: create_uuid(desc.input_id),
+            vendor_id: desc.input_id.vendor,
+            product_id: desc.input_id.product,
+            bt_capacity_fd: -1,
+            bt_status_fd: -1,
+            player_leds: Vec::new(),
+            axes_values: VecMap::new(),
+            buttons_values: VecMap::new(),
+            events: Vec::new(),
+            pending: VecDeque::new(),
+            replay: None,
+            capture: None,
+            axes: Vec::new(),
+            buttons: Vec::new(),
+            is_connected: true,
+        };
+
+        gamepad.collect_axes_and_buttons(desc);
+
+        gamepad
+    }
+
     fn register_fd(&self, epoll: &Epoll, data: u64) -> Result<(), Errno> {
         let fd = unsafe { BorrowedFd::borrow_raw(self.fd) };
         epoll.add(fd, EpollEvent::new(EpollFlags::EPOLLIN, data))
     }
 
-    fn collect_axes_and_buttons(&mut self) {
-        let mut key_bits = [0u8; (KEY_MAX / 8) as usize + 1];
-        let mut abs_bits = [0u8; (ABS_MAX / 8) as usize + 1];
-
-        unsafe {
-            ioctl::eviocgbit(
-                self.fd,
-                u32::from(EV_KEY),
-                key_bits.len() as i32,
-                key_bits.as_mut_ptr(),
-            );
-            ioctl::eviocgbit(
-                self.fd,
-                u32::from(EV_ABS),
-                abs_bits.len() as i32,
-                abs_bits.as_mut_ptr(),
-            );
-        }
-
-        self.buttons = Self::find_buttons(&key_bits, false);
-        self.axes = Self::find_axes(&abs_bits);
+    fn collect_axes_and_buttons(&mut self, desc: &Descriptor) {
+        self.buttons = Self::find_buttons(&desc.key_bits, false);
+        self.axes = Self::find_axes(&desc.abs_bits);
     }
 
     fn get_name(fd: i32) -> Option<String> {
@@ -687,37 +877,40 @@ impl Gamepad {
         }
     }
 
-    fn get_input_id(fd: i32) -> Option<ioctl::input_id> {
+    fn get_uniq(fd: i32) -> Option<String> {
         unsafe {
-            let mut iid = MaybeUninit::<ioctl::input_id>::uninit();
-            if ioctl::eviocgid(fd, iid.as_mut_ptr()).is_err() {
+            let mut uniqbuff: [MaybeUninit<u8>; 128] = MaybeUninit::uninit().assume_init();
+            if ioctl::eviocguniq(fd, &mut uniqbuff).is_err() {
                 return None;
             }
 
-            Some(iid.assume_init())
+            let uniq = CStr::from_ptr(uniqbuff.as_ptr() as *const c_char).to_string_lossy();
+            if uniq.is_empty() {
+                None
+            } else {
+                Some(uniq.into_owned())
+            }
         }
     }
 
-    fn test_ff(fd: i32) -> bool {
+    fn get_input_id(fd: i32) -> Option<input_id> {
         unsafe {
-            let mut ff_bits = [0u8; (FF_MAX / 8) as usize + 1];
-            if ioctl::eviocgbit(
-                fd,
-                u32::from(EV_FF),
-                ff_bits.len() as i32,
-                ff_bits.as_mut_ptr(),
-            ) >= 0
-            {
-                utils::test_bit(FF_SQUARE, &ff_bits)
-                    && utils::test_bit(FF_TRIANGLE, &ff_bits)
-                    && utils::test_bit(FF_SINE, &ff_bits)
-                    && utils::test_bit(FF_GAIN, &ff_bits)
-            } else {
-                false
+            let mut iid = MaybeUninit::<input_id>::uninit();
+            if ioctl::eviocgid(fd, iid.as_mut_ptr()).is_err() {
+                return None;
             }
+
+            Some(iid.assume_init())
         }
     }
 
+    fn test_ff(ff_bits: &[u8]) -> bool {
+        utils::test_bit(FF_SQUARE, ff_bits)
+            && utils::test_bit(FF_TRIANGLE, ff_bits)
+            && utils::test_bit(FF_SINE, ff_bits)
+            && utils::test_bit(FF_GAIN, ff_bits)
+    }
+
     fn is_gamepad(&self) -> bool {
         // TODO: improve it (for example check for buttons in range)
         !self.buttons.is_empty() && self.axes.len() >= 2
@@ -784,15 +977,36 @@ impl Gamepad {
         (-1, -1)
     }
 
+    fn player_leds(syspath: &Path) -> Vec<PathBuf> {
+        use std::fs;
+
+        // HID drivers that support player indicators (hid-playstation, hid-nintendo, …) register
+        // them as LED class devices named "<device>:<color>:player-<n>".
+        let mut leds = Vec::new();
+        if let Ok(read_dir) = fs::read_dir(syspath.join("device/device/leds")) {
+            for entry in read_dir.flatten() {
+                let n = entry
+                    .file_name()
+                    .to_str()
+                    .and_then(|name| name.rsplit_once(":player-"))
+                    .and_then(|(_, n)| n.parse::<u8>().ok());
+
+                if let Some(n) = n {
+                    leds.push((n, entry.path().join("brightness")));
+                }
+            }
+        }
+        leds.sort();
+
+        leds.into_iter().map(|(_, path)| path).collect()
+    }
+
     fn event(&mut self) -> Option<(EventType, SystemTime)> {
         let mut skip = false;
         // Skip all unknown events and return Option on first know event or when there is no more
         // events to read. Returning None on unknown event breaks iterators.
         loop {
-            let event = match self.next_event() {
-                Some(e) => e,
-                None => return None,
-            };
+            let event = self.next_event()?;
 
             if skip {
                 if event.type_ == EV_SYN && event.code == SYN_REPORT {
@@ -835,8 +1049,28 @@ impl Gamepad {
     }
 
     fn next_event(&mut self) -> Option<input_event> {
-        if !self.events.is_empty() {
-            self.events.pop()
+        if let Some(ev) = self.events.pop() {
+            return Some(ev);
+        }
+
+        let ev = match self.replay {
+            Some(ref mut replay) => replay.next_event(),
+            None => self.read_event(),
+        }?;
+
+        if let Some(ref mut capture) = self.capture {
+            if let Err(e) = capture.event(&ev) {
+                error!("Failed to write capture of {}: {}", self.devpath, e);
+                self.capture = None;
+            }
+        }
+
+        Some(ev)
+    }
+
+    fn read_event(&mut self) -> Option<input_event> {
+        if let Some(ev) = self.pending.pop_front() {
+            Some(ev)
         } else {
             unsafe {
                 let mut event_buf: [MaybeUninit<ioctl::input_event>; 12] =
@@ -857,8 +1091,8 @@ impl Gamepad {
                 } else {
                     let n = n as usize / size;
                     trace!("Got {} new events", n);
-                    for ev in event_buf[1..n].iter().rev() {
-                        self.events.push(ev.assume_init());
+                    for ev in event_buf[1..n].iter() {
+                        self.pending.push_back(ev.assume_init());
                     }
 
                     Some(event_buf[0].assume_init())
@@ -867,35 +1101,52 @@ impl Gamepad {
         }
     }
 
-    fn compare_state(&mut self) {
-        let mut absinfo = input_absinfo::default();
-        for axis in self.axes.iter().cloned() {
-            let value = unsafe {
-                ioctl::eviocgabs(self.fd, u32::from(axis.code), &mut absinfo);
-                absinfo.value
-            };
+    /// Reads current values of axes and state of all keys.
+    fn current_state(&mut self) -> (Vec<(u16, i32)>, [u8; KEY_MAX as usize / 8 + 1]) {
+        let mut keys = [0u8; KEY_MAX as usize / 8 + 1];
+        let mut values = Vec::with_capacity(self.axes.len());
 
-            if self
-                .axes_values
-                .get(axis.code as usize)
-                .cloned()
-                .unwrap_or(0)
-                != value
-            {
+        if let Some(ref mut replay) = self.replay {
+            for axis in &self.axes {
+                values.push((axis.code, replay.abs_value(axis.code)));
+            }
+            replay.key_state(&mut keys);
+        } else {
+            let mut absinfo = input_absinfo::default();
+            for axis in &self.axes {
+                unsafe {
+                    ioctl::eviocgabs(self.fd, u32::from(axis.code), &mut absinfo);
+                }
+                values.push((axis.code, absinfo.value));
+            }
+            unsafe {
+                let _ = ioctl::eviocgkey(self.fd, &mut keys);
+            }
+        }
+
+        if let Some(ref mut capture) = self.capture {
+            if let Err(e) = capture.state(&keys, &values) {
+                error!("Failed to write capture of {}: {}", self.devpath, e);
+                self.capture = None;
+            }
+        }
+
+        (values, keys)
+    }
+
+    fn compare_state(&mut self) {
+        let (values, buf) = self.current_state();
+        for (code, value) in values {
+            if self.axes_values.get(code as usize).cloned().unwrap_or(0) != value {
                 self.events.push(input_event {
                     type_: EV_ABS,
-                    code: axis.code,
+                    code,
                     value,
                     ..Default::default()
                 });
             }
         }
 
-        let mut buf = [0u8; KEY_MAX as usize / 8 + 1];
-        unsafe {
-            let _ = ioctl::eviocgkey(self.fd, &mut buf);
-        }
-
         for btn in self.buttons.iter().cloned() {
             let val = utils::test_bit(btn.code, &buf);
             if self
@@ -923,6 +1174,8 @@ impl Gamepad {
         }
         self.fd = -2;
         self.devpath.clear();
+        self.player_leds.clear();
+        self.capture = None;
         self.is_connected = false;
     }
 
@@ -988,6 +1241,23 @@ impl Gamepad {
         self.ff_supported
     }
 
+    pub fn set_player_leds(&self, player: Option<u8>) -> bool {
+        if self.player_leds.is_empty() {
+            return false;
+        }
+
+        let mut ok = true;
+        for (i, path) in self.player_leds.iter().enumerate() {
+            let lit = player == Some(i as u8 + 1);
+            if let Err(e) = std::fs::write(path, if lit { "1" } else { "0" }) {
+                debug!("Failed to set player LED {:?}: {}", path, e);
+                ok = false;
+            }
+        }
+
+        ok
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -996,6 +1266,10 @@ impl Gamepad {
         self.uuid
     }
 
+    pub fn uniq(&self) -> Option<&str> {
+        self.uniq.as_deref()
+    }
+
     pub fn vendor_id(&self) -> Option<u16> {
         Some(self.vendor_id)
     }
@@ -1146,17 +1420,17 @@ fn errno_to_platform_error(errno: Errno, ctx: &'static str) -> PlatformError {
     PlatformError::Other(Box::new(Error::Errno(errno, ctx)))
 }
 
-const KEY_MAX: u16 = 0x2ff;
+pub(super) const KEY_MAX: u16 = 0x2ff;
 #[allow(dead_code)]
 const EV_MAX: u16 = 0x1f;
 const EV_SYN: u16 = 0x00;
-const EV_KEY: u16 = 0x01;
+pub(super) const EV_KEY: u16 = 0x01;
 const EV_REL: u16 = 0x02;
-const EV_ABS: u16 = 0x03;
+pub(super) const EV_ABS: u16 = 0x03;
 const EV_MSC: u16 = 0x04;
 const EV_SW: u16 = 0x05;
-const ABS_MAX: u16 = 0x3f;
-const EV_FF: u16 = 0x15;
+pub(super) const ABS_MAX: u16 = 0x3f;
+pub(super) const EV_FF: u16 = 0x15;
 
 const SYN_REPORT: u16 = 0x00;
 const SYN_DROPPED: u16 = 0x03;
@@ -1338,8 +1612,10 @@ pub mod native_ev_codes {
 
 #[cfg(test)]
 mod tests {
+    use super::super::capture::Replay;
     use super::super::ioctl;
-    use super::create_uuid;
+    use super::{create_uuid, EvCode, Gamepad, EV_ABS, EV_KEY};
+    use crate::EventType;
     use uuid::Uuid;
 
     #[test]
@@ -1353,4 +1629,50 @@ mod tests {
         });
         assert_eq!(x, y);
     }
+
+    #[test]
+    fn replay_syn_dropped() {
+        let capture = "\
+N: Test Pad
+I: 0003 045e 028e 0114
+B: 01 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 03
+B: 03 03
+A: 00 -128 127 0 0 0
+A: 01 -128 127 0 0 0
+E: 1.000000 0001 0130 1
+E: 1.000000 0000 0000 0
+E: 1.000100 0000 0003 0
+E: 1.000200 0003 0000 50
+E: 1.000200 0000 0000 0
+K: 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 02
+X: 00 50
+X: 01 -20
+E: 1.000300 0003 0001 -30
+E: 1.000300 0000 0000 0
+";
+        let (desc, replay) = Replay::parse(capture.as_bytes()).unwrap();
+        let mut gamepad = Gamepad::replayed("test".to_owned(), &desc, replay).unwrap();
+        assert_eq!(gamepad.name(), "Test Pad");
+        assert_eq!(gamepad.axis_info(EvCode::new(EV_ABS, 1)).unwrap().min, -128);
+
+        let key = |code| crate::EvCode(EvCode::new(EV_KEY, code));
+        let abs = |code| crate::EvCode(EvCode::new(EV_ABS, code));
+        let mut events = Vec::new();
+        while let Some((ev, _)) = gamepad.event() {
+            events.push(ev);
+        }
+
+        assert_eq!(
+            events,
+            [
+                EventType::ButtonPressed(key(0x130)),
+                // Generated by `compare_state()` from state captured after SYN_DROPPED.
+                EventType::ButtonPressed(key(0x131)),
+                EventType::ButtonReleased(key(0x130)),
+                EventType::AxisValueChanged(-20, abs(1)),
+                EventType::AxisValueChanged(50, abs(0)),
+                EventType::AxisValueChanged(-30, abs(1)),
+            ]
+        );
+    }
 }
@@ -21,6 +21,7 @@ ioctl_read!(eviocgid, b'E', 0x02, /*struct*/ input_id);
 ioctl_write_int!(eviocrmff, b'E', 0x81);
 ioctl_write_ptr!(eviocsff, b'E', 0x80, ff_effect);
 ioctl_read_buf!(eviocgname, b'E', 0x06, MaybeUninit<u8>);
+ioctl_read_buf!(eviocguniq, b'E', 0x08, MaybeUninit<u8>);
 ioctl_read_buf!(eviocgkey, b'E', 0x18, u8);
 
 pub unsafe fn eviocgbit(fd: libc::c_int, ev: u32, len: libc::c_int, buf: *mut u8) -> libc::c_int {
@@ -65,7 +66,7 @@ impl ::std::fmt::Debug for input_event {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 #[repr(C)]
 pub struct input_id {
     pub bustype: u16,
@@ -99,6 +100,7 @@ pub struct ff_trigger {
     pub interval: u16,
 }
 
+#[allow(dead_code)]
 #[derive(Copy, Clone)]
 #[repr(C)]
 pub struct ff_envelope {
@@ -108,6 +110,7 @@ pub struct ff_envelope {
     pub fade_level: u16,
 }
 
+#[allow(dead_code)]
 #[derive(Copy, Clone)]
 #[repr(C)]
 pub struct ff_constant_effect {
@@ -115,6 +118,7 @@ pub struct ff_constant_effect {
     pub envelope: ff_envelope,
 }
 
+#[allow(dead_code)]
 #[derive(Copy, Clone)]
 #[repr(C)]
 pub struct ff_ramp_effect {
@@ -123,6 +127,7 @@ pub struct ff_ramp_effect {
     pub envelope: ff_envelope,
 }
 
+#[allow(dead_code)]
 #[derive(Copy, Clone)]
 #[repr(C)]
 pub struct ff_condition_effect {
@@ -136,6 +141,7 @@ pub struct ff_condition_effect {
     pub center: i16,
 }
 
+#[allow(dead_code)]
 #[derive(Copy, Clone)]
 #[repr(C)]
 pub struct ff_periodic_effect {
@@ -5,6 +5,7 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 // Copyright 2016 GilRs Developers
+mod capture;
 mod ff;
 mod gamepad;
 mod ioctl;
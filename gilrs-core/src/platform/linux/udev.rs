@@ -229,15 +229,8 @@ impl Monitor {
         }
     }
 
-    pub fn wait_hotplug_available(&self) -> bool {
-        unsafe {
-            let mut fds = c::pollfd {
-                fd: ud::udev_monitor_get_fd(self.0),
-                events: c::POLLIN,
-                revents: 0,
-            };
-            (c::poll(&mut fds, 1, -1) == 1) && (fds.revents & c::POLLIN != 0)
-        }
+    pub fn fd(&self) -> c::c_int {
+        unsafe { ud::udev_monitor_get_fd(self.0) }
     }
 
     pub fn device(&self) -> Device {
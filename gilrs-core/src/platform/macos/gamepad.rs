@@ -311,6 +311,10 @@ impl Gamepad {
         &self.name
     }
 
+    pub fn uniq(&self) -> Option<&str> {
+        None
+    }
+
     pub fn vendor_id(&self) -> Option<u16> {
         self.vendor
     }
@@ -327,6 +331,10 @@ impl Gamepad {
         PowerInfo::Unknown
     }
 
+    pub fn set_player_leds(&self, _player: Option<u8>) -> bool {
+        false
+    }
+
     pub fn is_ff_supported(&self) -> bool {
         false
     }
@@ -21,7 +21,7 @@
 
 pub use self::platform::*;
 
-#[cfg(any(target_os = "linux"))]
+#[cfg(target_os = "linux")]
 #[path = "linux/mod.rs"]
 mod platform;
 
@@ -51,7 +51,7 @@ mod platform;
 mod platform;
 
 #[cfg(all(
-    not(any(target_os = "linux")),
+    not(target_os = "linux"),
     not(target_os = "macos"),
     not(target_os = "windows"),
     not(target_arch = "wasm32")
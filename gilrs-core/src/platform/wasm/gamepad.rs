@@ -308,6 +308,10 @@ impl Gamepad {
         self.uuid
     }
 
+    pub fn uniq(&self) -> Option<&str> {
+        None
+    }
+
     pub fn vendor_id(&self) -> Option<u16> {
         self.vendor
     }
@@ -324,6 +328,10 @@ impl Gamepad {
         PowerInfo::Unknown
     }
 
+    pub fn set_player_leds(&self, _player: Option<u8>) -> bool {
+        false
+    }
+
     pub fn is_ff_supported(&self) -> bool {
         false
     }
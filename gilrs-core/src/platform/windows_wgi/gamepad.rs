@@ -580,6 +580,10 @@ impl Gamepad {
         self.uuid
     }
 
+    pub fn uniq(&self) -> Option<&str> {
+        None
+    }
+
     pub fn vendor_id(&self) -> Option<u16> {
         self.raw_game_controller.HardwareVendorId().ok()
     }
@@ -623,6 +627,10 @@ impl Gamepad {
         Ok(power_info)
     }
 
+    pub fn set_player_leds(&self, _player: Option<u8>) -> bool {
+        false
+    }
+
     pub fn is_ff_supported(&self) -> bool {
         self.wgi_gamepad.is_some()
             && self
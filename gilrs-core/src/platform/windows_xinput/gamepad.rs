@@ -422,6 +422,10 @@ impl Gamepad {
         self.uuid
     }
 
+    pub fn uniq(&self) -> Option<&str> {
+        None
+    }
+
     pub fn vendor_id(&self) -> Option<u16> {
         None
     }
@@ -466,6 +470,10 @@ impl Gamepad {
         }
     }
 
+    pub fn set_player_leds(&self, _player: Option<u8>) -> bool {
+        false
+    }
+
     pub fn is_ff_supported(&self) -> bool {
         true
     }
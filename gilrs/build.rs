@@ -17,6 +17,7 @@ const PATH_SEPARATOR: &str = "backslash";
 const PATH_SEPARATOR: &str = "slash";
 
 fn main() {
+    println!(r#"cargo:rustc-check-cfg=cfg(path_separator, values("backslash", "slash"))"#);
     println!(r#"cargo:rustc-cfg=path_separator="{}""#, PATH_SEPARATOR);
 
     let out_dir = env::var("OUT_DIR").unwrap();
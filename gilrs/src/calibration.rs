@@ -0,0 +1,277 @@
+// Copyright 2016-2018 Mateusz Sieczko and other GilRs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Calibration of axes.
+//!
+//! Drivers report range of every axis, but worn or cheap gamepads often don't reach it or don't
+//! rest in its center. [`Calibration`] stores range and center observed for each axis. When
+//! calibration is set for gamepad's UUID with
+//! [`Gilrs::set_calibration()`](../struct.Gilrs.html#method.set_calibration), raw values are
+//! mapped from observed range to driver's range before they are normalised. Calibration of single
+//! device can be set for its [`uniq`](../struct.Gamepad.html#method.uniq) with
+//! [`Gilrs::set_device_calibration()`](../struct.Gilrs.html#method.set_device_calibration) and
+//! is used instead of the one set for UUID.
+//!
+//! Calibration can be created by observing raw values during calibration session:
+//!
+//! - Guided session starts with [`CalibrationStep::Center`]. User is asked to not touch the
+//!   gamepad and axes values are averaged to find center. Then, after switching to
+//!   [`CalibrationStep::Range`], user is asked to move all sticks and triggers to their limits.
+//! - Automatic session starts directly with `CalibrationStep::Range`. Center of axis is assumed
+//!   to be in the middle of observed range.
+//!
+//! ```
+//! use gilrs::Gilrs;
+//! use gilrs::calibration::CalibrationStep;
+//!
+//! let mut gilrs = Gilrs::new().unwrap();
+//! # let id = gilrs.gamepads().next().map(|(id, _)| id);
+//! # if let Some(id) = id {
+//! gilrs.start_calibration(id, CalibrationStep::Center);
+//! // "Don't touch the gamepad", process events for a second…
+//! while gilrs.next_event().is_some() {}
+//!
+//! gilrs.set_calibration_step(id, CalibrationStep::Range);
+//! // "Rotate sticks and press triggers", process events until user is done…
+//! while gilrs.next_event().is_some() {}
+//!
+//! if let Some(calibration) = gilrs.finish_calibration(id) {
+//!     // Calibration is now used for this device (or all gamepads with the same UUID if OS
+//!     // doesn't report serial number). Save it for later.
+//!     println!("{:?}", calibration);
+//! }
+//! # }
+//! ```
+
+use crate::ev::Code;
+
+use gilrs_core::AxisInfo;
+
+#[cfg(feature = "serde-serialize")]
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+/// Observed range and center of an axis, in raw values.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct AxisCalibration {
+    pub code: Code,
+    pub min: i32,
+    pub max: i32,
+    /// Raw value at rest. If `None`, axis rests in the middle of `min` and `max`.
+    pub center: Option<i32>,
+}
+
+impl AxisCalibration {
+    /// Maps `val` from calibrated range to range from `info`.
+    pub(crate) fn apply(&self, val: i32, info: &AxisInfo, use_center: bool) -> i32 {
+        let imin = info.min as f64;
+        let imax = info.max as f64;
+        // Same value that `axis_value()` maps to 0.0.
+        let icenter = (info.min as i64 + (info.max as i64 - info.min as i64) / 2) as f64;
+        let val = val as f64;
+
+        let out = match self.center {
+            Some(c) if use_center && val < c as f64 => lerp(val, self.min, c, imin, icenter),
+            Some(c) if use_center => lerp(val, c, self.max, icenter, imax),
+            _ => lerp(val, self.min, self.max, imin, imax),
+        };
+
+        out.round().clamp(imin, imax) as i32
+    }
+}
+
+fn lerp(val: f64, from_min: i32, from_max: i32, to_min: f64, to_max: f64) -> f64 {
+    let range = from_max as f64 - from_min as f64;
+    if range <= 0.0 {
+        return to_min;
+    }
+
+    to_min + (val - from_min as f64) / range * (to_max - to_min)
+}
+
+/// Calibration of all axes of a gamepad.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct Calibration {
+    axes: Vec<AxisCalibration>,
+}
+
+impl Calibration {
+    /// Creates calibration without any axes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns calibration of axis with `code`.
+    pub fn axis(&self, code: Code) -> Option<&AxisCalibration> {
+        self.axes.iter().find(|a| a.code == code)
+    }
+
+    /// Adds or replaces calibration of axis.
+    pub fn set_axis(&mut self, axis: AxisCalibration) {
+        match self.axes.iter_mut().find(|a| a.code == axis.code) {
+            Some(a) => *a = axis,
+            None => self.axes.push(axis),
+        }
+    }
+
+    /// Removes calibration of axis with `code`.
+    pub fn remove_axis(&mut self, code: Code) {
+        self.axes.retain(|a| a.code != code);
+    }
+
+    /// Returns iterator over all calibrated axes.
+    pub fn axes(&self) -> impl Iterator<Item = &AxisCalibration> {
+        self.axes.iter()
+    }
+}
+
+/// Step of calibration session.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum CalibrationStep {
+    /// Axes are at rest. Observed values are averaged to find center.
+    Center,
+    /// Axes are moved to their limits.
+    Range,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Observed {
+    min: i32,
+    max: i32,
+    center_sum: i64,
+    center_samples: u32,
+}
+
+/// Raw values observed during calibration session.
+#[derive(Clone, Debug)]
+pub(crate) struct Calibrator {
+    step: CalibrationStep,
+    axes: HashMap<Code, Observed>,
+}
+
+impl Calibrator {
+    pub fn new(step: CalibrationStep) -> Self {
+        Calibrator {
+            step,
+            axes: HashMap::new(),
+        }
+    }
+
+    pub fn set_step(&mut self, step: CalibrationStep) {
+        self.step = step;
+    }
+
+    pub fn observe(&mut self, code: Code, val: i32) {
+        let observed = self.axes.entry(code).or_insert(Observed {
+            min: val,
+            max: val,
+            center_sum: 0,
+            center_samples: 0,
+        });
+
+        observed.min = observed.min.min(val);
+        observed.max = observed.max.max(val);
+        if self.step == CalibrationStep::Center {
+            observed.center_sum += val as i64;
+            observed.center_samples += 1;
+        }
+    }
+
+    /// Creates calibration for axes that moved. Center is used only if it's inside of observed
+    /// range.
+    pub fn finish(&self) -> Calibration {
+        let mut calibration = Calibration::new();
+
+        for (&code, observed) in &self.axes {
+            if observed.min >= observed.max {
+                continue;
+            }
+
+            let center = Some(observed)
+                .filter(|o| o.center_samples > 0)
+                .map(|o| (o.center_sum / o.center_samples as i64) as i32)
+                .filter(|&c| c > observed.min && c < observed.max);
+
+            calibration.set_axis(AxisCalibration {
+                code,
+                min: observed.min,
+                max: observed.max,
+                center,
+            });
+        }
+
+        calibration.axes.sort_by_key(|a| a.code.into_u32());
+
+        calibration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AxisCalibration, CalibrationStep, Calibrator};
+    use crate::ev::Code;
+    use gilrs_core::{native_ev_codes as nec, AxisInfo};
+
+    const INFO: AxisInfo = AxisInfo {
+        min: 0,
+        max: 255,
+        deadzone: None,
+    };
+
+    #[test]
+    fn apply() {
+        let cal = AxisCalibration {
+            code: Code(nec::AXIS_LSTICKX),
+            min: 20,
+            max: 220,
+            center: Some(140),
+        };
+
+        assert_eq!(cal.apply(20, &INFO, true), 0);
+        assert_eq!(cal.apply(140, &INFO, true), 127);
+        assert_eq!(cal.apply(220, &INFO, true), 255);
+        assert_eq!(cal.apply(250, &INFO, true), 255);
+        assert_eq!(cal.apply(80, &INFO, true), 64);
+        // Buttons ignore center.
+        assert_eq!(cal.apply(120, &INFO, false), 128);
+    }
+
+    #[test]
+    fn calibrator() {
+        let x = Code(nec::AXIS_LSTICKX);
+        let y = Code(nec::AXIS_LSTICKY);
+        let z = Code(nec::AXIS_LT2);
+
+        let mut calibrator = Calibrator::new(CalibrationStep::Center);
+        calibrator.observe(x, 139);
+        calibrator.observe(x, 141);
+        calibrator.observe(y, 127);
+        calibrator.set_step(CalibrationStep::Range);
+        for val in [20, 220, 140] {
+            calibrator.observe(x, val);
+        }
+        calibrator.observe(z, 10);
+        calibrator.observe(z, 240);
+
+        let cal = calibrator.finish();
+        assert_eq!(
+            cal.axis(x),
+            Some(&AxisCalibration {
+                code: x,
+                min: 20,
+                max: 220,
+                center: Some(140),
+            })
+        );
+        // Never moved.
+        assert_eq!(cal.axis(y), None);
+        assert_eq!(cal.axis(z).and_then(|a| a.center), None);
+    }
+}
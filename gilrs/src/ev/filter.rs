@@ -7,494 +7,1643 @@
 
 //! Alter events in various ways.
 //!
-//! This modules contains "event filters" that can change, drop or create new events. To use them,
-//! import `Filter` trait and call `filter()` function on `Option<Event>`. Because `filter` also
-//! returns `Option<Event>` you can combine multiple filters by using `filter()` function on
-//! returned event.
+//! This modules contains "event filters" that can change, drop or create new events. The easiest
+//! way to use them is to install them with
+//! [`GilrsBuilder::filters()`](../../struct.GilrsBuilder.html#method.filters). `Gilrs` will then
+//! run them, in order, on every event returned from `next_event()` and update cached state
+//! afterwards. By default, [`default_filters()`] are used.
 //!
 //! Filters in this modules have public fields that can be used to configure their behaviour. You
-//! can also create them with default values using `new()` method. If filter is not configurable,
-//! it is implemented as function (for example `deadzone()`).
+//! can also create them with default values using `new()` or `default()` method.
 //!
 //! # Example
 //!
 //! ```
-//! use gilrs::{GilrsBuilder, Filter};
-//! use gilrs::ev::filter::{Jitter, Repeat, deadzone};
+//! use gilrs::GilrsBuilder;
+//! use gilrs::ev::filter::{Deadzone, Jitter, Repeat};
 //!
-//! let mut gilrs = GilrsBuilder::new().with_default_filters(false).build().unwrap();
-//! let jitter = Jitter { threshold: 0.02 };
-//! let repeat = Repeat::new();
+//! // Default filters without `AxisDpadToButton`, with custom `Jitter` and with `Repeat`.
+//! let mut gilrs = GilrsBuilder::new()
+//!     .with_default_filters(false)
+//!     .add_filter(Jitter { threshold: 0.02 })
+//!     .add_filter(Deadzone::new())
+//!     .add_filter(Repeat::new())
+//!     .build()
+//!     .unwrap();
 //!
 //! // Event loop
 //! loop {
-//!     while let Some(event) = gilrs
-//!         .next_event()
-//!         .filter_ev(&jitter, &mut gilrs)
-//!         .filter_ev(&deadzone, &mut gilrs)
-//!         .filter_ev(&repeat, &mut gilrs)
-//!     {
-//!         gilrs.update(&event);
+//!     while let Some(event) = gilrs.next_event() {
 //!         println!("{:?}", event);
 //!     }
 //!     # break;
 //! }
 //! ```
+//!
 //! # Implementing custom filters
 //!
-//! If you want to implement your own filters, you will have to implement `FilterFn` trait.
-//! **Do not return `None` if you got `Some(event)`**. If you want to discard an event, uses
-//! `EventType::Dropped`. Returning `None` means that there are no more events to process and
-//! will end `while let` loop.
+//! If you want to implement your own filters, you will have to implement `EventFilter` trait. For
+//! every event, filter returns [`FilterResult`] which tells what to do with it: pass it unchanged,
+//! drop it, replace it with another event or emit any number of events in its place. Events
+//! emitted by a filter are passed, in order, to the next filters.
+//!
+//! Filters can also create events on their own, when there are no new events from gamepads. To do
+//! this, implement `EventFilter::poll()`.
 //!
 //! ## Example
 //!
 //! Example implementations of filter that will drop all events with `Unknown` axis or button.
 //!
 //! ```
-//! use gilrs::ev::filter::FilterFn;
-//! use gilrs::{Gilrs, Event, EventType, Button, Axis, Filter};
+//! use gilrs::ev::filter::{EventFilter, FilterResult};
+//! use gilrs::{Gilrs, GilrsBuilder, Event, EventType, Button, Axis};
 //!
 //! struct UnknownSlayer;
 //!
-//! impl FilterFn for UnknownSlayer {
-//!     fn filter(&self, ev: Option<Event>, _gilrs: &mut Gilrs) -> Option<Event> {
-//!         match ev {
-//!             Some(Event { event: EventType::ButtonPressed(Button::Unknown, ..), id, .. })
-//!             | Some(Event { event: EventType::ButtonReleased(Button::Unknown, ..), id, .. })
-//!             | Some(Event { event: EventType::AxisChanged(Axis::Unknown, ..), id, .. })
-//!             => Some(Event::new(id, EventType::Dropped)),
-//!             _ => ev,
+//! impl EventFilter for UnknownSlayer {
+//!     fn filter_event(&mut self, ev: Event, _gilrs: &mut Gilrs) -> FilterResult {
+//!         match ev.event {
+//!             EventType::ButtonPressed(Button::Unknown, ..)
+//!             | EventType::ButtonReleased(Button::Unknown, ..)
+//!             | EventType::AxisChanged(Axis::Unknown, ..) => FilterResult::Drop,
+//!             _ => FilterResult::Pass(ev),
 //!         }
 //!     }
 //! }
+//!
+//! let gilrs = GilrsBuilder::new().add_filter(UnknownSlayer).build().unwrap();
+//! ```
+//!
+//! `EventFilter` is also implemented for all `FnMut(Event, &mut Gilrs) -> FilterResult`, so above
+//! example could be simplified to passing closure to `add_filter()` function.
+//!
+//! # `FilterFn` compatibility
+//!
+//! Older versions of GilRs used `FilterFn` trait, which works on `Option<Event>` and uses
+//! `EventType::Dropped` to discard events. It is still supported: all filters in this module
+//! implement it and `Filter::filter_ev()` can be used to apply them to events outside of `Gilrs`.
+//! In this case, you have to update cached state yourself. Filters that emit more than one event
+//! will add remaining events to `Gilrs`'s queue with `Gilrs::insert_event()`.
+//!
+//! ```
+//! use gilrs::{GilrsBuilder, Filter};
+//! use gilrs::ev::filter::{Jitter, Repeat, deadzone};
+//!
+//! let mut gilrs = GilrsBuilder::new().with_default_filters(false).build().unwrap();
+//! let jitter = Jitter { threshold: 0.02 };
+//! let repeat = Repeat::new();
+//!
+//! // Event loop
+//! loop {
+//!     while let Some(event) = gilrs
+//!         .next_event()
+//!         .filter_ev(&jitter, &mut gilrs)
+//!         .filter_ev(&deadzone, &mut gilrs)
+//!         .filter_ev(&repeat, &mut gilrs)
+//!     {
+//!         gilrs.update(&event);
+//!         println!("{:?}", event);
+//!     }
+//!     # break;
+//! }
 //! ```
 //!
-//! `FilterFn` is also implemented for all `Fn(Option<Event>, &Gilrs) -> Option<Event>`, so above
-//! example could be simplified to passing closure to `filter()` function.
+//! Existing `FilterFn` implementations can be installed in `Gilrs` by wrapping them in
+//! [`Compat`]. **Do not return `None` if you got `Some(event)`** from such filter. If you want to
+//! discard an event, use `EventType::Dropped`. Returning `None` means that there are no more events
+//! to process.
 
-use crate::ev::{Axis, AxisOrBtn, Button, Code, Event, EventType};
-use crate::gamepad::{Gamepad, Gilrs};
+use crate::ev::state::ButtonData;
+use crate::ev::{Axis, AxisOrBtn, Button, Chord, Code, Event, EventType};
+use crate::gamepad::{Gamepad, GamepadId, Gilrs};
 use crate::utils;
 
-use std::time::Duration;
+pub use smallvec::{smallvec, SmallVec};
+
+#[cfg(feature = "serde-serialize")]
+use serde::{Deserialize, Serialize};
+
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Debug, Formatter};
+use std::time::{Duration, SystemTime};
+
+/// Events emitted by a filter in place of one event.
+pub type EventVec = SmallVec<[Event; 4]>;
+
+/// What filter did with an event.
+#[derive(Clone, PartialEq, Debug)]
+pub enum FilterResult {
+    /// Event is passed unchanged.
+    Pass(Event),
+    /// Event is discarded.
+    Drop,
+    /// Event is replaced with another one.
+    Replace(Event),
+    /// Event is replaced with all events in the vector, in order. Empty vector is the same as
+    /// `Drop`.
+    Emit(EventVec),
+}
+
+impl FilterResult {
+    /// Creates result from events emitted in place of `ev`.
+    fn from_events(ev: Event, mut events: EventVec) -> Self {
+        match events.len() {
+            0 => FilterResult::Drop,
+            1 if events[0] == ev => FilterResult::Pass(ev),
+            1 => FilterResult::Replace(events.pop().unwrap()),
+            _ => FilterResult::Emit(events),
+        }
+    }
+}
+
+/// Event filter that can be installed in `Gilrs`.
+///
+/// See module level documentation for more info.
+pub trait EventFilter {
+    /// Filters single event.
+    fn filter_event(&mut self, ev: Event, gilrs: &mut Gilrs) -> FilterResult;
+
+    /// Called when there are no new events. Returned event is passed to filters installed after
+    /// this one. Default implementation returns `None`.
+    fn poll(&mut self, _gilrs: &mut Gilrs) -> Option<Event> {
+        None
+    }
+}
+
+impl<F> EventFilter for F
+where
+    F: FnMut(Event, &mut Gilrs) -> FilterResult,
+{
+    fn filter_event(&mut self, ev: Event, gilrs: &mut Gilrs) -> FilterResult {
+        self(ev, gilrs)
+    }
+}
+
+/// Allows using `FilterFn` as `EventFilter`.
+///
+/// Dropped events and `None` returned for an event are turned into `FilterResult::Drop`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Compat<F>(pub F);
+
+impl<F: FilterFn> EventFilter for Compat<F> {
+    fn filter_event(&mut self, ev: Event, gilrs: &mut Gilrs) -> FilterResult {
+        match self.0.filter(Some(ev), gilrs) {
+            Some(out) if out.is_dropped() => FilterResult::Drop,
+            Some(out) if out == ev => FilterResult::Pass(out),
+            Some(out) => FilterResult::Replace(out),
+            None => FilterResult::Drop,
+        }
+    }
+
+    fn poll(&mut self, gilrs: &mut Gilrs) -> Option<Event> {
+        self.0.filter(None, gilrs).filter(|ev| !ev.is_dropped())
+    }
+}
+
+/// Runs `EventFilter` with `FilterFn` semantics.
+fn compat_filter<F: EventFilter>(
+    filter: &mut F,
+    ev: Option<Event>,
+    gilrs: &mut Gilrs,
+) -> Option<Event> {
+    let ev = match ev {
+        Some(ev) => ev,
+        None => return filter.poll(gilrs),
+    };
+
+    Some(match filter.filter_event(ev, gilrs) {
+        FilterResult::Pass(ev) | FilterResult::Replace(ev) => ev,
+        FilterResult::Drop => ev.drop(),
+        FilterResult::Emit(events) => {
+            let mut events = events.into_iter();
+            let first = events.next().unwrap_or_else(|| ev.drop());
+            for ev in events {
+                gilrs.insert_event(ev);
+            }
+
+            first
+        }
+    })
+}
+
+/// Returns filters used by `Gilrs` by default: [`AxisDpadToButton`], [`Jitter`] and
+/// [`Deadzone`], in that order.
+pub fn default_filters() -> Vec<Box<dyn EventFilter + Send>> {
+    vec![
+        Box::new(AxisDpadToButton),
+        Box::new(Jitter::new()),
+        Box::new(Deadzone::new()),
+    ]
+}
+
+/// Filters installed in `Gilrs`.
+#[derive(Default)]
+pub(crate) struct FilterList(Vec<Box<dyn EventFilter + Send>>);
+
+impl FilterList {
+    pub fn push(&mut self, filter: Box<dyn EventFilter + Send>) {
+        self.0.push(filter);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Runs `ev` through all filters. If `ev` is `None`, filters are polled in order and first
+    /// polled event is passed to the rest of them.
+    pub fn apply(&mut self, ev: Option<Event>, gilrs: &mut Gilrs) -> EventVec {
+        let (mut events, start): (EventVec, _) = match ev {
+            Some(ev) => (smallvec![ev], 0),
+            None => {
+                let polled = self
+                    .0
+                    .iter_mut()
+                    .enumerate()
+                    .find_map(|(i, filter)| filter.poll(gilrs).map(|ev| (i + 1, ev)));
+                match polled {
+                    Some((start, ev)) => (smallvec![ev], start),
+                    None => return EventVec::new(),
+                }
+            }
+        };
+
+        for filter in &mut self.0[start..] {
+            let mut out = EventVec::new();
+            for ev in events {
+                match filter.filter_event(ev, gilrs) {
+                    FilterResult::Pass(ev) | FilterResult::Replace(ev) => out.push(ev),
+                    FilterResult::Drop => (),
+                    FilterResult::Emit(emitted) => out.extend(emitted),
+                }
+            }
+            out.retain(|ev| !ev.is_dropped());
+
+            events = out;
+            if events.is_empty() {
+                break;
+            }
+        }
+
+        events
+    }
+}
+
+impl FromIterator<Box<dyn EventFilter + Send>> for FilterList {
+    fn from_iter<I: IntoIterator<Item = Box<dyn EventFilter + Send>>>(iter: I) -> Self {
+        FilterList(iter.into_iter().collect())
+    }
+}
+
+impl Debug for FilterList {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "FilterList({} filters)", self.0.len())
+    }
+}
+
+/// Discard axis events that changed less than `threshold`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Jitter {
+    pub threshold: f32,
+}
+
+impl Jitter {
+    /// Creates new `Repeat` filter with threshold set to 0.01.
+    pub fn new() -> Self {
+        Jitter { threshold: 0.01 }
+    }
+}
+
+impl Default for Jitter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventFilter for Jitter {
+    fn filter_event(&mut self, ev: Event, gilrs: &mut Gilrs) -> FilterResult {
+        match ev.event {
+            EventType::AxisChanged(_, val, axis) => {
+                match gilrs.gamepad(ev.id).state().axis_data(axis) {
+                    Some(data) if val != 0.0 && (val - data.value()).abs() < self.threshold => {
+                        FilterResult::Drop
+                    }
+                    _ => FilterResult::Pass(ev),
+                }
+            }
+            _ => FilterResult::Pass(ev),
+        }
+    }
+}
+
+impl FilterFn for Jitter {
+    fn filter(&self, ev: Option<Event>, gilrs: &mut Gilrs) -> Option<Event> {
+        let mut filter = *self;
+        compat_filter(&mut filter, ev, gilrs)
+    }
+}
+
+/// Shape of dead zone used for sticks.
+///
+/// Axes that are not part of a stick and analog buttons always use axial dead zone.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum DeadzoneShape {
+    /// Each axis is handled separately. Makes it easy to move only along one axis, but diagonals
+    /// snap to cardinal directions near center.
+    Axial,
+    /// Stick position is ignored if its distance from center is smaller than inner dead zone.
+    /// Outside of it, values are not rescaled, so they jump from 0 to inner dead zone.
+    Radial,
+    /// Like `Radial`, but values outside of dead zone are rescaled to start from 0.
+    ScaledRadial,
+    /// Radial dead zone combined with axial one. Values are rescaled separately for each axis.
+    Hybrid,
+    /// Radial dead zone in center and axial dead zone that gets wider as other axis moves away
+    /// from center. Makes it easy to hold cardinal directions when stick is fully tilted.
+    BowTie,
+}
+
+/// Dead zone parameters.
+///
+/// All values are magnitudes in range [0.0, 1.0].
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct DeadzoneSettings {
+    pub shape: DeadzoneShape,
+    /// Values smaller than this are changed to 0.0. If `None`,
+    /// [`Gamepad::deadzone()`](../../struct.Gamepad.html#method.deadzone) is used and elements
+    /// for which it is not known are not filtered.
+    pub inner: Option<f32>,
+    /// Values larger than this are changed to 1.0.
+    pub outer: f32,
+    /// Smallest non-zero value, used to compensate for game's own dead zone.
+    pub anti: f32,
+}
+
+impl DeadzoneSettings {
+    /// Creates settings with `ScaledRadial` shape, inner dead zone from gamepad, outer dead zone
+    /// set to 1.0 and no anti-dead zone.
+    pub fn new() -> Self {
+        DeadzoneSettings {
+            shape: DeadzoneShape::ScaledRadial,
+            inner: None,
+            outer: 1.0,
+            anti: 0.0,
+        }
+    }
+
+    /// Maps value from range [inner, outer] to [anti, 1.0].
+    fn scale(&self, val: f32, inner: f32) -> f32 {
+        if val <= inner {
+            return 0.0;
+        }
+
+        let range = self.outer - inner;
+        let t = if range > 0.0 {
+            utils::clamp((val - inner) / range, 0.0, 1.0)
+        } else {
+            1.0
+        };
+
+        self.anti + (1.0 - self.anti) * t
+    }
+
+    fn apply_axial(&self, val: f32, inner: f32) -> f32 {
+        val.signum() * self.scale(val.abs(), inner)
+    }
+
+    fn apply_radial(&self, x: f32, y: f32, scale_from: f32) -> (f32, f32) {
+        // Clamped, so corners of square gates are kept at full tilt on both axes.
+        let magnitude = utils::clamp((x * x + y * y).sqrt(), 0.0, 1.0);
+        let norm = self.scale(magnitude, scale_from) / magnitude;
+
+        (x * norm, y * norm)
+    }
+
+    /// Applies dead zone to stick position.
+    fn apply(&self, x: f32, y: f32, inner: f32) -> (f32, f32) {
+        if self.shape != DeadzoneShape::Axial && (x * x + y * y).sqrt() <= inner {
+            return (0.0, 0.0);
+        }
+
+        match self.shape {
+            DeadzoneShape::Axial | DeadzoneShape::Hybrid => {
+                (self.apply_axial(x, inner), self.apply_axial(y, inner))
+            }
+            DeadzoneShape::Radial => self.apply_radial(x, y, 0.0),
+            DeadzoneShape::ScaledRadial => self.apply_radial(x, y, inner),
+            DeadzoneShape::BowTie => (
+                self.apply_axial(x, inner * y.abs()),
+                self.apply_axial(y, inner * x.abs()),
+            ),
+        }
+    }
+}
+
+impl Default for DeadzoneSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Element of gamepad that can have its own dead zone settings.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum DeadzoneTarget {
+    LeftStick,
+    RightStick,
+    /// Dpad reported as pair of axes.
+    DPad,
+    /// Axis that is not part of a stick, for example `LeftZ`.
+    Axis(Axis),
+    /// Analog button, for example `LeftTrigger2`.
+    Button(Button),
+}
+
+impl DeadzoneTarget {
+    fn from_axis(axis: Axis) -> Self {
+        match axis {
+            Axis::LeftStickX | Axis::LeftStickY => DeadzoneTarget::LeftStick,
+            Axis::RightStickX | Axis::RightStickY => DeadzoneTarget::RightStick,
+            Axis::DPadX | Axis::DPadY => DeadzoneTarget::DPad,
+            axis => DeadzoneTarget::Axis(axis),
+        }
+    }
+}
+
+fn deadzone_nonzero_axis_idx(axis: Axis) -> Option<usize> {
+    Some(match axis {
+        Axis::DPadX => 0,
+        Axis::DPadY => 1,
+        Axis::LeftStickX => 2,
+        Axis::LeftStickY => 3,
+        Axis::RightStickX => 4,
+        Axis::RightStickY => 5,
+        _ => {
+            return None;
+        }
+    })
+}
+
+/// Drops events in dead zone and remaps value to keep it in standard range.
+///
+/// Settings can be changed for all gamepads and elements with `default` field or for a specific
+/// gamepad and/or element with `set()`. When stick moves into dead zone, event that clears its
+/// other axis is also emitted.
+///
+/// ```
+/// use gilrs::{GamepadId, GilrsBuilder};
+/// use gilrs::ev::filter::{Deadzone, DeadzoneSettings, DeadzoneShape, DeadzoneTarget};
+///
+/// let stick = DeadzoneSettings {
+///     shape: DeadzoneShape::Hybrid,
+///     inner: Some(0.08),
+///     outer: 0.95,
+///     anti: 0.1,
+/// };
+/// let deadzone = Deadzone::new().with(None, Some(DeadzoneTarget::LeftStick), stick);
+///
+/// // Larger dead zone for all elements of a worn gamepad.
+/// fn worn_gamepad(deadzone: &mut Deadzone, id: GamepadId) {
+///     deadzone.set(Some(id), None, DeadzoneSettings { inner: Some(0.2), ..Default::default() });
+/// }
+///
+/// let gilrs = GilrsBuilder::new().with_default_filters(false).add_filter(deadzone).build();
+/// ```
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Deadzone {
+    /// Settings used when there are no more specific ones.
+    pub default: DeadzoneSettings,
+    settings: HashMap<(Option<GamepadId>, Option<DeadzoneTarget>), DeadzoneSettings>,
+}
+
+impl Deadzone {
+    /// Creates new `Deadzone` filter with default settings for all elements.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets dead zone for `target` of `gamepad`. `None` means all gamepads or all elements.
+    ///
+    /// Most specific settings are used: first for given gamepad and element, then for given
+    /// gamepad, then for given element and `default` at the end.
+    pub fn set(
+        &mut self,
+        gamepad: Option<GamepadId>,
+        target: Option<DeadzoneTarget>,
+        settings: DeadzoneSettings,
+    ) {
+        self.settings.insert((gamepad, target), settings);
+    }
+
+    /// Same as `set()`, but consumes and returns `self`.
+    pub fn with(
+        mut self,
+        gamepad: Option<GamepadId>,
+        target: Option<DeadzoneTarget>,
+        settings: DeadzoneSettings,
+    ) -> Self {
+        self.set(gamepad, target, settings);
+
+        self
+    }
+
+    /// Removes settings previously set with `set()`.
+    pub fn remove(&mut self, gamepad: Option<GamepadId>, target: Option<DeadzoneTarget>) {
+        self.settings.remove(&(gamepad, target));
+    }
+
+    /// Returns settings used for `target` of `gamepad`.
+    pub fn settings(&self, gamepad: GamepadId, target: DeadzoneTarget) -> &DeadzoneSettings {
+        [
+            (Some(gamepad), Some(target)),
+            (Some(gamepad), None),
+            (None, Some(target)),
+        ]
+        .iter()
+        .find_map(|key| self.settings.get(key))
+        .unwrap_or(&self.default)
+    }
+}
+
+impl EventFilter for Deadzone {
+    fn filter_event(&mut self, ev: Event, gilrs: &mut Gilrs) -> FilterResult {
+        let Event { id, time, .. } = ev;
+
+        match ev.event {
+            EventType::AxisChanged(axis, val, nec) => {
+                let settings = self.settings(id, DeadzoneTarget::from_axis(axis));
+                let inner = match settings.inner.or_else(|| gilrs.gamepad(id).deadzone(nec)) {
+                    Some(t) => t,
+                    None => return FilterResult::Pass(ev),
+                };
+
+                let mut events = EventVec::new();
+                let mut clear_other = None;
+
+                let val = if let Some((other_axis, other_code)) = axis
+                    .second_axis()
+                    .and_then(|axis| gilrs.gamepad(id).axis_code(axis).map(|code| (axis, code)))
+                {
+                    let other_val = gilrs.gamepad(id).state().value(other_code);
+                    let val = settings.apply(val, other_val, inner);
+
+                    // Since this is the second axis, deadzone_nonzero_axis_idx() will always
+                    // returns something.
+                    let other_axis_idx = deadzone_nonzero_axis_idx(other_axis).unwrap();
+
+                    if val.0 == 0.
+                        && val.1 == 0.
+                        && gilrs.gamepads_data[id.0].have_sent_nonzero_for_axis[other_axis_idx]
+                        && other_val != 0.
+                    {
+                        // Clear other axis that is now within the dead zone threshold.
+                        clear_other = Some(Event {
+                            id,
+                            time,
+                            event: EventType::AxisChanged(other_axis, 0., other_code),
+                        });
+                        gilrs.gamepads_data[id.0].have_sent_nonzero_for_axis[other_axis_idx] =
+                            false;
+                    }
+
+                    val.0
+                } else {
+                    settings.apply_axial(val, inner)
+                };
+
+                if gilrs.gamepad(id).state().value(nec) != val {
+                    if let Some(axis_idx) = deadzone_nonzero_axis_idx(axis) {
+                        gilrs.gamepads_data[id.0].have_sent_nonzero_for_axis[axis_idx] = val != 0.;
+                    }
+                    events.push(Event {
+                        id,
+                        time,
+                        event: EventType::AxisChanged(axis, val, nec),
+                    });
+                }
+                events.extend(clear_other);
+
+                FilterResult::from_events(ev, events)
+            }
+            EventType::ButtonChanged(btn, val, nec) => {
+                let settings = self.settings(id, DeadzoneTarget::Button(btn));
+                let gp = &gilrs.gamepad(id);
+                let inner = match settings.inner.or_else(|| gp.deadzone(nec)) {
+                    Some(t) => t,
+                    None => return FilterResult::Pass(ev),
+                };
+                let val = settings.apply_axial(val, inner);
+
+                if gp.state().value(nec) == val {
+                    FilterResult::Drop
+                } else {
+                    FilterResult::from_events(
+                        ev,
+                        smallvec![Event {
+                            id,
+                            time,
+                            event: EventType::ButtonChanged(btn, val, nec),
+                        }],
+                    )
+                }
+            }
+            _ => FilterResult::Pass(ev),
+        }
+    }
+}
+
+impl FilterFn for Deadzone {
+    fn filter(&self, ev: Option<Event>, gilrs: &mut Gilrs) -> Option<Event> {
+        compat_filter(&mut self.clone(), ev, gilrs)
+    }
+}
+
+/// Drops events in dead zone and remaps value to keep it in standard range.
+///
+/// `FilterFn` version of [`Deadzone`] with default settings.
+pub fn deadzone(ev: Option<Event>, gilrs: &mut Gilrs) -> Option<Event> {
+    compat_filter(&mut Deadzone::new(), ev, gilrs)
+}
+
+/// Response curve applied to magnitude of axis or button value.
+#[derive(Clone, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum Curve {
+    /// Value is not changed.
+    #[default]
+    Linear,
+    /// Value is raised to given power. Exponents larger than 1.0 give more precision near
+    /// center.
+    Power(f32),
+    /// `x^k / (x^k + (1 - x)^k)`. For `k` larger than 1.0, gives more precision near center and
+    /// near edges.
+    SCurve(f32),
+    /// Piecewise-linear function defined by `(input, output)` points sorted by input. Values
+    /// outside of the table use output of the nearest point.
+    Table(Vec<(f32, f32)>),
+}
+
+impl Curve {
+    /// Creates `Curve::Table` from points in any order.
+    pub fn table(mut points: Vec<(f32, f32)>) -> Self {
+        points.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+        Curve::Table(points)
+    }
+
+    /// Applies curve to `val`. Sign of `val` is preserved.
+    pub fn apply(&self, val: f32) -> f32 {
+        let x = val.abs();
+        let y = match *self {
+            Curve::Linear => x,
+            Curve::Power(exp) => x.powf(exp),
+            Curve::SCurve(k) => {
+                let a = x.powf(k);
+                let b = (1.0 - x).max(0.0).powf(k);
+                if a + b == 0.0 {
+                    x
+                } else {
+                    a / (a + b)
+                }
+            }
+            Curve::Table(ref points) => match points.iter().position(|&(px, _)| px >= x) {
+                Some(0) => points[0].1,
+                Some(i) => {
+                    let (x0, y0) = points[i - 1];
+                    let (x1, y1) = points[i];
+                    y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+                }
+                None => points.last().map(|p| p.1).unwrap_or(x),
+            },
+        };
+
+        val.signum() * y
+    }
+}
+
+/// Applies response curves to axis and analog button values.
+///
+/// Curves can be set for all gamepads and elements with `default` field or for a specific gamepad
+/// and/or element with `set()`. This filter should be installed after [`Deadzone`].
+///
+/// ```
+/// use gilrs::{Axis, Button, GilrsBuilder};
+/// use gilrs::ev::AxisOrBtn;
+/// use gilrs::ev::filter::{default_filters, Curve, ResponseCurve};
+///
+/// let curves = ResponseCurve::new()
+///     .with(None, Some(AxisOrBtn::Axis(Axis::RightStickX)), Curve::Power(2.0))
+///     .with(None, Some(AxisOrBtn::Axis(Axis::RightStickY)), Curve::Power(2.0))
+///     .with(
+///         None,
+///         Some(AxisOrBtn::Btn(Button::RightTrigger2)),
+///         Curve::table(vec![(0.0, 0.0), (0.5, 0.2), (1.0, 1.0)]),
+///     );
+///
+/// let gilrs = GilrsBuilder::new()
+///     .filters(default_filters())
+///     .add_filter(curves)
+///     .build();
+/// ```
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct ResponseCurve {
+    /// Curve used when there is no more specific one.
+    pub default: Curve,
+    curves: HashMap<(Option<GamepadId>, Option<AxisOrBtn>), Curve>,
+}
+
+impl ResponseCurve {
+    /// Creates new `ResponseCurve` filter with linear curve for all elements.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets curve for `element` of `gamepad`. `None` means all gamepads or all elements.
+    ///
+    /// Most specific curve is used: first for given gamepad and element, then for given
+    /// gamepad, then for given element and `default` at the end.
+    pub fn set(&mut self, gamepad: Option<GamepadId>, element: Option<AxisOrBtn>, curve: Curve) {
+        self.curves.insert((gamepad, element), curve);
+    }
+
+    /// Same as `set()`, but consumes and returns `self`.
+    pub fn with(
+        mut self,
+        gamepad: Option<GamepadId>,
+        element: Option<AxisOrBtn>,
+        curve: Curve,
+    ) -> Self {
+        self.set(gamepad, element, curve);
+
+        self
+    }
+
+    /// Removes curve previously set with `set()`.
+    pub fn remove(&mut self, gamepad: Option<GamepadId>, element: Option<AxisOrBtn>) {
+        self.curves.remove(&(gamepad, element));
+    }
+
+    /// Returns curve used for `element` of `gamepad`.
+    pub fn curve(&self, gamepad: GamepadId, element: AxisOrBtn) -> &Curve {
+        [
+            (Some(gamepad), Some(element)),
+            (Some(gamepad), None),
+            (None, Some(element)),
+        ]
+        .iter()
+        .find_map(|key| self.curves.get(key))
+        .unwrap_or(&self.default)
+    }
+}
+
+impl EventFilter for ResponseCurve {
+    fn filter_event(&mut self, ev: Event, _gilrs: &mut Gilrs) -> FilterResult {
+        let event = match ev.event {
+            EventType::AxisChanged(axis, val, nec) => EventType::AxisChanged(
+                axis,
+                self.curve(ev.id, AxisOrBtn::Axis(axis)).apply(val),
+                nec,
+            ),
+            EventType::ButtonChanged(btn, val, nec) => EventType::ButtonChanged(
+                btn,
+                self.curve(ev.id, AxisOrBtn::Btn(btn)).apply(val),
+                nec,
+            ),
+            _ => return FilterResult::Pass(ev),
+        };
+
+        if event == ev.event {
+            FilterResult::Pass(ev)
+        } else {
+            FilterResult::Replace(Event { event, ..ev })
+        }
+    }
+}
+
+impl FilterFn for ResponseCurve {
+    fn filter(&self, ev: Option<Event>, gilrs: &mut Gilrs) -> Option<Event> {
+        compat_filter(&mut self.clone(), ev, gilrs)
+    }
+}
+
+/// Maps axis dpad events to button dpad events.
+///
+/// This filter will do nothing if gamepad has dpad buttons (to prevent double events for same
+/// element) and if standard `NativeEvCode` for dpads is used by some other buttons. It will always
+/// try to map if SDL mappings contains mappings for all four hats.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct AxisDpadToButton;
+
+impl AxisDpadToButton {
+    fn can_map(gp: &Gamepad<'_>) -> bool {
+        use gilrs_core::native_ev_codes as necs;
+
+        let hats_mapped = gp.mapping().hats_mapped();
+        if hats_mapped == 0b0000_1111 {
+            true
+        } else if hats_mapped == 0 {
+            gp.axis_or_btn_name(Code(necs::BTN_DPAD_RIGHT)).is_none()
+                && gp.axis_or_btn_name(Code(necs::BTN_DPAD_LEFT)).is_none()
+                && gp.axis_or_btn_name(Code(necs::BTN_DPAD_DOWN)).is_none()
+                && gp.axis_or_btn_name(Code(necs::BTN_DPAD_UP)).is_none()
+                && gp.button_code(Button::DPadRight).is_none()
+        } else {
+            // Not all hats are mapped so let's ignore it for now.
+            false
+        }
+    }
+
+    /// Emits button events for dpad axis with value `val`. `neg` is button pressed for -1.0 and
+    /// `pos` for 1.0.
+    fn map_axis(
+        ev: Event,
+        val: f32,
+        neg: (Button, Code),
+        pos: (Button, Code),
+        gamepad: &Gamepad<'_>,
+    ) -> FilterResult {
+        let is_pressed = |(_, code): (Button, Code)| gamepad.state().is_pressed(code);
+
+        // The axis value might change from one side to the other immediately without us getting
+        // an additional event for the release at the center position (0.0).
+        let (release, press) = if val == 1.0 {
+            ([Some(neg).filter(|&b| is_pressed(b)), None], Some(pos))
+        } else if val == -1.0 {
+            ([Some(pos).filter(|&b| is_pressed(b)), None], Some(neg))
+        } else {
+            (
+                [
+                    Some(pos).filter(|&b| is_pressed(b)),
+                    Some(neg).filter(|&b| is_pressed(b)),
+                ],
+                None,
+            )
+        };
+
+        let mut events = EventVec::new();
+        for (btn, code) in release.iter().flatten() {
+            events.push(Event {
+                event: EventType::ButtonReleased(*btn, *code),
+                ..ev
+            });
+            events.push(Event {
+                event: EventType::ButtonChanged(*btn, 0.0, *code),
+                ..ev
+            });
+        }
+        if let Some((btn, code)) = press {
+            events.push(Event {
+                event: EventType::ButtonPressed(btn, code),
+                ..ev
+            });
+            events.push(Event {
+                event: EventType::ButtonChanged(btn, 1.0, code),
+                ..ev
+            });
+        }
+
+        FilterResult::from_events(ev, events)
+    }
+}
+
+impl EventFilter for AxisDpadToButton {
+    fn filter_event(&mut self, ev: Event, gilrs: &mut Gilrs) -> FilterResult {
+        use gilrs_core::native_ev_codes as necs;
+
+        let gamepad = gilrs.gamepad(ev.id);
+
+        if !Self::can_map(&gamepad) {
+            return FilterResult::Pass(ev);
+        }
+
+        match ev.event {
+            EventType::AxisChanged(Axis::DPadX, val, _) => Self::map_axis(
+                ev,
+                val,
+                (Button::DPadLeft, Code(necs::BTN_DPAD_LEFT)),
+                (Button::DPadRight, Code(necs::BTN_DPAD_RIGHT)),
+                &gamepad,
+            ),
+            EventType::AxisChanged(Axis::DPadY, val, _) => Self::map_axis(
+                ev,
+                val,
+                (Button::DPadDown, Code(necs::BTN_DPAD_DOWN)),
+                (Button::DPadUp, Code(necs::BTN_DPAD_UP)),
+                &gamepad,
+            ),
+            _ => FilterResult::Pass(ev),
+        }
+    }
+}
+
+impl FilterFn for AxisDpadToButton {
+    fn filter(&self, ev: Option<Event>, gilrs: &mut Gilrs) -> Option<Event> {
+        let mut filter = *self;
+        compat_filter(&mut filter, ev, gilrs)
+    }
+}
+
+/// Maps axis dpad events to button dpad events.
+///
+/// `FilterFn` version of [`AxisDpadToButton`].
+pub fn axis_dpad_to_button(ev: Option<Event>, gilrs: &mut Gilrs) -> Option<Event> {
+    compat_filter(&mut AxisDpadToButton, ev, gilrs)
+}
+
+/// Stick used by [`StickToDpad`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum Stick {
+    #[default]
+    Left,
+    Right,
+}
+
+impl Stick {
+    fn axes(self) -> (Axis, Axis) {
+        match self {
+            Stick::Left => (Axis::LeftStickX, Axis::LeftStickY),
+            Stick::Right => (Axis::RightStickX, Axis::RightStickY),
+        }
+    }
+}
+
+/// Directions that can be pressed by [`StickToDpad`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum DpadMode {
+    /// Only one direction can be pressed.
+    FourWay,
+    /// Two directions can be pressed when stick is in diagonal sector.
+    #[default]
+    EightWay,
+}
+
+const DPAD_BUTTONS: [(Button, u8); 4] = [
+    (Button::DPadUp, 0b0001),
+    (Button::DPadDown, 0b0010),
+    (Button::DPadLeft, 0b0100),
+    (Button::DPadRight, 0b1000),
+];
+
+fn dpad_code(btn: Button) -> Code {
+    use gilrs_core::native_ev_codes as necs;
+
+    match btn {
+        Button::DPadUp => Code(necs::BTN_DPAD_UP),
+        Button::DPadDown => Code(necs::BTN_DPAD_DOWN),
+        Button::DPadLeft => Code(necs::BTN_DPAD_LEFT),
+        _ => Code(necs::BTN_DPAD_RIGHT),
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+struct StickState {
+    x: f32,
+    y: f32,
+    pressed: u8,
+}
+
+/// Emits `DPad*` button events when stick is moved, so it can be used like a d-pad.
+///
+/// Stick is divided into angular sectors. In `FourWay` mode every direction has 90° sector. In
+/// `EightWay` mode diagonal sectors are `diagonal` degrees wide and press two buttons. Directions
+/// are pressed when stick moves further than `press` from center and released when it moves closer
+/// than `release`. Pressed directions don't change until stick leaves their sector widened by
+/// `angle_hysteresis` degrees on both sides.
+///
+/// If `repeat` is set, this filter also emits `ButtonRepeated` for pressed directions, so there is
+/// no need to install [`Repeat`] filter just for them. Stick axis events are passed unchanged
+/// unless `drop_axes` is true.
+///
+/// ```
+/// use gilrs::GilrsBuilder;
+/// use gilrs::ev::filter::{default_filters, DpadMode, Repeat, StickToDpad};
+///
+/// let mut menu_stick = StickToDpad::new();
+/// menu_stick.mode = DpadMode::FourWay;
+/// menu_stick.repeat = Some(Repeat::new());
+///
+/// let gilrs = GilrsBuilder::new()
+///     .filters(default_filters())
+///     .add_filter(menu_stick)
+///     .build();
+/// ```
+#[derive(Clone, PartialEq, Debug)]
+pub struct StickToDpad {
+    pub stick: Stick,
+    pub mode: DpadMode,
+    pub press: f32,
+    pub release: f32,
+    /// Width of diagonal sectors in degrees, used only in `EightWay` mode.
+    pub diagonal: f32,
+    pub angle_hysteresis: f32,
+    pub repeat: Option<Repeat>,
+    pub drop_axes: bool,
+    sticks: HashMap<GamepadId, StickState>,
+}
+
+impl StickToDpad {
+    /// Creates new `StickToDpad` filter for left stick in `EightWay` mode, with `press` set to 0.5,
+    /// `release` set to 0.35, `diagonal` set to 45° and `angle_hysteresis` set to 5°.
+    pub fn new() -> Self {
+        StickToDpad {
+            stick: Stick::Left,
+            mode: DpadMode::EightWay,
+            press: 0.5,
+            release: 0.35,
+            diagonal: 45.0,
+            angle_hysteresis: 5.0,
+            repeat: None,
+            drop_axes: false,
+            sticks: HashMap::new(),
+        }
+    }
+
+    /// Returns true if `angle` is inside of sector of `dirs`, widened by `margin` on both sides.
+    fn in_sector(&self, angle: f32, dirs: u8, margin: f32) -> bool {
+        let (center, half_width) = match dirs {
+            0b0001 => (90.0, self.cardinal_width()),
+            0b0010 => (270.0, self.cardinal_width()),
+            0b0100 => (180.0, self.cardinal_width()),
+            0b1000 => (0.0, self.cardinal_width()),
+            0b1001 => (45.0, self.diagonal_width()),
+            0b0101 => (135.0, self.diagonal_width()),
+            0b0110 => (225.0, self.diagonal_width()),
+            0b1010 => (315.0, self.diagonal_width()),
+            _ => return false,
+        };
+
+        let diff = (angle - center).rem_euclid(360.0);
+        diff.min(360.0 - diff) <= half_width / 2.0 + margin
+    }
+
+    fn cardinal_width(&self) -> f32 {
+        match self.mode {
+            DpadMode::FourWay => 90.0,
+            DpadMode::EightWay => 90.0 - self.diagonal_width(),
+        }
+    }
+
+    fn diagonal_width(&self) -> f32 {
+        match self.mode {
+            DpadMode::FourWay => 0.0,
+            DpadMode::EightWay => self.diagonal.clamp(0.0, 90.0),
+        }
+    }
+
+    /// Returns directions that should be pressed for stick position.
+    fn directions(&self, x: f32, y: f32, pressed: u8) -> u8 {
+        let magnitude = (x * x + y * y).sqrt();
+        let threshold = if pressed == 0 {
+            self.press
+        } else {
+            self.release
+        };
+        if magnitude < threshold || magnitude == 0.0 {
+            return 0;
+        }
+
+        let angle = y.atan2(x).to_degrees().rem_euclid(360.0);
+        if pressed != 0 && self.in_sector(angle, pressed, self.angle_hysteresis) {
+            return pressed;
+        }
+
+        [
+            0b1001, 0b0101, 0b0110, 0b1010, 0b0001, 0b0010, 0b0100, 0b1000,
+        ]
+        .iter()
+        .copied()
+        .find(|&dirs| self.in_sector(angle, dirs, 0.0))
+        .unwrap_or(0)
+    }
+}
+
+impl Default for StickToDpad {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventFilter for StickToDpad {
+    fn filter_event(&mut self, ev: Event, _gilrs: &mut Gilrs) -> FilterResult {
+        let (axis_x, axis_y) = self.stick.axes();
+        let mut state = self.sticks.get(&ev.id).copied().unwrap_or_default();
+        match ev.event {
+            EventType::AxisChanged(axis, val, _) if axis == axis_x => state.x = val,
+            EventType::AxisChanged(axis, val, _) if axis == axis_y => state.y = val,
+            EventType::Disconnected => {
+                self.sticks.remove(&ev.id);
+                if let Some(ref repeat) = self.repeat {
+                    repeat.observe(&ev);
+                }
+                return FilterResult::Pass(ev);
+            }
+            _ => return FilterResult::Pass(ev),
+        }
+
+        let pressed = self.directions(state.x, state.y, state.pressed);
+        let released = state.pressed & !pressed;
+        let newly_pressed = pressed & !state.pressed;
+        state.pressed = pressed;
+        self.sticks.insert(ev.id, state);
+
+        let mut events = EventVec::new();
+        if !self.drop_axes {
+            events.push(ev);
+        }
+
+        for &(btn, bit) in &DPAD_BUTTONS {
+            if released & bit != 0 {
+                events.push(Event {
+                    event: EventType::ButtonReleased(btn, dpad_code(btn)),
+                    ..ev
+                });
+                events.push(Event {
+                    event: EventType::ButtonChanged(btn, 0.0, dpad_code(btn)),
+                    ..ev
+                });
+            }
+        }
+        for &(btn, bit) in &DPAD_BUTTONS {
+            if newly_pressed & bit != 0 {
+                events.push(Event {
+                    event: EventType::ButtonPressed(btn, dpad_code(btn)),
+                    ..ev
+                });
+                events.push(Event {
+                    event: EventType::ButtonChanged(btn, 1.0, dpad_code(btn)),
+                    ..ev
+                });
+            }
+        }
+
+        if let Some(ref repeat) = self.repeat {
+            events.iter().for_each(|ev| repeat.observe(ev));
+        }
+
+        FilterResult::from_events(ev, events)
+    }
+
+    fn poll(&mut self, _gilrs: &mut Gilrs) -> Option<Event> {
+        self.repeat.as_ref()?.poll_at(utils::time_now())
+    }
+}
+
+/// Timing of repeats of a button.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct RepeatSettings {
+    /// Time between press and first repeat.
+    pub after: Duration,
+    /// Time between first and second repeat.
+    pub every: Duration,
+    /// Every next interval is multiplied by this value. 1.0 disables acceleration.
+    pub acceleration: f32,
+    /// Interval never gets shorter than this.
+    pub min_every: Duration,
+}
+
+impl RepeatSettings {
+    /// Creates settings with `after` set to 500ms, `every` set to 30ms and without acceleration.
+    pub fn new() -> Self {
+        RepeatSettings {
+            after: Duration::from_millis(500),
+            every: Duration::from_millis(30),
+            acceleration: 1.0,
+            min_every: Duration::from_millis(30),
+        }
+    }
+
+    fn next_interval(&self, interval: Duration) -> Duration {
+        if self.acceleration == 1.0 || !self.acceleration.is_finite() || self.acceleration < 0.0 {
+            return interval;
+        }
+
+        interval.mul_f32(self.acceleration).max(self.min_every)
+    }
+}
+
+impl Default for RepeatSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct PendingRepeat {
+    btn: Button,
+    next: SystemTime,
+    interval: Duration,
+}
+
+/// Repeats of buttons that are currently pressed.
+#[derive(Clone, PartialEq, Debug, Default)]
+struct RepeatSchedule(HashMap<(GamepadId, Code), PendingRepeat>);
+
+impl RepeatSchedule {
+    fn observe(&mut self, ev: &Event, repeat: &Repeat) {
+        match ev.event {
+            EventType::ButtonPressed(btn, code) if repeat.is_repeated(btn) => {
+                let settings = repeat.settings(btn);
+                self.0.insert(
+                    (ev.id, code),
+                    PendingRepeat {
+                        btn,
+                        next: ev.time + settings.after,
+                        interval: settings.every,
+                    },
+                );
+            }
+            EventType::ButtonReleased(_, code) => {
+                self.0.remove(&(ev.id, code));
+            }
+            EventType::Disconnected => self.0.retain(|&(id, _), _| id != ev.id),
+            _ => (),
+        }
+    }
+
+    fn poll(&mut self, repeat: &Repeat, now: SystemTime) -> Option<Event> {
+        let (&(id, code), pending) = self
+            .0
+            .iter_mut()
+            .filter(|(_, pending)| pending.next <= now)
+            .min_by_key(|(_, pending)| pending.next)?;
+
+        let time = pending.next;
+        pending.next += pending.interval;
+        pending.interval = repeat.settings(pending.btn).next_interval(pending.interval);
+
+        Some(Event {
+            id,
+            event: EventType::ButtonRepeated(pending.btn, code),
+            time,
+        })
+    }
+}
+
+/// Repeats pressed keys.
+///
+/// Timing can be changed for all buttons with public fields or for a specific button with
+/// `set()`. By default, all buttons are repeated. This can be changed with `set_included()` and
+/// `set_excluded()`.
+///
+/// Repeats are scheduled when this filter sees `ButtonPressed` event, so it has to be installed
+/// after filters that create or drop button events.
+///
+/// ```
+/// use std::time::Duration;
+/// use gilrs::{Button, GilrsBuilder};
+/// use gilrs::ev::filter::{default_filters, Repeat, RepeatSettings};
+///
+/// // Scrolling that speeds up, only for d-pad.
+/// let dpad = [Button::DPadUp, Button::DPadDown, Button::DPadLeft, Button::DPadRight];
+/// let mut repeat = Repeat::new().with_included(Some(&dpad));
+/// repeat.after = Duration::from_millis(400);
+/// repeat.every = Duration::from_millis(200);
+/// repeat.acceleration = 0.8;
+/// repeat.min_every = Duration::from_millis(40);
+///
+/// // South button doesn't accelerate.
+/// repeat.set(Button::South, RepeatSettings::new());
+///
+/// let gilrs = GilrsBuilder::new()
+///     .filters(default_filters())
+///     .add_filter(repeat)
+///     .build();
+/// ```
+#[derive(Clone, PartialEq, Debug)]
+pub struct Repeat {
+    /// Time between press and first repeat of buttons without own settings.
+    pub after: Duration,
+    /// Time between first and second repeat of buttons without own settings.
+    pub every: Duration,
+    /// See [`RepeatSettings::acceleration`].
+    pub acceleration: f32,
+    /// See [`RepeatSettings::min_every`].
+    pub min_every: Duration,
+    settings: HashMap<Button, RepeatSettings>,
+    included: Option<HashSet<Button>>,
+    excluded: HashSet<Button>,
+    schedule: RefCell<RepeatSchedule>,
+}
+
+impl Repeat {
+    /// Creates new `Repeat` filter with default settings, that repeats all buttons.
+    pub fn new() -> Self {
+        let default = RepeatSettings::new();
+        Repeat {
+            after: default.after,
+            every: default.every,
+            acceleration: default.acceleration,
+            min_every: default.min_every,
+            settings: HashMap::new(),
+            included: None,
+            excluded: HashSet::new(),
+            schedule: RefCell::new(RepeatSchedule::default()),
+        }
+    }
+
+    /// Creates new `Repeat` filter that repeats all buttons `after` press and then `every`
+    /// interval.
+    #[deprecated(note = "use `Repeat { after, every, ..Repeat::new() }`")]
+    pub fn with_timing(after: Duration, every: Duration) -> Self {
+        Repeat {
+            after,
+            every,
+            ..Repeat::new()
+        }
+    }
+
+    /// Sets settings for `button`.
+    pub fn set(&mut self, button: Button, settings: RepeatSettings) {
+        self.settings.insert(button, settings);
+    }
+
+    /// Sets settings for `button`.
+    pub fn with(mut self, button: Button, settings: RepeatSettings) -> Self {
+        self.set(button, settings);
+        self
+    }
+
+    /// Removes settings for `button`, so it will use settings from public fields.
+    pub fn remove(&mut self, button: Button) {
+        self.settings.remove(&button);
+    }
+
+    /// Returns settings used for `button`.
+    pub fn settings(&self, button: Button) -> RepeatSettings {
+        self.settings
+            .get(&button)
+            .copied()
+            .unwrap_or(RepeatSettings {
+                after: self.after,
+                every: self.every,
+                acceleration: self.acceleration,
+                min_every: self.min_every,
+            })
+    }
+
+    /// Sets buttons that can be repeated. `None` means all buttons.
+    pub fn set_included(&mut self, buttons: Option<&[Button]>) {
+        self.included = buttons.map(|b| b.iter().copied().collect());
+    }
+
+    /// Sets buttons that can be repeated. `None` means all buttons.
+    pub fn with_included(mut self, buttons: Option<&[Button]>) -> Self {
+        self.set_included(buttons);
+        self
+    }
+
+    /// Sets buttons that are never repeated, even if they are included.
+    pub fn set_excluded(&mut self, buttons: &[Button]) {
+        self.excluded = buttons.iter().copied().collect();
+    }
+
+    /// Sets buttons that are never repeated, even if they are included.
+    pub fn with_excluded(mut self, buttons: &[Button]) -> Self {
+        self.set_excluded(buttons);
+        self
+    }
+
+    /// Returns true if `button` is repeated.
+    pub fn is_repeated(&self, button: Button) -> bool {
+        !self.excluded.contains(&button)
+            && self
+                .included
+                .as_ref()
+                .map_or(true, |included| included.contains(&button))
+    }
 
-/// Discard axis events that changed less than `threshold`.
-#[derive(Copy, Clone, PartialEq, Debug)]
-pub struct Jitter {
-    pub threshold: f32,
-}
+    fn observe(&self, ev: &Event) {
+        self.schedule.borrow_mut().observe(ev, self);
+    }
 
-impl Jitter {
-    /// Creates new `Repeat` filter with threshold set to 0.01.
-    pub fn new() -> Self {
-        Jitter { threshold: 0.01 }
+    fn poll_at(&self, now: SystemTime) -> Option<Event> {
+        self.schedule.borrow_mut().poll(self, now)
     }
 }
 
-impl Default for Jitter {
+impl Default for Repeat {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl FilterFn for Jitter {
-    fn filter(&self, ev: Option<Event>, gilrs: &mut Gilrs) -> Option<Event> {
+impl EventFilter for Repeat {
+    fn filter_event(&mut self, ev: Event, _gilrs: &mut Gilrs) -> FilterResult {
+        self.observe(&ev);
+        FilterResult::Pass(ev)
+    }
+
+    fn poll(&mut self, _gilrs: &mut Gilrs) -> Option<Event> {
+        self.poll_at(utils::time_now())
+    }
+}
+
+// `FilterFn` takes `&self`, that's why schedule is in `RefCell`.
+impl FilterFn for Repeat {
+    fn filter(&self, ev: Option<Event>, _gilrs: &mut Gilrs) -> Option<Event> {
         match ev {
-            Some(Event {
-                event: EventType::AxisChanged(_, val, axis),
-                id,
-                ..
-            }) => match gilrs.gamepad(id).state().axis_data(axis) {
-                Some(data) if val != 0.0 && (val - data.value()).abs() < self.threshold => {
-                    Some(Event::new(id, EventType::Dropped))
-                }
-                _ => ev,
-            },
-            _ => ev,
+            Some(ev) => {
+                self.observe(&ev);
+                Some(ev)
+            }
+            None => self.poll_at(utils::time_now()),
         }
     }
 }
 
-fn apply_deadzone(x: f32, y: f32, threshold: f32) -> (f32, f32) {
-    let magnitude = utils::clamp((x * x + y * y).sqrt(), 0.0, 1.0);
-    if magnitude <= threshold {
-        (0.0, 0.0)
-    } else {
-        let norm = ((magnitude - threshold) / (1.0 - threshold)) / magnitude;
-        (x * norm, y * norm)
-    }
+/// Returns state of button `nec` of gamepad `id`, if gamepad is connected and button was used.
+fn button_data(gilrs: &Gilrs, id: GamepadId, nec: Code) -> Option<ButtonData> {
+    gilrs
+        .connected_gamepad(id)
+        .and_then(|gamepad| gamepad.state().button_data(nec).copied())
 }
 
-fn deadzone_nonzero_axis_idx(axis: Axis) -> Option<usize> {
-    Some(match axis {
-        Axis::DPadX => 0,
-        Axis::DPadY => 1,
-        Axis::LeftStickX => 2,
-        Axis::LeftStickY => 3,
-        Axis::RightStickX => 4,
-        Axis::RightStickY => 5,
-        _ => {
-            return None;
+/// Emits `ButtonDoubleTapped` after `ButtonPressed` when button is pressed second time in less
+/// than `interval` after first press.
+///
+/// Third press starts new double tap. Time of previous press is read from gamepad state, so this
+/// filter doesn't work if state updates are disabled.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DoubleTap {
+    pub interval: Duration,
+    // Buttons which last press completed double tap.
+    completed: HashSet<(GamepadId, Code)>,
+}
+
+impl DoubleTap {
+    /// Creates new `DoubleTap` filter with `interval` set to 300ms.
+    pub fn new() -> Self {
+        DoubleTap {
+            interval: Duration::from_millis(300),
+            completed: HashSet::new(),
         }
-    })
+    }
 }
 
-/// Drops events in dead zone and remaps value to keep it in standard range.
-pub fn deadzone(ev: Option<Event>, gilrs: &mut Gilrs) -> Option<Event> {
-    match ev {
-        Some(Event {
-            event: EventType::AxisChanged(axis, val, nec),
-            id,
-            time,
-        }) => {
-            let threshold = match gilrs.gamepad(id).deadzone(nec) {
-                Some(t) => t,
-                None => return ev,
-            };
-
-            if let Some((other_axis, other_code)) = axis
-                .second_axis()
-                .and_then(|axis| gilrs.gamepad(id).axis_code(axis).map(|code| (axis, code)))
-            {
-                let other_val = gilrs.gamepad(id).state().value(other_code);
-                let val = apply_deadzone(val, other_val, threshold);
-
-                // Since this is the second axis, deadzone_nonzero_axis_idx() will always returns something.
-                let other_axis_idx = deadzone_nonzero_axis_idx(other_axis).unwrap();
-
-                if val.0 == 0.
-                    && val.1 == 0.
-                    && gilrs.gamepads_data[id.0].have_sent_nonzero_for_axis[other_axis_idx]
-                    && gilrs.gamepad(id).state().value(other_code) != 0.
-                {
-                    // Clear other axis that is now within the dead zone threshold.
-                    gilrs.insert_event(Event {
-                        id,
-                        time,
-                        event: EventType::AxisChanged(other_axis, 0., other_code),
-                    });
-                    gilrs.gamepads_data[id.0].have_sent_nonzero_for_axis[other_axis_idx] = false;
-                }
+impl Default for DoubleTap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-                Some(if gilrs.gamepad(id).state().value(nec) == val.0 {
-                    Event::new(id, EventType::Dropped)
-                } else {
-                    if let Some(axis_idx) = deadzone_nonzero_axis_idx(axis) {
-                        gilrs.gamepads_data[id.0].have_sent_nonzero_for_axis[axis_idx] =
-                            val.0 != 0.;
-                    }
-                    Event {
-                        id,
-                        time,
-                        event: EventType::AxisChanged(axis, val.0, nec),
-                    }
-                })
-            } else {
-                let val = apply_deadzone(val, 0.0, threshold).0;
+impl EventFilter for DoubleTap {
+    fn filter_event(&mut self, ev: Event, gilrs: &mut Gilrs) -> FilterResult {
+        match ev.event {
+            EventType::ButtonPressed(btn, nec) => {
+                // State is updated after filtering, so it still holds previous press.
+                let first = button_data(gilrs, ev.id, nec).map(|data| data.press_timestamp());
+                let is_second = !self.completed.remove(&(ev.id, nec))
+                    && matches!(
+                        first.map(|first| ev.time.duration_since(first)),
+                        Some(Ok(dur)) if dur <= self.interval
+                    );
 
-                Some(if gilrs.gamepad(id).state().value(nec) == val {
-                    Event::new(id, EventType::Dropped)
+                if is_second {
+                    self.completed.insert((ev.id, nec));
+                    FilterResult::Emit(smallvec![
+                        ev,
+                        Event {
+                            event: EventType::ButtonDoubleTapped(btn, nec),
+                            ..ev
+                        }
+                    ])
                 } else {
-                    if let Some(axis_idx) = deadzone_nonzero_axis_idx(axis) {
-                        gilrs.gamepads_data[id.0].have_sent_nonzero_for_axis[axis_idx] = val != 0.;
-                    }
-                    Event {
-                        id,
-                        time,
-                        event: EventType::AxisChanged(axis, val, nec),
-                    }
-                })
-            }
-        }
-        Some(Event {
-            event: EventType::ButtonChanged(btn, val, nec),
-            id,
-            time,
-        }) => {
-            let gp = &gilrs.gamepad(id);
-            let threshold = match gp.deadzone(nec) {
-                Some(t) => t,
-                None => return ev,
-            };
-            let val = apply_deadzone(val, 0.0, threshold).0;
-
-            Some(if gp.state().value(nec) == val {
-                Event::new(id, EventType::Dropped)
-            } else {
-                Event {
-                    id,
-                    time,
-                    event: EventType::ButtonChanged(btn, val, nec),
+                    FilterResult::Pass(ev)
                 }
-            })
+            }
+            EventType::Disconnected => {
+                self.completed.retain(|&(id, _)| id != ev.id);
+                FilterResult::Pass(ev)
+            }
+            _ => FilterResult::Pass(ev),
         }
-        _ => ev,
     }
 }
 
-/// Maps axis dpad events to button dpad events.
+/// Emits `ButtonLongPressed` once for every press that lasted at least `after`.
 ///
-/// This filter will do nothing if gamepad has dpad buttons (to prevent double events for same
-/// element) and if standard `NativeEvCode` for dpads is used by some other buttons. It will always
-/// try to map if SDL mappings contains mappings for all four hats.
-pub fn axis_dpad_to_button(ev: Option<Event>, gilrs: &mut Gilrs) -> Option<Event> {
-    use gilrs_core::native_ev_codes as necs;
+/// Like [`Repeat`], this filter creates events when there are no other events to process. Time of
+/// press is read from gamepad state, so this filter doesn't work if state updates are disabled.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LongPress {
+    pub after: Duration,
+    // Pressed buttons for which event wasn't emitted yet.
+    pending: HashMap<(GamepadId, Code), Button>,
+}
 
-    fn can_map(gp: &Gamepad<'_>) -> bool {
-        let hats_mapped = gp.mapping().hats_mapped();
-        if hats_mapped == 0b0000_1111 {
-            true
-        } else if hats_mapped == 0 {
-            gp.axis_or_btn_name(Code(necs::BTN_DPAD_RIGHT)).is_none()
-                && gp.axis_or_btn_name(Code(necs::BTN_DPAD_LEFT)).is_none()
-                && gp.axis_or_btn_name(Code(necs::BTN_DPAD_DOWN)).is_none()
-                && gp.axis_or_btn_name(Code(necs::BTN_DPAD_UP)).is_none()
-                && gp.button_code(Button::DPadRight).is_none()
-        } else {
-            // Not all hats are mapped so let's ignore it for now.
-            false
+impl LongPress {
+    /// Creates new `LongPress` filter with `after` set to 800ms.
+    pub fn new() -> Self {
+        LongPress {
+            after: Duration::from_millis(800),
+            pending: HashMap::new(),
         }
     }
+}
 
-    let ev = ev?;
-    let gamepad = gilrs.gamepad(ev.id);
-
-    if !can_map(&gamepad) {
-        return Some(ev);
+impl Default for LongPress {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    let mut out_event = ev.drop();
-
-    match ev.event {
-        EventType::AxisChanged(Axis::DPadX, val, _) => {
-            let mut release_left = false;
-            let mut release_right = false;
-
-            if val == 1.0 {
-                // The axis value might change from left (-1.0) to right (1.0) immediately without
-                // us getting an additional event for the release at the center position (0.0).
-                release_left = gamepad.state().is_pressed(Code(necs::BTN_DPAD_LEFT));
-
-                gilrs.insert_event(Event {
-                    event: EventType::ButtonChanged(
-                        Button::DPadRight,
-                        1.0,
-                        Code(necs::BTN_DPAD_RIGHT),
-                    ),
-                    ..ev
-                });
-                out_event = Event {
-                    event: EventType::ButtonPressed(Button::DPadRight, Code(necs::BTN_DPAD_RIGHT)),
-                    ..ev
-                };
-            } else if val == -1.0 {
-                // The axis value might change from right (1.0) to left (-1.0) immediately without
-                // us getting an additional event for the release at the center position (0.0).
-                release_right = gamepad.state().is_pressed(Code(necs::BTN_DPAD_RIGHT));
-
-                gilrs.insert_event(Event {
-                    event: EventType::ButtonChanged(
-                        Button::DPadLeft,
-                        1.0,
-                        Code(necs::BTN_DPAD_LEFT),
-                    ),
-                    ..ev
-                });
-                out_event = Event {
-                    event: EventType::ButtonPressed(Button::DPadLeft, Code(necs::BTN_DPAD_LEFT)),
-                    ..ev
-                };
-            } else {
-                release_left = gamepad.state().is_pressed(Code(necs::BTN_DPAD_LEFT));
-                release_right = gamepad.state().is_pressed(Code(necs::BTN_DPAD_RIGHT));
-            }
-
-            if release_right {
-                if !out_event.is_dropped() {
-                    gilrs.insert_event(out_event);
-                }
-
-                gilrs.insert_event(Event {
-                    event: EventType::ButtonChanged(
-                        Button::DPadRight,
-                        0.0,
-                        Code(necs::BTN_DPAD_RIGHT),
-                    ),
-                    ..ev
-                });
-                out_event = Event {
-                    event: EventType::ButtonReleased(Button::DPadRight, Code(necs::BTN_DPAD_RIGHT)),
-                    ..ev
-                };
+impl EventFilter for LongPress {
+    fn filter_event(&mut self, ev: Event, _gilrs: &mut Gilrs) -> FilterResult {
+        match ev.event {
+            EventType::ButtonPressed(btn, nec) => {
+                self.pending.insert((ev.id, nec), btn);
             }
-
-            if release_left {
-                if !out_event.is_dropped() {
-                    gilrs.insert_event(out_event);
-                }
-
-                gilrs.insert_event(Event {
-                    event: EventType::ButtonChanged(
-                        Button::DPadLeft,
-                        0.0,
-                        Code(necs::BTN_DPAD_LEFT),
-                    ),
-                    ..ev
-                });
-                out_event = Event {
-                    event: EventType::ButtonReleased(Button::DPadLeft, Code(necs::BTN_DPAD_LEFT)),
-                    ..ev
-                };
+            EventType::ButtonReleased(_, nec) => {
+                self.pending.remove(&(ev.id, nec));
             }
-
-            Some(out_event)
+            EventType::Disconnected => self.pending.retain(|&(id, _), _| id != ev.id),
+            _ => (),
         }
-        EventType::AxisChanged(Axis::DPadY, val, _) => {
-            let mut release_up = false;
-            let mut release_down = false;
-
-            if val == 1.0 {
-                // The axis value might change from down (-1.0) to up (1.0) immediately without us
-                // getting an additional event for the release at the center position (0.0).
-                release_down = gamepad.state().is_pressed(Code(necs::BTN_DPAD_DOWN));
-
-                gilrs.insert_event(Event {
-                    event: EventType::ButtonChanged(Button::DPadUp, 1.0, Code(necs::BTN_DPAD_UP)),
-                    ..ev
-                });
-                out_event = Event {
-                    event: EventType::ButtonPressed(Button::DPadUp, Code(necs::BTN_DPAD_UP)),
-                    ..ev
-                };
-            } else if val == -1.0 {
-                // The axis value might change from up (1.0) to down (-1.0) immediately without us
-                // getting an additional event for the release at the center position (0.0).
-                release_up = gamepad.state().is_pressed(Code(necs::BTN_DPAD_UP));
-
-                gilrs.insert_event(Event {
-                    event: EventType::ButtonChanged(
-                        Button::DPadDown,
-                        1.0,
-                        Code(necs::BTN_DPAD_DOWN),
-                    ),
-                    ..ev
-                });
-                out_event = Event {
-                    event: EventType::ButtonPressed(Button::DPadDown, Code(necs::BTN_DPAD_DOWN)),
-                    ..ev
-                };
-            } else {
-                release_up = gamepad.state().is_pressed(Code(necs::BTN_DPAD_UP));
-                release_down = gamepad.state().is_pressed(Code(necs::BTN_DPAD_DOWN));
-            }
 
-            if release_up {
-                if !out_event.is_dropped() {
-                    gilrs.insert_event(out_event);
-                }
+        FilterResult::Pass(ev)
+    }
 
-                gilrs.insert_event(Event {
-                    event: EventType::ButtonChanged(Button::DPadUp, 0.0, Code(necs::BTN_DPAD_UP)),
-                    ..ev
-                });
-                out_event = Event {
-                    event: EventType::ButtonReleased(Button::DPadUp, Code(necs::BTN_DPAD_UP)),
-                    ..ev
-                };
-            }
+    fn poll(&mut self, gilrs: &mut Gilrs) -> Option<Event> {
+        let now = utils::time_now();
+        let after = self.after;
 
-            if release_down {
-                if !out_event.is_dropped() {
-                    gilrs.insert_event(out_event);
-                }
+        let (id, nec, btn, time) = self.pending.iter().find_map(|(&(id, nec), &btn)| {
+            let data = button_data(gilrs, id, nec).filter(ButtonData::is_pressed)?;
+            let time = data.press_timestamp();
 
-                gilrs.insert_event(Event {
-                    event: EventType::ButtonChanged(
-                        Button::DPadDown,
-                        0.0,
-                        Code(necs::BTN_DPAD_DOWN),
-                    ),
-                    ..ev
-                });
-                out_event = Event {
-                    event: EventType::ButtonReleased(Button::DPadDown, Code(necs::BTN_DPAD_DOWN)),
-                    ..ev
-                };
-            }
+            now.duration_since(time)
+                .map_or(false, |dur| dur >= after)
+                .then_some((id, nec, btn, time))
+        })?;
+        self.pending.remove(&(id, nec));
 
-            Some(out_event)
-        }
-        _ => Some(ev),
+        Some(Event {
+            id,
+            event: EventType::ButtonLongPressed(btn, after, nec),
+            time: time + after,
+        })
     }
 }
 
-/// Repeats pressed keys.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
-pub struct Repeat {
-    pub after: Duration,
-    pub every: Duration,
+/// Emits `ChordPressed` after `ButtonPressed` that completes one of `chords`.
+///
+/// Chord is completed when all of its buttons are pressed and first of them was pressed at most
+/// `window` before last one. Times of presses are read from gamepad state, so this filter doesn't
+/// work if state updates are disabled.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Chords {
+    pub chords: Vec<Chord>,
+    pub window: Duration,
 }
 
-impl Repeat {
-    /// Creates new `Repeat` filter with `after` set to 500ms and `every` set to 30ms.
-    pub fn new() -> Self {
-        Repeat {
-            after: Duration::from_millis(500),
-            every: Duration::from_millis(30),
+impl Chords {
+    /// Creates new `Chords` filter with `window` set to 200ms.
+    pub fn new(chords: Vec<Chord>) -> Self {
+        Chords {
+            chords,
+            window: Duration::from_millis(200),
         }
     }
 }
 
-impl Default for Repeat {
-    fn default() -> Self {
-        Self::new()
-    }
-}
+impl EventFilter for Chords {
+    fn filter_event(&mut self, ev: Event, gilrs: &mut Gilrs) -> FilterResult {
+        match ev.event {
+            EventType::ButtonPressed(btn, _) if btn != Button::Unknown => {
+                let gamepad = gilrs.connected_gamepad(ev.id);
+                let is_recent = |b| {
+                    if b == btn {
+                        return true;
+                    }
 
-impl FilterFn for Repeat {
-    fn filter(&self, ev: Option<Event>, gilrs: &mut Gilrs) -> Option<Event> {
-        match ev {
-            Some(ev) => Some(ev),
-            None => {
-                let now = utils::time_now();
-                for (id, gamepad) in gilrs.gamepads() {
-                    for (nec, btn_data) in gamepad.state().buttons() {
-                        match (
-                            btn_data.is_pressed(),
-                            btn_data.is_repeating(),
-                            now.duration_since(btn_data.timestamp()),
-                        ) {
-                            (true, false, Ok(dur)) if dur >= self.after => {
-                                let btn_name = match gamepad.axis_or_btn_name(nec) {
-                                    Some(AxisOrBtn::Btn(b)) => b,
-                                    _ => Button::Unknown,
-                                };
-
-                                return Some(Event {
-                                    id,
-                                    event: EventType::ButtonRepeated(btn_name, nec),
-                                    time: btn_data.timestamp() + self.after,
-                                });
-                            }
-                            (true, true, Ok(dur)) if dur >= self.every => {
-                                let btn_name = match gamepad.axis_or_btn_name(nec) {
-                                    Some(AxisOrBtn::Btn(b)) => b,
-                                    _ => Button::Unknown,
-                                };
-
-                                return Some(Event {
-                                    id,
-                                    event: EventType::ButtonRepeated(btn_name, nec),
-                                    time: btn_data.timestamp() + self.every,
-                                });
-                            }
-                            _ => (),
-                        }
+                    match gamepad.as_ref().and_then(|g| g.button_data(b)) {
+                        Some(data) if data.is_pressed() => ev
+                            .time
+                            .duration_since(data.press_timestamp())
+                            .map_or(true, |dur| dur <= self.window),
+                        _ => false,
+                    }
+                };
+
+                let mut events: EventVec = smallvec![ev];
+                for &chord in self.chords.iter().filter(|c| c.contains(btn)) {
+                    if chord.buttons().all(&is_recent) {
+                        events.push(Event {
+                            event: EventType::ChordPressed(chord),
+                            ..ev
+                        });
                     }
                 }
-                None
+
+                FilterResult::from_events(ev, events)
             }
+            _ => FilterResult::Pass(ev),
         }
     }
 }
@@ -506,9 +1655,10 @@ pub trait Filter {
     fn filter_ev<F: FilterFn>(&self, filter: &F, gilrs: &mut Gilrs) -> Option<Event>;
 }
 
-/// Actual filter implementation.
+/// Filter implementation working on `Option<Event>`.
 ///
-/// See module level documentation for more info.
+/// This is the older filter API, kept for compatibility. New filters should implement
+/// [`EventFilter`] instead. See module level documentation for more info.
 pub trait FilterFn {
     fn filter(&self, ev: Option<Event>, gilrs: &mut Gilrs) -> Option<Event>;
 }
@@ -545,3 +1695,318 @@ impl Filter for Event {
         e
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Chords, Curve, DeadzoneSettings, DeadzoneShape, DoubleTap, DpadMode, LongPress, Repeat,
+        RepeatSettings, StickToDpad,
+    };
+    use crate::ev::{Axis, Button, Chord, Code, Event, EventType};
+    use crate::record::tests::info;
+    use crate::record::{Record, ReplayMode};
+    use crate::{GamepadId, Gilrs, GilrsBuilder};
+    use gilrs_core::native_ev_codes as nec;
+    use std::time::{Duration, SystemTime};
+
+    fn settings(shape: DeadzoneShape) -> DeadzoneSettings {
+        DeadzoneSettings {
+            shape,
+            inner: Some(0.2),
+            outer: 0.9,
+            anti: 0.0,
+        }
+    }
+
+    fn assert_close(a: (f32, f32), b: (f32, f32)) {
+        assert!(
+            (a.0 - b.0).abs() < 1e-5 && (a.1 - b.1).abs() < 1e-5,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn deadzone_shapes() {
+        use DeadzoneShape::*;
+
+        for shape in [Axial, Radial, ScaledRadial, Hybrid, BowTie] {
+            let s = settings(shape);
+            assert_close(s.apply(0.1, 0.1, 0.2), (0.0, 0.0));
+            assert_close(s.apply(-1.0, 0.0, 0.2), (-1.0, 0.0));
+        }
+
+        // Magnitude is above inner dead zone, but both axes are below it.
+        assert_close(settings(Axial).apply(0.15, 0.15, 0.2), (0.0, 0.0));
+        assert_close(settings(Hybrid).apply(0.15, 0.15, 0.2), (0.0, 0.0));
+        let (x, y) = settings(ScaledRadial).apply(0.15, 0.15, 0.2);
+        assert!(x > 0.0 && x == y);
+
+        assert_close(settings(Radial).apply(0.0, 0.45, 0.2), (0.0, 0.5));
+        assert_close(settings(ScaledRadial).apply(0.0, 0.55, 0.2), (0.0, 0.5));
+        assert_close(settings(Axial).apply(0.1, 0.55, 0.2), (0.0, 0.5));
+
+        // Bow tie dead zone for one axis grows with other axis.
+        assert_close(settings(BowTie).apply(0.15, 0.9, 0.2), (0.0, 1.0));
+        assert!(settings(BowTie).apply(0.15, 0.3, 0.2).0 > 0.0);
+
+        // Full diagonal stays at full tilt, like before dead zone settings were added.
+        assert_close(DeadzoneSettings::new().apply(1.0, 1.0, 0.2), (1.0, 1.0));
+        assert_close(DeadzoneSettings::new().apply(-1.0, 1.0, 0.0), (-1.0, 1.0));
+    }
+
+    #[test]
+    fn anti_deadzone() {
+        let s = DeadzoneSettings {
+            anti: 0.25,
+            ..settings(DeadzoneShape::ScaledRadial)
+        };
+
+        assert_close(s.apply(0.0, 0.2, 0.2), (0.0, 0.0));
+        assert_close(s.apply(0.0, 0.55, 0.2), (0.0, 0.625));
+        assert_close((s.apply_axial(-0.55, 0.2), 0.0), s.apply(-0.55, 0.0, 0.2));
+    }
+
+    #[test]
+    fn curves() {
+        assert_eq!(Curve::Linear.apply(-0.3), -0.3);
+        assert_eq!(Curve::Power(2.0).apply(-0.5), -0.25);
+        assert_eq!(Curve::SCurve(2.0).apply(0.5), 0.5);
+        assert!(Curve::SCurve(2.0).apply(0.25) < 0.25);
+        assert!(Curve::SCurve(2.0).apply(0.75) > 0.75);
+        assert_eq!(Curve::SCurve(2.0).apply(1.0), 1.0);
+
+        let table = Curve::table(vec![(1.0, 1.0), (0.1, 0.0), (0.5, 0.2)]);
+        assert_eq!(table.apply(0.05), 0.0);
+        assert_close((table.apply(0.3), table.apply(-0.75)), (0.1, -0.6));
+        assert_eq!(table.apply(1.5), 1.0);
+        assert_eq!(Curve::Table(Vec::new()).apply(0.4), 0.4);
+    }
+
+    // Some filters read gamepad state, so events need to come from existing gamepad.
+    fn gilrs_with<F: super::EventFilter + Send + 'static>(filter: F) -> Gilrs {
+        GilrsBuilder::new()
+            .with_default_filters(false)
+            .add_filter(filter)
+            .replay(vec![Record::Gamepad(info(0))], ReplayMode::AsFastAsPossible)
+            .filter_replay(true)
+            .build()
+            .unwrap()
+    }
+
+    fn event(event: EventType, time: SystemTime) -> Event {
+        Event {
+            id: GamepadId(0),
+            event,
+            time,
+        }
+    }
+
+    fn events(gilrs: &mut Gilrs) -> Vec<EventType> {
+        let mut events = Vec::new();
+        while let Some(ev) = gilrs.next_event() {
+            events.push(ev.event);
+        }
+
+        events
+    }
+
+    #[test]
+    fn double_tap() {
+        let mut gilrs = gilrs_with(DoubleTap::new());
+        let south = Code(nec::BTN_SOUTH);
+        let pressed = EventType::ButtonPressed(Button::South, south);
+        let released = EventType::ButtonReleased(Button::South, south);
+        let t = SystemTime::UNIX_EPOCH;
+
+        gilrs.insert_event(event(pressed, t));
+        gilrs.insert_event(event(released, t + Duration::from_millis(100)));
+        gilrs.insert_event(event(pressed, t + Duration::from_millis(200)));
+        gilrs.insert_event(event(pressed, t + Duration::from_millis(300)));
+        gilrs.insert_event(event(pressed, t + Duration::from_millis(1000)));
+
+        assert_eq!(
+            events(&mut gilrs),
+            [
+                pressed,
+                released,
+                pressed,
+                EventType::ButtonDoubleTapped(Button::South, south),
+                pressed,
+                pressed
+            ]
+        );
+    }
+
+    #[test]
+    fn long_press() {
+        let mut gilrs = gilrs_with(LongPress::new());
+        let south = Code(nec::BTN_SOUTH);
+        let east = Code(nec::BTN_EAST);
+        let long_ago = SystemTime::now() - Duration::from_secs(10);
+
+        gilrs.insert_event(event(
+            EventType::ButtonPressed(Button::South, south),
+            long_ago,
+        ));
+        // Value changes don't restart long press.
+        gilrs.insert_event(event(
+            EventType::ButtonChanged(Button::South, 0.9, south),
+            SystemTime::now(),
+        ));
+        gilrs.insert_event(event(
+            EventType::ButtonPressed(Button::East, east),
+            SystemTime::now(),
+        ));
+
+        assert_eq!(
+            events(&mut gilrs)[3..],
+            [EventType::ButtonLongPressed(
+                Button::South,
+                Duration::from_millis(800),
+                south
+            )]
+        );
+    }
+
+    #[test]
+    fn chords() {
+        let chord = Chord::new(&[Button::Select, Button::Start]);
+        let mut gilrs = gilrs_with(Chords::new(vec![chord]));
+        let select = EventType::ButtonPressed(Button::Select, Code(nec::BTN_SELECT));
+        let start = EventType::ButtonPressed(Button::Start, Code(nec::BTN_START));
+        let released = EventType::ButtonReleased(Button::Start, Code(nec::BTN_START));
+        let t = SystemTime::UNIX_EPOCH;
+
+        gilrs.insert_event(event(select, t));
+        gilrs.insert_event(event(start, t + Duration::from_millis(50)));
+        gilrs.insert_event(event(released, t + Duration::from_millis(100)));
+        gilrs.insert_event(event(start, t + Duration::from_millis(500)));
+
+        assert_eq!(
+            chord.buttons().collect::<Vec<_>>(),
+            [Button::Select, Button::Start]
+        );
+        assert_eq!(
+            events(&mut gilrs),
+            [
+                select,
+                start,
+                EventType::ChordPressed(chord),
+                released,
+                start
+            ]
+        );
+    }
+
+    #[test]
+    fn stick_to_dpad() {
+        use EventType::*;
+
+        let mut filter = StickToDpad::new();
+        assert_eq!(filter.directions(0.6, 0.0, 0), 0b1000);
+        assert_eq!(filter.directions(0.4, 0.0, 0), 0);
+        assert_eq!(filter.directions(0.4, 0.0, 0b1000), 0b1000);
+        assert_eq!(filter.directions(0.5, 0.5, 0), 0b1001);
+        // 20° is in sector of right, but close enough to keep diagonal pressed.
+        let (sin, cos) = 20f32.to_radians().sin_cos();
+        assert_eq!(filter.directions(cos, sin, 0), 0b1000);
+        assert_eq!(filter.directions(cos, sin, 0b1001), 0b1001);
+        filter.mode = DpadMode::FourWay;
+        assert_eq!(filter.directions(0.5, 0.6, 0), 0b0001);
+
+        let mut gilrs = gilrs_with(StickToDpad::new());
+        let x = Code(nec::AXIS_LSTICKX);
+        let right = Code(nec::BTN_DPAD_RIGHT);
+        let left = Code(nec::BTN_DPAD_LEFT);
+        let t = SystemTime::UNIX_EPOCH;
+
+        gilrs.insert_event(event(AxisChanged(Axis::LeftStickX, 0.7, x), t));
+        gilrs.insert_event(event(AxisChanged(Axis::LeftStickX, 0.4, x), t));
+        gilrs.insert_event(event(AxisChanged(Axis::LeftStickX, -0.9, x), t));
+        gilrs.insert_event(event(AxisChanged(Axis::LeftStickX, 0.0, x), t));
+
+        assert_eq!(
+            events(&mut gilrs),
+            [
+                AxisChanged(Axis::LeftStickX, 0.7, x),
+                ButtonPressed(Button::DPadRight, right),
+                ButtonChanged(Button::DPadRight, 1.0, right),
+                AxisChanged(Axis::LeftStickX, 0.4, x),
+                AxisChanged(Axis::LeftStickX, -0.9, x),
+                ButtonReleased(Button::DPadRight, right),
+                ButtonChanged(Button::DPadRight, 0.0, right),
+                ButtonPressed(Button::DPadLeft, left),
+                ButtonChanged(Button::DPadLeft, 1.0, left),
+                AxisChanged(Axis::LeftStickX, 0.0, x),
+                ButtonReleased(Button::DPadLeft, left),
+                ButtonChanged(Button::DPadLeft, 0.0, left),
+            ]
+        );
+    }
+
+    #[test]
+    fn repeat() {
+        let settings = RepeatSettings {
+            after: Duration::from_millis(500),
+            every: Duration::from_millis(200),
+            acceleration: 0.5,
+            min_every: Duration::from_millis(60),
+        };
+        assert!(Repeat::new().is_repeated(Button::LeftTrigger2));
+        assert!(Repeat::new().is_repeated(Button::Mode));
+
+        let repeat = Repeat::new()
+            .with(Button::South, settings)
+            .with_included(Some(&[Button::South, Button::Mode]))
+            .with_excluded(&[Button::Mode]);
+        assert!(repeat.is_repeated(Button::South));
+        assert!(!repeat.is_repeated(Button::East));
+        assert!(!repeat.is_repeated(Button::Mode));
+
+        let south = Code(nec::BTN_SOUTH);
+        let t = SystemTime::UNIX_EPOCH;
+        repeat.observe(&event(EventType::ButtonPressed(Button::South, south), t));
+        repeat.observe(&event(
+            EventType::ButtonPressed(Button::East, Code(nec::BTN_EAST)),
+            t,
+        ));
+
+        let now = t + Duration::from_millis(1000);
+        let mut times = Vec::new();
+        while let Some(ev) = repeat.poll_at(now) {
+            assert_eq!(ev.event, EventType::ButtonRepeated(Button::South, south));
+            times.push(ev.time.duration_since(t).unwrap().as_millis());
+        }
+        assert_eq!(times, [500, 700, 800, 860, 920, 980]);
+
+        repeat.observe(&event(EventType::ButtonReleased(Button::South, south), now));
+        assert_eq!(repeat.poll_at(now + Duration::from_secs(1)), None);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn repeat_with_timing() {
+        let repeat = Repeat::with_timing(Duration::from_millis(100), Duration::from_millis(50));
+        assert_eq!(repeat.after, Duration::from_millis(100));
+        assert_eq!(repeat.every, Duration::from_millis(50));
+        assert_eq!(
+            repeat.settings(Button::South).every,
+            Duration::from_millis(50)
+        );
+
+        let lt2 = Code(nec::BTN_LT2);
+        let t = SystemTime::UNIX_EPOCH;
+        repeat.observe(&event(
+            EventType::ButtonPressed(Button::LeftTrigger2, lt2),
+            t,
+        ));
+
+        let mut times = Vec::new();
+        while let Some(ev) = repeat.poll_at(t + Duration::from_millis(200)) {
+            times.push(ev.time.duration_since(t).unwrap().as_millis());
+        }
+        assert_eq!(times, [100, 150, 200]);
+    }
+}
@@ -12,10 +12,10 @@ pub mod state;
 
 use std::{
     fmt::{Display, Formatter, Result as FmtResult},
-    time::SystemTime,
+    time::{Duration, SystemTime},
 };
 
-use crate::{constants::*, gamepad::GamepadId, utils};
+use crate::{constants::*, ff::EffectId, gamepad::GamepadId, utils};
 
 #[cfg(feature = "serde-serialize")]
 use serde::{Deserialize, Serialize};
@@ -101,8 +101,18 @@ pub enum EventType {
     Disconnected,
     /// There was an `Event`, but it was dropped by one of filters. You should ignore it.
     Dropped,
-    /// A force feedback effect has ran for its duration and stopped.
-    ForceFeedbackEffectCompleted,
+    /// A force feedback effect has ran for its duration and stopped. Emitted once for every
+    /// gamepad that effect was played on.
+    ForceFeedbackEffectCompleted(EffectId),
+    /// Button was pressed twice in short time. This event can be generated by
+    /// [`DoubleTap`](filter/struct.DoubleTap.html) event filter.
+    ButtonDoubleTapped(Button, Code),
+    /// Button has been held for given duration. This event can be generated by
+    /// [`LongPress`](filter/struct.LongPress.html) event filter.
+    ButtonLongPressed(Button, Duration, Code),
+    /// All buttons of chord have been pressed together. This event can be generated by
+    /// [`Chords`](filter/struct.Chords.html) event filter.
+    ChordPressed(Chord),
 }
 
 #[repr(u16)]
@@ -249,6 +259,67 @@ impl Axis {
     }
 }
 
+/// Set of buttons pressed together, like Select+Start.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct Chord(u32);
+
+impl Chord {
+    /// Creates chord from `buttons`. `Button::Unknown` is ignored.
+    pub fn new(buttons: &[Button]) -> Self {
+        Chord(
+            buttons
+                .iter()
+                .filter(|&&btn| btn != Button::Unknown)
+                .fold(0, |bits, &btn| bits | 1 << btn as u16),
+        )
+    }
+
+    /// Returns true if `btn` is part of chord.
+    pub fn contains(self, btn: Button) -> bool {
+        btn != Button::Unknown && self.0 & 1 << btn as u16 != 0
+    }
+
+    /// Returns number of buttons in chord.
+    pub fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// Returns true if chord doesn't have any buttons.
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns iterator over buttons in chord.
+    pub fn buttons(self) -> impl Iterator<Item = Button> {
+        use crate::Button::*;
+
+        [
+            South,
+            East,
+            North,
+            West,
+            C,
+            Z,
+            LeftTrigger,
+            LeftTrigger2,
+            RightTrigger,
+            RightTrigger2,
+            Select,
+            Start,
+            Mode,
+            LeftThumb,
+            RightThumb,
+            DPadUp,
+            DPadDown,
+            DPadLeft,
+            DPadRight,
+        ]
+        .into_iter()
+        .filter(move |&btn| self.contains(btn))
+    }
+}
+
 /// Represents `Axis` or `Button`.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 #[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
@@ -5,7 +5,7 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
-use crate::ev::Code;
+use crate::ev::{Axis, Button, Code};
 
 use fnv::FnvHashMap;
 
@@ -13,13 +13,23 @@ use std::collections::hash_map;
 use std::iter::Iterator;
 use std::time::SystemTime;
 
+const BUTTON_COUNT: usize = Button::DPadRight as usize + 1;
+const AXIS_COUNT: usize = Axis::DPadY as usize + 1;
+
 /// Cached gamepad state.
+///
+/// State is stored both by `Code` and by `Button` or `Axis` from events, so it can be examined
+/// without looking up `Code` in gamepad's mapping.
 #[derive(Clone, Debug)]
 pub struct GamepadState {
     // Indexed by EvCode (nec)
     buttons: FnvHashMap<Code, ButtonData>,
     // Indexed by EvCode (nec)
     axes: FnvHashMap<Code, AxisData>,
+    // Indexed by Button
+    named_buttons: [Option<ButtonData>; BUTTON_COUNT],
+    // Indexed by Axis
+    named_axes: [Option<AxisData>; AXIS_COUNT],
 }
 
 impl GamepadState {
@@ -27,6 +37,8 @@ impl GamepadState {
         GamepadState {
             buttons: FnvHashMap::default(),
             axes: FnvHashMap::default(),
+            named_buttons: [None; BUTTON_COUNT],
+            named_axes: [None; AXIS_COUNT],
         }
     }
 
@@ -69,9 +81,34 @@ impl GamepadState {
         self.axes.get(&axis)
     }
 
+    /// Returns state of button that was reported in events as `btn`. Always returns `None` for
+    /// `Button::Unknown`.
+    pub fn named_button_data(&self, btn: Button) -> Option<&ButtonData> {
+        match btn {
+            Button::Unknown => None,
+            _ => self.named_buttons[btn as usize].as_ref(),
+        }
+    }
+
+    /// Returns state of axis that was reported in events as `axis`. Always returns `None` for
+    /// `Axis::Unknown`.
+    pub fn named_axis_data(&self, axis: Axis) -> Option<&AxisData> {
+        match axis {
+            Axis::Unknown => None,
+            _ => self.named_axes[axis as usize].as_ref(),
+        }
+    }
+
+    fn set_named_btn(&mut self, name: Button, data: ButtonData) {
+        if name != Button::Unknown {
+            self.named_buttons[name as usize] = Some(data);
+        }
+    }
+
     pub(crate) fn set_btn_pressed(
         &mut self,
         btn: Code,
+        name: Button,
         pressed: bool,
         counter: u64,
         timestamp: SystemTime,
@@ -89,9 +126,20 @@ impl GamepadState {
         data.is_repeating = false;
         data.counter = counter;
         data.last_event_ts = timestamp;
+        if pressed {
+            data.press_ts = timestamp;
+        }
+        let data = *data;
+        self.set_named_btn(name, data);
     }
 
-    pub(crate) fn set_btn_repeating(&mut self, btn: Code, counter: u64, timestamp: SystemTime) {
+    pub(crate) fn set_btn_repeating(
+        &mut self,
+        btn: Code,
+        name: Button,
+        counter: u64,
+        timestamp: SystemTime,
+    ) {
         let data = self
             .buttons
             .entry(btn)
@@ -99,11 +147,14 @@ impl GamepadState {
         data.is_repeating = true;
         data.counter = counter;
         data.last_event_ts = timestamp;
+        let data = *data;
+        self.set_named_btn(name, data);
     }
 
     pub(crate) fn set_btn_value(
         &mut self,
         btn: Code,
+        name: Button,
         value: f32,
         counter: u64,
         timestamp: SystemTime,
@@ -115,10 +166,15 @@ impl GamepadState {
         data.value = value;
         data.counter = counter;
         data.last_event_ts = timestamp;
+        let data = *data;
+        self.set_named_btn(name, data);
     }
 
-    pub(crate) fn update_axis(&mut self, axis: Code, data: AxisData) {
+    pub(crate) fn update_axis(&mut self, axis: Code, name: Axis, data: AxisData) {
         self.axes.insert(axis, data);
+        if name != Axis::Unknown {
+            self.named_axes[name as usize] = Some(data);
+        }
     }
 }
 
@@ -148,6 +204,7 @@ impl<'a> Iterator for AxisDataIter<'a> {
 #[derive(Clone, Copy, Debug)]
 pub struct ButtonData {
     last_event_ts: SystemTime,
+    press_ts: SystemTime,
     counter: u64,
     value: f32,
     is_pressed: bool,
@@ -164,6 +221,7 @@ impl ButtonData {
     ) -> Self {
         ButtonData {
             last_event_ts: time,
+            press_ts: time,
             counter,
             value,
             is_pressed: pressed,
@@ -195,6 +253,12 @@ impl ButtonData {
     pub fn timestamp(&self) -> SystemTime {
         self.last_event_ts
     }
+
+    /// Returns when button was last pressed. Unlike `timestamp()`, it's not changed by value
+    /// changes and repeats.
+    pub fn press_timestamp(&self) -> SystemTime {
+        self.press_ts
+    }
 }
 
 /// Information about axis stored in `State`.
@@ -228,3 +292,36 @@ impl AxisData {
         self.last_event_ts
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{AxisData, GamepadState};
+    use crate::ev::{Axis, Button, Code};
+    use gilrs_core::native_ev_codes as nec;
+    use std::time::SystemTime;
+
+    #[test]
+    fn named_state() {
+        let mut state = GamepadState::new();
+        let t = SystemTime::UNIX_EPOCH;
+        let south = Code(nec::BTN_SOUTH);
+        let x = Code(nec::AXIS_LSTICKX);
+
+        state.set_btn_pressed(south, Button::South, true, 1, t);
+        state.set_btn_value(south, Button::South, 0.8, 2, t);
+        state.set_btn_pressed(Code(nec::BTN_EAST), Button::Unknown, true, 2, t);
+        state.update_axis(x, Axis::LeftStickX, AxisData::new(0.5, 3, t));
+
+        let data = state.named_button_data(Button::South).unwrap();
+        assert!(data.is_pressed());
+        assert_eq!(data.value(), 0.8);
+        assert_eq!(data.counter(), 2);
+        assert!(state.named_button_data(Button::East).is_none());
+        assert!(state.named_button_data(Button::Unknown).is_none());
+        assert_eq!(
+            state.named_axis_data(Axis::LeftStickX).unwrap().value(),
+            0.5
+        );
+        assert_eq!(state.value(x), 0.5);
+    }
+}
@@ -5,26 +5,195 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+#[cfg(feature = "serde-serialize")]
+use serde::{Deserialize, Serialize};
+use std::f32;
 use std::ops::Mul;
 
-use super::time::Ticks;
+use super::time::{Ticks, TICK_DURATION};
+
+/// Motor of gamepad.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum Motor {
+    Weak,
+    Strong,
+}
+
+/// Shape of periodic [`Waveform`](enum.Waveform.html).
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum WaveShape {
+    Sine,
+    Square,
+    Triangle,
+    SawtoothUp,
+    SawtoothDown,
+}
+
+impl WaveShape {
+    /// Returns value in range [-1.0, 1.0] at `x` in range [0.0, 1.0) of period.
+    fn at(self, x: f32) -> f32 {
+        match self {
+            WaveShape::Sine => (x * 2.0 * f32::consts::PI).sin(),
+            WaveShape::Square if x < 0.5 => 1.0,
+            WaveShape::Square => -1.0,
+            WaveShape::Triangle if x < 0.25 => 4.0 * x,
+            WaveShape::Triangle if x < 0.75 => 2.0 - 4.0 * x,
+            WaveShape::Triangle => 4.0 * x - 4.0,
+            WaveShape::SawtoothUp => 2.0 * x - 1.0,
+            WaveShape::SawtoothDown => 1.0 - 2.0 * x,
+        }
+    }
+}
+
+/// Magnitude of motor as function of time.
+///
+/// Time is counted from the start of current playback of base effect, so waveform is restarted on
+/// every repetition from [`Replay`](struct.Replay.html).
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum Waveform {
+    /// Constant magnitude.
+    Constant { magnitude: u16 },
+    /// Periodic wave. Magnitude is `offset + magnitude * wave(t + phase)`, where `wave` is in range
+    /// [-1.0, 1.0], clamped to [0, u16::MAX].
+    Periodic {
+        shape: WaveShape,
+        period: Ticks,
+        phase: Ticks,
+        magnitude: u16,
+        offset: u16,
+    },
+    /// Linear change from `start` to `end` during `play_for` from `Replay`.
+    Ramp { start: u16, end: u16 },
+    /// Linear interpolation between `(time, magnitude)` points, sorted by time. Magnitude before
+    /// first and after last point is constant.
+    Keyframes(Vec<(Ticks, u16)>),
+}
+
+impl Waveform {
+    /// Creates `Keyframes` waveform from unsorted points.
+    pub fn keyframes(mut points: Vec<(Ticks, u16)>) -> Self {
+        points.sort_by_key(|&(t, _)| t);
+        Waveform::Keyframes(points)
+    }
+
+    /// Returns magnitude at `ticks` since start of playback that lasts `dur`.
+    pub(super) fn at(&self, ticks: Ticks, dur: Ticks) -> u16 {
+        let val = match *self {
+            Waveform::Constant { magnitude } => magnitude as f32,
+            Waveform::Periodic {
+                shape,
+                period,
+                phase,
+                magnitude,
+                offset,
+            } => {
+                let wave = if period.0 == 0 {
+                    0.0
+                } else {
+                    let x = ((ticks + phase) % period).0 as f32 / period.0 as f32;
+                    shape.at(x)
+                };
+
+                offset as f32 + magnitude as f32 * wave
+            }
+            Waveform::Ramp { start, end } => {
+                let x = if dur.0 == 0 {
+                    1.0
+                } else {
+                    (ticks.0 as f32 / dur.0 as f32).min(1.0)
+                };
+
+                lerp(start, end, x)
+            }
+            Waveform::Keyframes(ref points) => {
+                let next = points.iter().position(|&(t, _)| t > ticks);
+                match next {
+                    Some(0) => points[0].1 as f32,
+                    Some(i) => {
+                        let (t0, m0) = points[i - 1];
+                        let (t1, m1) = points[i];
+                        lerp(m0, m1, (ticks - t0).0 as f32 / (t1 - t0).0 as f32)
+                    }
+                    None => points.last().map_or(0.0, |&(_, m)| m as f32),
+                }
+            }
+        };
+
+        val.max(0.0).min(u16::MAX as f32) as u16
+    }
+}
+
+fn lerp(from: u16, to: u16, x: f32) -> f32 {
+    from as f32 + (to as f32 - from as f32) * x
+}
+
+impl Mul<f32> for Waveform {
+    type Output = Waveform;
+
+    fn mul(self, rhs: f32) -> Self::Output {
+        let scale = |mg: u16| (mg as f32 * rhs) as u16;
+        match self {
+            Waveform::Constant { magnitude } => Waveform::Constant {
+                magnitude: scale(magnitude),
+            },
+            Waveform::Periodic {
+                shape,
+                period,
+                phase,
+                magnitude,
+                offset,
+            } => Waveform::Periodic {
+                shape,
+                period,
+                phase,
+                magnitude: scale(magnitude),
+                offset: scale(offset),
+            },
+            Waveform::Ramp { start, end } => Waveform::Ramp {
+                start: scale(start),
+                end: scale(end),
+            },
+            Waveform::Keyframes(points) => {
+                Waveform::Keyframes(points.into_iter().map(|(t, m)| (t, scale(m))).collect())
+            }
+        }
+    }
+}
 
 /// Kind of [`BaseEffect`](struct.BaseEffect.html).
 ///
 /// Currently base effect support only xinput model of force feedback, which means that  gamepad
 /// have weak and strong motor.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
 #[non_exhaustive]
 pub enum BaseEffectType {
-    Weak { magnitude: u16 },
-    Strong { magnitude: u16 },
+    Weak {
+        magnitude: u16,
+    },
+    Strong {
+        magnitude: u16,
+    },
+    /// Magnitude of `motor` changes over time.
+    Waveform {
+        motor: Motor,
+        waveform: Waveform,
+    },
 }
 
 impl BaseEffectType {
-    fn magnitude(&self) -> u16 {
+    /// Returns motor and magnitude at `ticks` since start of playback that lasts `dur`.
+    fn at(&self, ticks: Ticks, dur: Ticks) -> (Motor, u16) {
         match *self {
-            BaseEffectType::Weak { magnitude } => magnitude,
-            BaseEffectType::Strong { magnitude } => magnitude,
+            BaseEffectType::Weak { magnitude } => (Motor::Weak, magnitude),
+            BaseEffectType::Strong { magnitude } => (Motor::Strong, magnitude),
+            BaseEffectType::Waveform {
+                motor,
+                ref waveform,
+            } => (motor, waveform.at(ticks, dur)),
         }
     }
 }
@@ -33,10 +202,17 @@ impl Mul<f32> for BaseEffectType {
     type Output = BaseEffectType;
 
     fn mul(self, rhs: f32) -> Self::Output {
-        let mg = (self.magnitude() as f32 * rhs) as u16;
         match self {
-            BaseEffectType::Weak { .. } => BaseEffectType::Weak { magnitude: mg },
-            BaseEffectType::Strong { .. } => BaseEffectType::Strong { magnitude: mg },
+            BaseEffectType::Weak { magnitude } => BaseEffectType::Weak {
+                magnitude: (magnitude as f32 * rhs) as u16,
+            },
+            BaseEffectType::Strong { magnitude } => BaseEffectType::Strong {
+                magnitude: (magnitude as f32 * rhs) as u16,
+            },
+            BaseEffectType::Waveform { motor, waveform } => BaseEffectType::Waveform {
+                motor,
+                waveform: waveform * rhs,
+            },
         }
     }
 }
@@ -51,32 +227,43 @@ impl Default for BaseEffectType {
 ///
 /// For each base effect you can specify it's type, for how long should it be played and it's
 /// strength during playback.
-#[derive(Copy, Clone, PartialEq, Debug, Default)]
+#[derive(Clone, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde-serialize", serde(default))]
 pub struct BaseEffect {
     /// Type of base effect.
     pub kind: BaseEffectType,
     /// Defines playback duration and delays between each repetition.
     pub scheduling: Replay,
-    // TODO: maybe allow other f(t)?
     /// Basic attenuation function.
     pub envelope: Envelope,
 }
 
 impl BaseEffect {
-    /// Returns `Weak` or `Strong` after applying envelope.
-    pub(super) fn magnitude_at(&self, ticks: Ticks) -> BaseEffectType {
-        if let Some(wrapped) = self.scheduling.wrap(ticks) {
-            let att =
-                self.scheduling.at(wrapped) * self.envelope.at(wrapped, self.scheduling.play_for);
-            self.kind * att
-        } else {
-            self.kind * 0.0
+    /// Returns motor and its magnitude after applying envelope.
+    pub(super) fn magnitude_at(&self, ticks: Ticks) -> (Motor, u16) {
+        let play_for = self.scheduling.play_for;
+        match self.scheduling.wrap(ticks) {
+            Some(wrapped) if wrapped < play_for => {
+                let (motor, magnitude) = self.kind.at(wrapped, play_for);
+                let att = self.scheduling.at(wrapped) * self.envelope.at(wrapped, play_for);
+                (motor, (magnitude as f32 * att) as u16)
+            }
+            _ => (self.kind.at(Ticks(0), play_for).0, 0),
         }
     }
+
+    /// Returns true if effect plays for non-zero time and its envelope fits in that time.
+    pub(super) fn is_valid(&self) -> bool {
+        let play_for = self.scheduling.play_for;
+
+        play_for > Ticks(0) && self.envelope.attack_length + self.envelope.fade_length < play_for
+    }
 }
 
 // TODO: Image with "envelope"
 #[derive(Copy, Clone, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
 /// Envelope shaped attenuation(time) function.
 pub struct Envelope {
     pub attack_length: Ticks,
@@ -111,6 +298,7 @@ impl Envelope {
 ///  after   play_for   with_delay   play_for   with_delay   play_for
 /// ```
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
 pub struct Replay {
     /// Start playback `after` ticks after `Effect::play()` is called.
     pub after: Ticks,
@@ -134,9 +322,12 @@ impl Replay {
         self.play_for + self.with_delay
     }
 
-    /// Returns `None` if effect hasn't started; or wrapped value
+    /// Returns `None` if effect hasn't started or has zero duration; or wrapped value
     fn wrap(&self, ticks: Ticks) -> Option<Ticks> {
-        ticks.checked_sub(self.after).map(|t| t % self.dur())
+        ticks
+            .checked_sub(self.after)
+            .and_then(|t| t.0.checked_rem(self.dur().0))
+            .map(Ticks)
     }
 }
 
@@ -144,7 +335,7 @@ impl Default for Replay {
     fn default() -> Self {
         Replay {
             after: Ticks(0),
-            play_for: Ticks(1),
+            play_for: Ticks(TICK_DURATION.into()),
             with_delay: Ticks(0),
         }
     }
@@ -0,0 +1,77 @@
+// Copyright 2016-2018 Mateusz Sieczko and other GilRs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+#[cfg(feature = "serde-serialize")]
+use serde::{Deserialize, Serialize};
+
+use super::base_effect::BaseEffect;
+use super::effect_source::DistanceModel;
+use super::time::Repeat;
+
+/// Description of force feedback effect that can be saved to and loaded from file.
+///
+/// With `serde-serialize` feature clip can be (de)serialized with any serde format, for example
+/// JSON or RON. All fields are optional in serialized form. `Ticks` are stored as milliseconds.
+///
+/// Clip doesn't contain gamepads and position, these have to be set on
+/// [`EffectBuilder`](struct.EffectBuilder.html) created by
+/// [`EffectBuilder::from_clip()`](struct.EffectBuilder.html#method.from_clip).
+///
+/// ```
+/// # #[cfg(feature = "serde-serialize")] {
+/// use gilrs::ff::{BaseEffectType, HapticClip, Repeat};
+///
+/// let clip = HapticClip::from_json(
+///     r#"{
+///         "base_effects": [{ "kind": { "Strong": { "magnitude": 40000 } } }],
+///         "repeat": { "For": 1000 },
+///         "gain": 0.5
+///     }"#,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(clip.base_effects[0].kind, BaseEffectType::Strong { magnitude: 40_000 });
+/// assert_eq!(clip.repeat, Repeat::For(gilrs::ff::Ticks::from_ms(1000)));
+/// # }
+/// ```
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde-serialize", serde(default))]
+pub struct HapticClip {
+    pub base_effects: Vec<BaseEffect>,
+    pub repeat: Repeat,
+    pub distance_model: DistanceModel,
+    pub gain: f32,
+    pub priority: u8,
+}
+
+impl HapticClip {
+    /// Parses clip from JSON.
+    #[cfg(feature = "serde-serialize")]
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Serializes clip to pretty printed JSON.
+    #[cfg(feature = "serde-serialize")]
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl Default for HapticClip {
+    /// Creates empty clip with the same defaults as `EffectBuilder::new()`.
+    fn default() -> Self {
+        HapticClip {
+            base_effects: Vec::new(),
+            repeat: Repeat::Infinitely,
+            distance_model: DistanceModel::None,
+            gain: 1.0,
+            priority: 0,
+        }
+    }
+}
@@ -5,13 +5,14 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+#[cfg(feature = "serde-serialize")]
+use serde::{Deserialize, Serialize};
 use std::error::Error;
+use std::fmt;
 use std::ops::{AddAssign, Mul};
-use std::{fmt, mem, u16};
+use std::sync::Mutex;
 
-use crate::{Event, EventType, GamepadId};
-
-use super::base_effect::{BaseEffect, BaseEffectType};
+use super::base_effect::{BaseEffect, Motor};
 use super::time::{Repeat, Ticks};
 
 use vec_map::VecMap;
@@ -25,6 +26,7 @@ use vec_map::VecMap;
 /// Make sure that all parameters are ≥ 0. Additionally `Linear` and `LinearClamped` models don't
 /// like if `ref_distance == max_distance` while others would prefer `ref_distance > 0`.
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
 pub enum DistanceModel {
     /// Effect is not attenuated by distance.
     #[default]
@@ -234,10 +236,76 @@ impl fmt::Display for DistanceModelError {
     }
 }
 
+/// Playback state of [`Effect`](struct.Effect.html).
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum PlaybackState {
+    Playing,
+    /// Effect was paused and can be resumed.
+    Paused,
+    /// Effect was never played or it was stopped.
+    Stopped,
+    /// Effect reached its end. See [`Repeat`](enum.Repeat.html).
+    Finished,
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub(super) enum EffectState {
-    Playing { since: Ticks },
+    /// Playing since `since` tick, started from `offset`.
+    Playing {
+        since: Ticks,
+        offset: Ticks,
+    },
+    Paused {
+        elapsed: Ticks,
+    },
     Stopped,
+    Finished {
+        elapsed: Ticks,
+    },
+}
+
+impl EffectState {
+    /// Returns playback position at `tick`.
+    pub(super) fn elapsed(self, tick: Ticks) -> Ticks {
+        match self {
+            EffectState::Playing { since, offset } => {
+                debug_assert!(tick >= since);
+                tick - since + offset
+            }
+            EffectState::Paused { elapsed } | EffectState::Finished { elapsed } => elapsed,
+            EffectState::Stopped => Ticks(0),
+        }
+    }
+
+    fn playback_state(self) -> PlaybackState {
+        match self {
+            EffectState::Playing { .. } => PlaybackState::Playing,
+            EffectState::Paused { .. } => PlaybackState::Paused,
+            EffectState::Stopped => PlaybackState::Stopped,
+            EffectState::Finished { .. } => PlaybackState::Finished,
+        }
+    }
+}
+
+/// Playback state and position of effect, shared between force feedback thread and `Effect`
+/// handles.
+#[derive(Debug)]
+pub(crate) struct SharedState(Mutex<(PlaybackState, Ticks)>);
+
+impl SharedState {
+    pub(super) fn new() -> Self {
+        SharedState(Mutex::new((PlaybackState::Stopped, Ticks(0))))
+    }
+
+    pub(super) fn store(&self, state: EffectState, tick: Ticks) {
+        let value = (state.playback_state(), state.elapsed(tick));
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = value;
+    }
+
+    pub(super) fn load(&self) -> (PlaybackState, Ticks) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
 }
 
 #[derive(Clone, PartialEq, Debug)]
@@ -249,8 +317,10 @@ pub(crate) struct EffectSource {
     pub(super) distance_model: DistanceModel,
     pub(super) position: [f32; 3],
     pub(super) gain: f32,
+    pub(super) priority: u8,
     pub(super) state: EffectState,
-    pub(super) completion_events: Vec<Event>,
+    /// Set when effect stops because it reached its end.
+    pub(super) completed: bool,
 }
 
 impl EffectSource {
@@ -269,29 +339,68 @@ impl EffectSource {
             distance_model: dist_model,
             position,
             gain,
+            priority: 0,
             state: EffectState::Stopped,
-            completion_events: vec![],
+            completed: false,
+        }
+    }
+
+    /// Starts playback from the beginning.
+    pub(super) fn play(&mut self, tick: Ticks) {
+        self.state = EffectState::Playing {
+            since: tick,
+            offset: Ticks(0),
+        };
+    }
+
+    pub(super) fn stop(&mut self) {
+        self.state = EffectState::Stopped;
+    }
+
+    /// Pauses playing effect.
+    pub(super) fn pause(&mut self, tick: Ticks) {
+        if let EffectState::Playing { .. } = self.state {
+            self.state = EffectState::Paused {
+                elapsed: self.state.elapsed(tick),
+            };
         }
     }
 
+    /// Resumes paused effect.
+    pub(super) fn resume(&mut self, tick: Ticks) {
+        if let EffectState::Paused { elapsed } = self.state {
+            self.state = EffectState::Playing {
+                since: tick,
+                offset: elapsed,
+            };
+        }
+    }
+
+    /// Moves playback position to `to`. Effect that is not playing is paused at `to`.
+    pub(super) fn seek(&mut self, tick: Ticks, to: Ticks) {
+        self.state = match self.state {
+            EffectState::Playing { .. } => EffectState::Playing {
+                since: tick,
+                offset: to,
+            },
+            _ => EffectState::Paused { elapsed: to },
+        };
+    }
+
     pub(super) fn combine_base_effects(&mut self, ticks: Ticks, actor_pos: [f32; 3]) -> Magnitude {
         let ticks = match self.state {
-            EffectState::Playing { since } => {
-                debug_assert!(ticks >= since);
-                ticks - since
-            }
-            EffectState::Stopped => return Magnitude::zero(),
+            EffectState::Playing { .. } => self.state.elapsed(ticks),
+            _ => return Magnitude::zero(),
         };
 
-        match self.repeat {
-            Repeat::For(max_dur) if ticks > max_dur => {
-                self.state = EffectState::Stopped;
-                self.devices.keys().for_each(|id| {
-                    let event = Event::new(GamepadId(id), EventType::ForceFeedbackEffectCompleted);
-                    self.completion_events.push(event);
-                });
-            }
-            _ => (),
+        let ended = match self.repeat {
+            Repeat::For(max_dur) => ticks > max_dur,
+            Repeat::Once => ticks >= self.natural_end(),
+            Repeat::Infinitely => false,
+        };
+        if ended {
+            self.state = EffectState::Finished { elapsed: ticks };
+            self.completed = true;
         }
 
         let attenuation = self
@@ -305,10 +414,10 @@ impl EffectSource {
         let mut final_magnitude = Magnitude::zero();
         for effect in &self.base_effects {
             match effect.magnitude_at(ticks) {
-                BaseEffectType::Strong { magnitude } => {
+                (Motor::Strong, magnitude) => {
                     final_magnitude.strong = final_magnitude.strong.saturating_add(magnitude)
                 }
-                BaseEffectType::Weak { magnitude } => {
+                (Motor::Weak, magnitude) => {
                     final_magnitude.weak = final_magnitude.weak.saturating_add(magnitude)
                 }
             };
@@ -316,13 +425,18 @@ impl EffectSource {
         final_magnitude * attenuation
     }
 
-    pub(super) fn flush_completion_events(&mut self) -> Vec<Event> {
-        mem::take(&mut self.completion_events)
+    /// Returns time after which all base effects finished their first playback.
+    pub(super) fn natural_end(&self) -> Ticks {
+        self.base_effects
+            .iter()
+            .map(|e| e.scheduling.after + e.scheduling.play_for)
+            .max()
+            .unwrap_or_default()
     }
 }
 
 /// (strong, weak) pair.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub(super) struct Magnitude {
     pub strong: u16,
     pub weak: u16,
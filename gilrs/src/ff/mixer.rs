@@ -0,0 +1,125 @@
+// Copyright 2016-2018 Mateusz Sieczko and other GilRs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+#[cfg(feature = "serde-serialize")]
+use serde::{Deserialize, Serialize};
+
+use super::effect_source::Magnitude;
+
+use vec_map::VecMap;
+
+/// Decides how effects playing on the same gamepad are combined.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum MixingPolicy {
+    /// Magnitudes of all effects are added.
+    #[default]
+    Sum,
+    /// Strongest magnitude of each motor is used.
+    Max,
+    /// Only effects with the highest priority are played; their magnitudes are added.
+    HighestPriority,
+    /// Magnitudes of all effects are added, but effects with lower than the highest priority are
+    /// attenuated by `percent`%.
+    Duck { percent: u8 },
+}
+
+/// Mixing settings of gamepad. See
+/// [`Gamepad::set_ff_mixing()`](../struct.Gamepad.html#method.set_ff_mixing).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct Mixing {
+    pub policy: MixingPolicy,
+    /// Maximum number of effects played at once. If more effects are audible, the ones with
+    /// lowest priority are skipped; from effects with the same priority, the newest are played.
+    pub max_effects: Option<usize>,
+}
+
+/// Magnitude of single effect on one gamepad.
+#[derive(Copy, Clone, Debug)]
+pub(super) struct Voice {
+    pub priority: u8,
+    /// Newer effects have higher value.
+    pub order: usize,
+    pub magnitude: Magnitude,
+}
+
+/// Mixing settings and gains of all gamepads.
+#[derive(Debug)]
+pub(super) struct Mixer {
+    gain: f32,
+    gamepads: VecMap<(Mixing, f32)>,
+}
+
+impl Mixer {
+    pub fn new() -> Self {
+        Mixer {
+            gain: 1.0,
+            gamepads: VecMap::new(),
+        }
+    }
+
+    pub fn set_gain(&mut self, gain: f32) {
+        self.gain = gain;
+    }
+
+    pub fn set_mixing(&mut self, gamepad: usize, mixing: Mixing) {
+        self.gamepads
+            .entry(gamepad)
+            .or_insert((Mixing::default(), 1.0))
+            .0 = mixing;
+    }
+
+    pub fn set_gamepad_gain(&mut self, gamepad: usize, gain: f32) {
+        self.gamepads
+            .entry(gamepad)
+            .or_insert((Mixing::default(), 1.0))
+            .1 = gain;
+    }
+
+    /// Combines `voices` playing on `gamepad`. Silent voices are ignored.
+    pub fn mix(&self, gamepad: usize, voices: &mut Vec<Voice>) -> Magnitude {
+        let (mixing, gamepad_gain) = self
+            .gamepads
+            .get(gamepad)
+            .cloned()
+            .unwrap_or((Mixing::default(), 1.0));
+
+        voices.retain(|voice| voice.magnitude != Magnitude::zero());
+        voices.sort_by(|a, b| {
+            b.priority
+                .cmp(&a.priority)
+                .then_with(|| b.order.cmp(&a.order))
+        });
+        if let Some(max) = mixing.max_effects {
+            voices.truncate(max);
+        }
+
+        let top = voices.first().map_or(0, |voice| voice.priority);
+        let mut magnitude = Magnitude::zero();
+        for voice in voices.iter() {
+            match mixing.policy {
+                MixingPolicy::Sum => magnitude += voice.magnitude,
+                MixingPolicy::Max => {
+                    magnitude.strong = magnitude.strong.max(voice.magnitude.strong);
+                    magnitude.weak = magnitude.weak.max(voice.magnitude.weak);
+                }
+                MixingPolicy::HighestPriority if voice.priority == top => {
+                    magnitude += voice.magnitude
+                }
+                MixingPolicy::HighestPriority => (),
+                MixingPolicy::Duck { .. } if voice.priority == top => magnitude += voice.magnitude,
+                MixingPolicy::Duck { percent } => {
+                    let att = 1.0 - f32::from(percent.min(100)) / 100.0;
+                    magnitude += voice.magnitude * att;
+                }
+            }
+        }
+
+        magnitude * (self.gain * gamepad_gain)
+    }
+}
@@ -53,28 +53,63 @@
 //! See [`examples/ff_pos.rs`](https://gitlab.com/gilrs-project/gilrs/blob/v0.10.3/examples/ff_pos.rs) for
 //! more advanced example.
 mod base_effect;
+mod clip;
 mod effect_source;
+mod mixer;
 pub(crate) mod server;
 mod time;
 
-pub use self::base_effect::{BaseEffect, BaseEffectType, Envelope, Replay};
-pub use self::effect_source::{DistanceModel, DistanceModelError};
-#[allow(unused_imports)]
+pub use self::base_effect::{
+    BaseEffect, BaseEffectType, Envelope, Motor, Replay, WaveShape, Waveform,
+};
+pub use self::clip::HapticClip;
+pub use self::effect_source::{DistanceModel, DistanceModelError, PlaybackState};
+pub use self::mixer::{Mixing, MixingPolicy};
 pub(crate) use self::time::TICK_DURATION;
 pub use self::time::{Repeat, Ticks};
 
+#[cfg(feature = "serde-serialize")]
+use serde::{Deserialize, Serialize};
+
 use std::error::Error as StdError;
 use std::hash::{Hash, Hasher};
-use std::sync::mpsc::{SendError, Sender};
+use std::sync::mpsc::{self, Receiver, SendError, Sender};
+use std::sync::Arc;
 use std::{f32, fmt};
 
-use self::effect_source::EffectSource;
+use self::effect_source::{EffectSource, SharedState};
 use crate::ff::server::Message;
 use crate::gamepad::{Gamepad, GamepadId, Gilrs};
 use crate::utils;
 
 use vec_map::VecMap;
 
+/// Decides how force feedback effects are updated. See
+/// [`GilrsBuilder::ff_threading()`](../struct.GilrsBuilder.html#method.ff_threading).
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum FfThreading {
+    /// Effects are updated by background thread, every tick.
+    #[default]
+    Thread,
+    /// No thread is spawned. Effects are updated only when
+    /// [`Gilrs::update_ff()`](../struct.Gilrs.html#method.update_ff) is called.
+    Manual,
+}
+
+/// Identifies force feedback effect.
+///
+/// ID is unique within `Gilrs` context and doesn't change when `Effect` is cloned.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct EffectId(pub(crate) usize);
+
+impl fmt::Display for EffectId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 /// Handle to force feedback effect.
 ///
 /// `Effect` represents force feedback effect that can be played on one or more gamepads. It uses a
@@ -85,6 +120,7 @@ use vec_map::VecMap;
 pub struct Effect {
     id: usize,
     tx: Sender<Message>,
+    shared: Arc<SharedState>,
 }
 
 impl PartialEq for Effect {
@@ -107,6 +143,7 @@ impl Clone for Effect {
         Effect {
             id: self.id,
             tx: self.tx.clone(),
+            shared: self.shared.clone(),
         }
     }
 }
@@ -118,6 +155,23 @@ impl Drop for Effect {
 }
 
 impl Effect {
+    /// Returns effect's ID. It's also included in `ForceFeedbackEffectCompleted` events.
+    pub fn id(&self) -> EffectId {
+        EffectId(self.id)
+    }
+
+    /// Returns channel that receives effect's ID every time effect reaches its end. Effect ends
+    /// only if its repeat mode is `Repeat::For` or `Repeat::Once`.
+    pub fn on_completion(&self) -> Result<Receiver<EffectId>, Error> {
+        let (sender, receiver) = mpsc::channel();
+        self.tx.send(Message::AddCompletionSender {
+            id: self.id,
+            sender,
+        })?;
+
+        Ok(receiver)
+    }
+
     /// Plays effect on all associated gamepads.
     pub fn play(&self) -> Result<(), Error> {
         self.tx.send(Message::Play { id: self.id })?;
@@ -131,6 +185,43 @@ impl Effect {
         Ok(())
     }
 
+    /// Pauses effect if it's playing. Paused effect can be continued with `resume()`.
+    pub fn pause(&self) -> Result<(), Error> {
+        self.tx.send(Message::Pause { id: self.id })?;
+
+        Ok(())
+    }
+
+    /// Continues playback of paused effect from the position where it was paused.
+    pub fn resume(&self) -> Result<(), Error> {
+        self.tx.send(Message::Resume { id: self.id })?;
+
+        Ok(())
+    }
+
+    /// Moves playback position to `to`. If effect is not playing, it will be paused at `to`, so
+    /// it can be started from there with `resume()`.
+    pub fn seek(&self, to: Ticks) -> Result<(), Error> {
+        self.tx.send(Message::Seek { id: self.id, to })?;
+
+        Ok(())
+    }
+
+    /// Returns playback state of effect.
+    ///
+    /// State is updated by force feedback thread once per tick, so changes made by `play()`,
+    /// `pause()` and other methods are visible after up to one tick.
+    pub fn state(&self) -> PlaybackState {
+        self.shared.load().0
+    }
+
+    /// Returns playback position of effect. It's `Ticks(0)` for stopped effect.
+    ///
+    /// Position is updated by force feedback thread once per tick.
+    pub fn elapsed(&self) -> Ticks {
+        self.shared.load().1
+    }
+
     /// Changes gamepads that are associated with effect. Effect will be only played on gamepads
     /// from last call to this function.
     ///
@@ -224,6 +315,16 @@ impl Effect {
 
         Ok(())
     }
+
+    /// Changes priority of the effect. See [`MixingPolicy`](enum.MixingPolicy.html).
+    pub fn set_priority(&self, priority: u8) -> Result<(), Error> {
+        self.tx.send(Message::SetPriority {
+            id: self.id,
+            priority,
+        })?;
+
+        Ok(())
+    }
 }
 
 /// Creates new [`Effect`](struct.Effect.html).
@@ -235,12 +336,13 @@ pub struct EffectBuilder {
     dist_model: DistanceModel,
     position: [f32; 3],
     gain: f32,
+    priority: u8,
 }
 
 impl EffectBuilder {
     /// Creates new builder with following defaults: no gamepads, no base effects, repeat set to
-    /// infinitely, no distance model, position in (0.0, 0.0, 0.0) and gain 1.0. Use `finish()` to
-    /// create new effect.
+    /// infinitely, no distance model, position in (0.0, 0.0, 0.0), gain 1.0 and priority 0. Use
+    /// `finish()` to create new effect.
     pub fn new() -> Self {
         EffectBuilder {
             base_effects: Vec::new(),
@@ -249,6 +351,39 @@ impl EffectBuilder {
             dist_model: DistanceModel::None,
             position: [0.0, 0.0, 0.0],
             gain: 1.0,
+            priority: 0,
+        }
+    }
+
+    /// Creates new builder with base effects, repeat, distance model, gain and priority from
+    /// `clip`. Gamepads and position have the same defaults as in `new()`.
+    ///
+    /// Returns `Error::InvalidBaseEffect(index)` if base effect in clip has zero `play_for` or its
+    /// envelope is not shorter than `play_for`.
+    pub fn from_clip(clip: &HapticClip) -> Result<Self, Error> {
+        if let Some(index) = clip.base_effects.iter().position(|e| !e.is_valid()) {
+            return Err(Error::InvalidBaseEffect(index));
+        }
+
+        let mut builder = EffectBuilder::new();
+        builder.base_effects = clip.base_effects.clone();
+        builder
+            .repeat(clip.repeat)
+            .distance_model(clip.distance_model)
+            .gain(clip.gain)
+            .priority(clip.priority);
+
+        Ok(builder)
+    }
+
+    /// Returns clip with base effects, repeat, distance model, gain and priority of this builder.
+    pub fn to_clip(&self) -> HapticClip {
+        HapticClip {
+            base_effects: self.base_effects.clone(),
+            repeat: self.repeat,
+            distance_model: self.dist_model,
+            gain: self.gain,
+            priority: self.priority,
         }
     }
 
@@ -298,6 +433,13 @@ impl EffectBuilder {
         self
     }
 
+    /// Changes priority of the effect. Effects with higher priority are preferred when gamepad
+    /// plays more effects than its [`Mixing`](struct.Mixing.html) allows.
+    pub fn priority(&mut self, priority: u8) -> &mut Self {
+        self.priority = priority;
+        self
+    }
+
     /// Validates all parameters and creates new effect.
     ///
     /// # Errors
@@ -321,7 +463,7 @@ impl EffectBuilder {
 
         self.dist_model.validate()?;
 
-        let effect = EffectSource::new(
+        let mut effect = EffectSource::new(
             self.base_effects.clone(),
             self.devices.clone(),
             self.repeat,
@@ -329,13 +471,20 @@ impl EffectBuilder {
             self.position,
             self.gain,
         );
+        effect.priority = self.priority;
         let id = gilrs.next_ff_id();
         let tx = gilrs.ff_sender();
+        let shared = Arc::new(SharedState::new());
         tx.send(Message::Create {
             id,
             effect: Box::new(effect),
+            shared: shared.clone(),
         })?;
-        Ok(Effect { id, tx: tx.clone() })
+        Ok(Effect {
+            id,
+            tx: tx.clone(),
+            shared,
+        })
     }
 }
 
@@ -355,6 +504,8 @@ pub enum Error {
     Disconnected(GamepadId),
     /// Distance model is invalid.
     InvalidDistanceModel(DistanceModelError),
+    /// Base effect with given index plays for zero time or has too long envelope.
+    InvalidBaseEffect(usize),
     /// The other end of channel was dropped.
     SendFailed,
     /// Unexpected error has occurred
@@ -386,6 +537,10 @@ impl fmt::Display for Error {
                 sbuf.as_ref()
             }
             Error::InvalidDistanceModel(_) => "distance model is invalid",
+            Error::InvalidBaseEffect(index) => {
+                sbuf = format!("base effect {} is invalid.", index);
+                sbuf.as_ref()
+            }
             Error::SendFailed => "receiving end of a channel is disconnected.",
             Error::Other => "unespected error has occurred.",
         };
@@ -455,4 +610,302 @@ mod tests {
         assert_eq!(replay.at(Ticks(60)), 0.0);
         assert_eq!(replay.at(Ticks(70)), 0.0);
     }
+
+    #[test]
+    fn waveform() {
+        let dur = Ticks(20);
+        let periodic = |shape| Waveform::Periodic {
+            shape,
+            period: Ticks(8),
+            phase: Ticks(0),
+            magnitude: 1000,
+            offset: 1000,
+        };
+
+        let sine = periodic(WaveShape::Sine);
+        assert_eq!(sine.at(Ticks(0), dur), 1000);
+        assert_eq!(sine.at(Ticks(2), dur), 2000);
+        assert!(sine.at(Ticks(6), dur) <= 1);
+        assert_eq!(sine.at(Ticks(10), dur), 2000);
+
+        let square = periodic(WaveShape::Square);
+        assert_eq!(square.at(Ticks(3), dur), 2000);
+        assert_eq!(square.at(Ticks(4), dur), 0);
+
+        let triangle = periodic(WaveShape::Triangle);
+        assert_eq!(triangle.at(Ticks(1), dur), 1500);
+        assert_eq!(triangle.at(Ticks(4), dur), 1000);
+        assert_eq!(triangle.at(Ticks(6), dur), 0);
+
+        let saw = periodic(WaveShape::SawtoothUp);
+        assert_eq!(saw.at(Ticks(0), dur), 0);
+        assert_eq!(saw.at(Ticks(4), dur), 1000);
+
+        let ramp = Waveform::Ramp {
+            start: 0,
+            end: 2000,
+        };
+        assert_eq!(ramp.at(Ticks(0), dur), 0);
+        assert_eq!(ramp.at(Ticks(5), dur), 500);
+        assert_eq!(ramp.at(Ticks(30), dur), 2000);
+
+        let keys = Waveform::keyframes(vec![(Ticks(10), 0), (Ticks(0), 100), (Ticks(5), 600)]);
+        assert_eq!(keys.at(Ticks(0), dur), 100);
+        assert_eq!(keys.at(Ticks(1), dur), 200);
+        assert_eq!(keys.at(Ticks(8), dur), 240);
+        assert_eq!(keys.at(Ticks(15), dur), 0);
+
+        let effect = BaseEffect {
+            kind: BaseEffectType::Waveform {
+                motor: Motor::Strong,
+                waveform: ramp,
+            },
+            scheduling: Replay {
+                play_for: dur,
+                with_delay: Ticks(10),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(effect.magnitude_at(Ticks(35)), (Motor::Strong, 500));
+        assert_eq!(effect.magnitude_at(Ticks(25)), (Motor::Strong, 0));
+    }
+
+    #[test]
+    fn clip() {
+        let mut builder = EffectBuilder::new();
+        builder
+            .add_effect(BaseEffect {
+                kind: BaseEffectType::Waveform {
+                    motor: Motor::Weak,
+                    waveform: Waveform::keyframes(vec![(Ticks(0), 0), (Ticks(4), 30_000)]),
+                },
+                scheduling: Replay {
+                    after: Ticks(2),
+                    play_for: Ticks(10),
+                    with_delay: Ticks(5),
+                },
+                envelope: Envelope {
+                    attack_length: Ticks(3),
+                    attack_level: 0.5,
+                    ..Default::default()
+                },
+            })
+            .repeat(Repeat::For(Ticks(40)))
+            .distance_model(DistanceModel::Inverse {
+                ref_distance: 1.0,
+                rolloff_factor: 0.5,
+            })
+            .gain(0.75);
+
+        let clip = builder.to_clip();
+        assert_eq!(EffectBuilder::from_clip(&clip).unwrap(), builder);
+
+        #[cfg(feature = "serde-serialize")]
+        {
+            let json = clip.to_json().unwrap();
+            assert_eq!(HapticClip::from_json(&json).unwrap(), clip);
+
+            let empty = HapticClip::from_json("{}").unwrap();
+            assert_eq!(
+                EffectBuilder::from_clip(&empty).unwrap(),
+                EffectBuilder::new()
+            );
+
+            // Zero-length effect would make force feedback thread divide by zero.
+            let zero = HapticClip::from_json(
+                r#"{ "base_effects": [{ "scheduling": { "after": 0, "play_for": 0, "with_delay": 0 } }] }"#,
+            )
+            .unwrap();
+            assert_eq!(
+                EffectBuilder::from_clip(&zero),
+                Err(Error::InvalidBaseEffect(0))
+            );
+        }
+
+        let mut clip = HapticClip::default();
+        clip.base_effects.push(BaseEffect::default());
+        clip.base_effects.push(BaseEffect {
+            envelope: Envelope {
+                attack_length: Ticks(40),
+                fade_length: Ticks(40),
+                ..Default::default()
+            },
+            scheduling: Replay {
+                play_for: Ticks(50),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        assert_eq!(
+            EffectBuilder::from_clip(&clip),
+            Err(Error::InvalidBaseEffect(1))
+        );
+    }
+
+    #[test]
+    fn zero_length_effect_is_silent() {
+        let effect = BaseEffect {
+            kind: BaseEffectType::Strong { magnitude: 1000 },
+            scheduling: Replay {
+                after: Ticks(0),
+                play_for: Ticks(0),
+                with_delay: Ticks(0),
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(effect.magnitude_at(Ticks(100)), (Motor::Strong, 0));
+    }
+
+    #[test]
+    fn mixing() {
+        use self::effect_source::Magnitude;
+        use self::mixer::{Mixer, Voice};
+
+        let voice = |priority, order, strong, weak| Voice {
+            priority,
+            order,
+            magnitude: Magnitude { strong, weak },
+        };
+        let voices = || {
+            vec![
+                voice(0, 0, 1000, 4000),
+                voice(2, 1, 2000, 0),
+                voice(1, 2, 0, 0),
+                voice(2, 3, 3000, 1000),
+                voice(0, 4, 500, 500),
+            ]
+        };
+        let mix = |mixer: &Mixer| {
+            let m = mixer.mix(0, &mut voices());
+            (m.strong, m.weak)
+        };
+        let mut mixer = Mixer::new();
+        assert_eq!(mix(&mixer), (6500, 5500));
+
+        let set = |mixer: &mut Mixer, policy, max_effects| {
+            mixer.set_mixing(
+                0,
+                Mixing {
+                    policy,
+                    max_effects,
+                },
+            )
+        };
+        set(&mut mixer, MixingPolicy::Max, None);
+        assert_eq!(mix(&mixer), (3000, 4000));
+        set(&mut mixer, MixingPolicy::HighestPriority, None);
+        assert_eq!(mix(&mixer), (5000, 1000));
+        set(&mut mixer, MixingPolicy::Duck { percent: 50 }, None);
+        assert_eq!(mix(&mixer), (5750, 3250));
+        // Silent voice doesn't take a slot; newer voice wins between equal priorities.
+        set(&mut mixer, MixingPolicy::Sum, Some(3));
+        assert_eq!(mix(&mixer), (5500, 1500));
+
+        mixer.set_gamepad_gain(0, 0.5);
+        mixer.set_gain(0.5);
+        assert_eq!(mix(&mixer), (1375, 375));
+        assert_eq!(mixer.mix(1, &mut voices()).strong, 3250);
+        mixer.set_gamepad_gain(0, 0.0);
+        assert_eq!(mix(&mixer), (0, 0));
+    }
+
+    #[test]
+    fn repeat_once() {
+        let base = |after, play_for| BaseEffect {
+            kind: BaseEffectType::Strong { magnitude: 60_000 },
+            scheduling: Replay {
+                after: Ticks(after),
+                play_for: Ticks(play_for),
+                with_delay: Ticks(10),
+            },
+            ..Default::default()
+        };
+        let mut effect = EffectSource::new(
+            vec![base(0, 10), base(5, 3)],
+            VecMap::new(),
+            Repeat::Once,
+            DistanceModel::None,
+            [0.0, 0.0, 0.0],
+            1.0,
+        );
+        effect.play(Ticks(0));
+
+        assert_eq!(effect.natural_end(), Ticks(10));
+        assert_eq!(
+            effect.combine_base_effects(Ticks(9), [0.0; 3]).strong,
+            60_000
+        );
+        assert!(!effect.completed);
+        effect.combine_base_effects(Ticks(10), [0.0; 3]);
+        assert!(effect.completed);
+        assert_eq!(effect.combine_base_effects(Ticks(11), [0.0; 3]).strong, 0);
+        assert_eq!(effect.state.elapsed(Ticks(11)), Ticks(10));
+    }
+
+    #[test]
+    fn pause_resume_seek() {
+        let mut effect = EffectSource::new(
+            Vec::new(),
+            VecMap::new(),
+            Repeat::Infinitely,
+            DistanceModel::None,
+            [0.0, 0.0, 0.0],
+            1.0,
+        );
+        let shared = SharedState::new();
+
+        effect.play(Ticks(100));
+        effect.pause(Ticks(105));
+        assert_eq!(effect.state.elapsed(Ticks(120)), Ticks(5));
+        effect.resume(Ticks(120));
+        assert_eq!(effect.state.elapsed(Ticks(123)), Ticks(8));
+        effect.seek(Ticks(123), Ticks(2));
+        shared.store(effect.state, Ticks(125));
+        assert_eq!(shared.load(), (PlaybackState::Playing, Ticks(4)));
+
+        effect.stop();
+        effect.resume(Ticks(130));
+        shared.store(effect.state, Ticks(130));
+        assert_eq!(shared.load(), (PlaybackState::Stopped, Ticks(0)));
+        effect.seek(Ticks(130), Ticks(7));
+        shared.store(effect.state, Ticks(140));
+        assert_eq!(shared.load(), (PlaybackState::Paused, Ticks(7)));
+    }
+
+    #[test]
+    fn manual_update() {
+        use crate::GilrsBuilder;
+        use std::time::{Duration, Instant};
+
+        let mut gilrs = match GilrsBuilder::new()
+            .ff_threading(FfThreading::Manual)
+            .build()
+        {
+            Ok(gilrs) => gilrs,
+            Err(crate::Error::NotImplemented(gilrs)) => gilrs,
+            Err(e) => panic!("{:?}", e),
+        };
+        let effect = EffectBuilder::new()
+            .add_effect(BaseEffect::default())
+            .finish(&mut gilrs)
+            .unwrap();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        gilrs.update_ff(at(0));
+        assert_eq!(effect.state(), PlaybackState::Stopped);
+
+        effect.play().unwrap();
+        gilrs.update_ff(at(20));
+        assert_eq!(effect.state(), PlaybackState::Playing);
+        gilrs.update_ff(at(120));
+        assert_eq!(effect.elapsed(), Ticks(100));
+
+        effect.pause().unwrap();
+        gilrs.update_ff(at(1000));
+        assert_eq!(effect.state(), PlaybackState::Paused);
+        assert_eq!(effect.elapsed(), Ticks(980));
+    }
 }
@@ -5,25 +5,36 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
-use super::effect_source::{DistanceModel, EffectSource, EffectState, Magnitude};
-use super::time::{Repeat, Ticks, TICK_DURATION};
+use super::effect_source::{DistanceModel, EffectSource, EffectState, Magnitude, SharedState};
+use super::mixer::{Mixer, Mixing, Voice};
+use super::time::{Repeat, Ticks};
+use super::{EffectId, FfThreading};
 
+use std::fmt;
 use std::ops::{Deref, DerefMut};
-use std::sync::mpsc::{self, Receiver, Sender};
-use std::thread;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
 
 use crate::gamepad::GamepadId;
-use crate::Event;
+use crate::{Event, EventType};
 use gilrs_core::FfDevice;
 
 use vec_map::VecMap;
 
+/// Length of state sent to device, in ticks. Device stops on its own if this thread doesn't refresh
+/// state in time.
+const REPLAY_TICKS: u32 = 10;
+/// State of device is refreshed if it would expire in less than this many ticks.
+const REFRESH_TICKS: u32 = 2;
+
 #[derive(Debug)]
 pub(crate) enum Message {
     Create {
         id: usize,
         effect: Box<EffectSource>,
+        shared: Arc<SharedState>,
     },
     HandleCloned {
         id: usize,
@@ -37,6 +48,16 @@ pub(crate) enum Message {
     Stop {
         id: usize,
     },
+    Pause {
+        id: usize,
+    },
+    Resume {
+        id: usize,
+    },
+    Seek {
+        id: usize,
+        to: Ticks,
+    },
     Open {
         id: usize,
         device: FfDevice,
@@ -48,6 +69,12 @@ pub(crate) enum Message {
         id: usize,
         position: [f32; 3],
     },
+    Rumble {
+        id: usize,
+        strong: u16,
+        weak: u16,
+        duration: Duration,
+    },
     SetGamepads {
         id: usize,
         gamepads: VecMap<()>,
@@ -72,6 +99,27 @@ pub(crate) enum Message {
         id: usize,
         gain: f32,
     },
+    SetPriority {
+        id: usize,
+        priority: u8,
+    },
+    SetMixing {
+        id: usize,
+        mixing: Mixing,
+    },
+    SetGamepadGain {
+        id: usize,
+        gain: f32,
+    },
+    SetGlobalGain {
+        gain: f32,
+    },
+    AddCompletionSender {
+        id: usize,
+        sender: Sender<EffectId>,
+    },
+    /// Stops all effects, closes devices and ends force feedback thread.
+    Shutdown,
 }
 
 pub(crate) enum FfMessage {
@@ -92,14 +140,49 @@ impl Message {
 
 #[derive(Debug)]
 struct Device {
-    inner: FfDevice,
+    /// `None` only in tests, where there is no real device.
+    inner: Option<FfDevice>,
     position: [f32; 3],
+    /// Last magnitude sent to device.
+    magnitude: Option<Magnitude>,
+    /// When device stops playing last sent magnitude.
+    expires: Instant,
+    /// Direct rumble from `Gamepad::rumble()` and when it ends.
+    rumble: Option<(Magnitude, Ticks)>,
+}
+
+impl Device {
+    fn is_stopped(&self) -> bool {
+        self.magnitude == Some(Magnitude::zero())
+    }
+
+    /// Sends `magnitude` to device if it changed or if device will stop playing it soon. Returns
+    /// `true` if state was sent. `now` is time of current tick.
+    fn update(&mut self, magnitude: Magnitude, now: Instant, tick_duration: Duration) -> bool {
+        let expiring = self.expires <= now + tick_duration * REFRESH_TICKS;
+        if self.magnitude == Some(magnitude) && (self.is_stopped() || !expiring) {
+            return false;
+        }
+
+        let length = tick_duration * REPLAY_TICKS;
+        if let Some(ref mut inner) = self.inner {
+            inner.set_ff_state(magnitude.strong, magnitude.weak, length);
+        }
+        self.magnitude = Some(magnitude);
+        self.expires = now + length;
+
+        true
+    }
 }
 
 struct Effect {
     source: EffectSource,
     /// Number of created effect's handles.
     count: usize,
+    /// Channels that are notified when effect completes.
+    completion_senders: Vec<Sender<EffectId>>,
+    /// State visible to effect's handles.
+    shared: Arc<SharedState>,
 }
 
 impl Effect {
@@ -114,9 +197,14 @@ impl Effect {
     }
 }
 
-impl From<EffectSource> for Effect {
-    fn from(source: EffectSource) -> Self {
-        Effect { source, count: 1 }
+impl Effect {
+    fn new(source: EffectSource, shared: Arc<SharedState>) -> Self {
+        Effect {
+            source,
+            count: 1,
+            completion_senders: Vec::new(),
+            shared,
+        }
     }
 }
 
@@ -137,183 +225,516 @@ impl DerefMut for Effect {
 impl From<FfDevice> for Device {
     fn from(inner: FfDevice) -> Self {
         Device {
-            inner,
+            inner: Some(inner),
             position: [0.0, 0.0, 0.0],
+            magnitude: None,
+            expires: Instant::now(),
+            rumble: None,
         }
     }
 }
 
-pub(crate) fn run(tx: Sender<FfMessage>, rx: Receiver<Message>) {
-    let mut effects = VecMap::<Effect>::new();
-    let mut devices = VecMap::<Device>::new();
-    let sleep_dur = Duration::from_millis(TICK_DURATION.into());
-    let mut tick = Ticks(0);
-    let mut completion_events = Vec::<Event>::new();
-
-    loop {
-        let t1 = Instant::now();
-        while let Ok(ev) = rx.try_recv() {
-            if ev.use_trace_level() {
-                trace!("New ff event: {:?}", ev);
-            } else {
-                debug!("New ff event: {:?}", ev);
+/// Effects and devices managed by force feedback thread or, with `FfThreading::Manual`, by
+/// `Gilrs::update_ff()`.
+pub(crate) struct Server {
+    tx: Sender<FfMessage>,
+    rx: Receiver<Message>,
+    effects: VecMap<Effect>,
+    devices: VecMap<Device>,
+    mixer: Mixer,
+    completion_events: Vec<Event>,
+    tick_duration: Duration,
+    start: Option<Instant>,
+}
+
+impl Server {
+    fn new(tx: Sender<FfMessage>, rx: Receiver<Message>, tick_duration: Duration) -> Self {
+        Server {
+            tx,
+            rx,
+            effects: VecMap::new(),
+            devices: VecMap::new(),
+            mixer: Mixer::new(),
+            completion_events: Vec::new(),
+            tick_duration,
+            start: None,
+        }
+    }
+
+    /// Converts `now` to time since first call to this function, so late iterations don't shift
+    /// effects.
+    fn tick_at(&mut self, now: Instant) -> Ticks {
+        let start = *self.start.get_or_insert(now);
+        Ticks::from(now.saturating_duration_since(start))
+    }
+
+    fn handle(&mut self, ev: Message, tick: Ticks) {
+        handle_message(
+            ev,
+            &mut self.effects,
+            &mut self.devices,
+            &mut self.mixer,
+            tick,
+        );
+    }
+
+    /// Sends state of effects at `tick` (which is at `now`) to devices and notifies about completed
+    /// effects.
+    fn play(&mut self, tick: Ticks, now: Instant) {
+        combine_and_play(
+            &mut self.effects,
+            &mut self.devices,
+            &self.mixer,
+            tick,
+            now,
+            self.tick_duration,
+            &mut self.completion_events,
+        );
+        for (_, effect) in &self.effects {
+            effect.shared.store(effect.source.state, tick);
+        }
+        for ev in self.completion_events.drain(..) {
+            let _ = self.tx.send(FfMessage::EffectCompleted { event: ev });
+        }
+    }
+
+    /// Handles all pending messages and plays effects at `now`.
+    pub(crate) fn update(&mut self, now: Instant) {
+        let tick = self.tick_at(now);
+        while let Ok(ev) = self.rx.try_recv() {
+            if let Message::Shutdown = ev {
+                continue;
             }
+            self.handle(ev, tick);
+        }
+        self.play(tick, now);
+    }
 
-            match ev {
-                Message::Create { id, effect } => {
-                    effects.insert(id, (*effect).into());
-                }
-                Message::Play { id } => {
-                    if let Some(effect) = effects.get_mut(id) {
-                        effect.source.state = EffectState::Playing { since: tick }
-                    } else {
-                        error!("{:?} with wrong ID", ev);
-                    }
-                }
-                Message::Stop { id } => {
-                    if let Some(effect) = effects.get_mut(id) {
-                        effect.source.state = EffectState::Stopped
-                    } else {
-                        error!("{:?} with wrong ID", ev);
-                    }
-                }
-                Message::Open { id, device } => {
-                    devices.insert(id, device.into());
-                }
-                Message::Close { id } => {
-                    devices.remove(id);
-                }
-                Message::SetListenerPosition { id, position } => {
-                    if let Some(device) = devices.get_mut(id) {
-                        device.position = position;
-                    } else {
-                        error!("{:?} with wrong ID", ev);
-                    }
-                }
-                Message::HandleCloned { id } => {
-                    if let Some(effect) = effects.get_mut(id) {
-                        effect.inc();
-                    } else {
-                        error!("{:?} with wrong ID", ev);
-                    }
-                }
-                Message::HandleDropped { id } => {
-                    let mut drop = false;
-                    if let Some(effect) = effects.get_mut(id) {
-                        if effect.dec() == 0 {
-                            drop = true;
-                        }
-                    } else {
-                        error!("{:?} with wrong ID", ev);
-                    }
+    fn run(mut self) {
+        let mut next_tick = Instant::now();
+        self.tick_at(next_tick);
+        let mut idle = false;
 
-                    if drop {
-                        effects.remove(id);
-                    }
-                }
-                Message::SetGamepads { id, gamepads } => {
-                    if let Some(eff) = effects.get_mut(id) {
-                        eff.source.devices = gamepads;
-                    } else {
-                        error!("Invalid effect id {} when changing gamepads.", id);
-                    }
-                }
-                Message::AddGamepad { id, gamepad_id } => {
-                    if let Some(eff) = effects.get_mut(id) {
-                        eff.source.devices.insert(gamepad_id.0, ());
-                    } else {
-                        error!("Invalid effect id {} when changing gamepads.", id);
-                    }
-                }
-                Message::SetRepeat { id, repeat } => {
-                    if let Some(eff) = effects.get_mut(id) {
-                        eff.source.repeat = repeat;
-                    } else {
-                        error!("Invalid effect id {} when changing repeat mode.", id);
-                    }
-                }
-                Message::SetDistanceModel { id, model } => {
-                    if let Some(eff) = effects.get_mut(id) {
-                        eff.source.distance_model = model;
-                    } else {
-                        error!("Invalid effect id {} when changing distance model.", id);
-                    }
+        loop {
+            // Block until next message if nothing is playing, otherwise wait until next tick.
+            let now = Instant::now();
+            let msg = if idle {
+                self.rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
+            } else if next_tick > now {
+                self.rx.recv_timeout(next_tick - now)
+            } else {
+                Err(RecvTimeoutError::Timeout)
+            };
+
+            match msg {
+                Ok(Message::Shutdown) => {
+                    debug!("Stopping force feedback thread.");
+                    return;
                 }
-                Message::SetPosition { id, position } => {
-                    if let Some(eff) = effects.get_mut(id) {
-                        eff.source.position = position;
-                    } else {
-                        error!("Invalid effect id {}.", id);
+                Ok(ev) => {
+                    // Direct rumble should start immediately, not on next tick.
+                    let immediate = idle || matches!(ev, Message::Rumble { .. });
+                    let tick = self.tick_at(Instant::now());
+                    self.handle(ev, tick);
+                    if immediate {
+                        idle = false;
+                        next_tick = Instant::now();
                     }
+                    continue;
                 }
-                Message::SetGain { id, gain } => {
-                    if let Some(eff) = effects.get_mut(id) {
-                        eff.source.gain = gain;
-                    } else {
-                        error!("Invalid effect id {} when changing effect gain.", id);
-                    }
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+
+            let tick = self.tick_at(next_tick);
+            self.play(tick, next_tick);
+
+            idle = is_idle(&self.effects, &self.devices);
+            if idle {
+                trace!("({:?}) Nothing is playing, waiting for messages.", tick);
+                continue;
+            }
+
+            next_tick += self.tick_duration;
+            let now = Instant::now();
+            if next_tick <= now {
+                warn!(
+                    "One iteration of a force feedback loop took more than {:?}!",
+                    self.tick_duration
+                );
+                // Skip missed ticks instead of trying to catch up.
+                while next_tick <= now {
+                    next_tick += self.tick_duration;
                 }
             }
         }
+    }
+}
 
-        combine_and_play(&mut effects, &mut devices, tick, &mut completion_events);
-        completion_events.iter().for_each(|ev| {
-            let _ = tx.send(FfMessage::EffectCompleted { event: *ev });
-        });
-        completion_events.clear();
+impl fmt::Debug for Server {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Server")
+            .field("effects", &self.effects.len())
+            .field("devices", &self.devices)
+            .field("tick_duration", &self.tick_duration)
+            .finish_non_exhaustive()
+    }
+}
 
-        let dur = Instant::now().duration_since(t1);
-        if dur > sleep_dur {
-            // TODO: Should we add dur - sleep_dur to next iteration's dur?
-            warn!(
-                "One iteration of a force feedback loop took more than {}ms!",
-                TICK_DURATION
-            );
-        } else {
-            thread::sleep(sleep_dur - dur);
+/// Where force feedback effects are played.
+#[derive(Debug)]
+pub(crate) enum Runner {
+    Thread(JoinHandle<()>),
+    Manual(Box<Server>),
+    /// Threads are not supported on this platform.
+    None,
+}
+
+/// Returns `true` if no effect or direct rumble is playing on opened device and all devices are
+/// stopped.
+fn is_idle(effects: &VecMap<Effect>, devices: &VecMap<Device>) -> bool {
+    let playing = effects.values().any(|effect| {
+        matches!(effect.source.state, EffectState::Playing { .. })
+            && effect.devices.keys().any(|dev| devices.contains_key(dev))
+    });
+
+    !playing
+        && devices
+            .values()
+            .all(|dev| dev.is_stopped() && dev.rumble.is_none())
+}
+
+fn handle_message(
+    ev: Message,
+    effects: &mut VecMap<Effect>,
+    devices: &mut VecMap<Device>,
+    mixer: &mut Mixer,
+    tick: Ticks,
+) {
+    if ev.use_trace_level() {
+        trace!("New ff event: {:?}", ev);
+    } else {
+        debug!("New ff event: {:?}", ev);
+    }
+
+    match ev {
+        Message::Create { id, effect, shared } => {
+            effects.insert(id, Effect::new(*effect, shared));
+        }
+        Message::Play { id } => {
+            if let Some(effect) = effects.get_mut(id) {
+                effect.source.play(tick);
+            } else {
+                error!("{:?} with wrong ID", ev);
+            }
+        }
+        Message::Stop { id } => {
+            if let Some(effect) = effects.get_mut(id) {
+                effect.source.stop();
+            } else {
+                error!("{:?} with wrong ID", ev);
+            }
+        }
+        Message::Pause { id } => {
+            if let Some(effect) = effects.get_mut(id) {
+                effect.source.pause(tick);
+            } else {
+                error!("{:?} with wrong ID", ev);
+            }
+        }
+        Message::Resume { id } => {
+            if let Some(effect) = effects.get_mut(id) {
+                effect.source.resume(tick);
+            } else {
+                error!("{:?} with wrong ID", ev);
+            }
         }
-        tick.inc();
+        Message::Seek { id, to } => {
+            if let Some(effect) = effects.get_mut(id) {
+                effect.source.seek(tick, to);
+            } else {
+                error!("{:?} with wrong ID", ev);
+            }
+        }
+        Message::Open { id, device } => {
+            devices.insert(id, device.into());
+        }
+        Message::Close { id } => {
+            devices.remove(id);
+        }
+        Message::Rumble {
+            id,
+            strong,
+            weak,
+            duration,
+        } => {
+            if let Some(device) = devices.get_mut(id) {
+                device.rumble = Some((Magnitude { strong, weak }, tick + Ticks::from(duration)));
+            } else {
+                error!("{:?} with wrong ID", ev);
+            }
+        }
+        Message::SetListenerPosition { id, position } => {
+            if let Some(device) = devices.get_mut(id) {
+                device.position = position;
+            } else {
+                error!("{:?} with wrong ID", ev);
+            }
+        }
+        Message::HandleCloned { id } => {
+            if let Some(effect) = effects.get_mut(id) {
+                effect.inc();
+            } else {
+                error!("{:?} with wrong ID", ev);
+            }
+        }
+        Message::HandleDropped { id } => {
+            let mut drop = false;
+            if let Some(effect) = effects.get_mut(id) {
+                if effect.dec() == 0 {
+                    drop = true;
+                }
+            } else {
+                error!("{:?} with wrong ID", ev);
+            }
+
+            if drop {
+                effects.remove(id);
+            }
+        }
+        Message::SetGamepads { id, gamepads } => {
+            if let Some(eff) = effects.get_mut(id) {
+                eff.source.devices = gamepads;
+            } else {
+                error!("Invalid effect id {} when changing gamepads.", id);
+            }
+        }
+        Message::AddGamepad { id, gamepad_id } => {
+            if let Some(eff) = effects.get_mut(id) {
+                eff.source.devices.insert(gamepad_id.0, ());
+            } else {
+                error!("Invalid effect id {} when changing gamepads.", id);
+            }
+        }
+        Message::SetRepeat { id, repeat } => {
+            if let Some(eff) = effects.get_mut(id) {
+                eff.source.repeat = repeat;
+            } else {
+                error!("Invalid effect id {} when changing repeat mode.", id);
+            }
+        }
+        Message::SetDistanceModel { id, model } => {
+            if let Some(eff) = effects.get_mut(id) {
+                eff.source.distance_model = model;
+            } else {
+                error!("Invalid effect id {} when changing distance model.", id);
+            }
+        }
+        Message::SetPosition { id, position } => {
+            if let Some(eff) = effects.get_mut(id) {
+                eff.source.position = position;
+            } else {
+                error!("Invalid effect id {}.", id);
+            }
+        }
+        Message::SetGain { id, gain } => {
+            if let Some(eff) = effects.get_mut(id) {
+                eff.source.gain = gain;
+            } else {
+                error!("Invalid effect id {} when changing effect gain.", id);
+            }
+        }
+        Message::SetPriority { id, priority } => {
+            if let Some(eff) = effects.get_mut(id) {
+                eff.source.priority = priority;
+            } else {
+                error!("Invalid effect id {} when changing effect priority.", id);
+            }
+        }
+        Message::SetMixing { id, mixing } => mixer.set_mixing(id, mixing),
+        Message::SetGamepadGain { id, gain } => mixer.set_gamepad_gain(id, gain),
+        Message::SetGlobalGain { gain } => mixer.set_gain(gain),
+        Message::AddCompletionSender { id, sender } => {
+            if let Some(eff) = effects.get_mut(id) {
+                eff.completion_senders.push(sender);
+            } else {
+                error!("Invalid effect id {} when adding completion channel.", id);
+            }
+        }
+        Message::Shutdown => unreachable!("shutdown is handled by run()"),
     }
 }
 
-pub(crate) fn init() -> (Sender<Message>, Receiver<FfMessage>) {
-    let (tx, _rx) = mpsc::channel();
-    let (_tx2, rx2) = mpsc::channel();
+pub(crate) fn init(
+    tick_duration: Duration,
+    threading: FfThreading,
+) -> (Sender<Message>, Receiver<FfMessage>, Runner) {
+    let (tx, rx) = mpsc::channel();
+    let (tx2, rx2) = mpsc::channel();
+    let server = Server::new(tx2, rx, tick_duration);
 
-    // Wasm doesn't support threads and force feedback
-    #[cfg(not(target_arch = "wasm32"))]
-    std::thread::Builder::new()
-        .name("gilrs".to_owned())
-        .spawn(move || run(_tx2, _rx))
-        .expect("failed to spawn thread");
+    let runner = match threading {
+        FfThreading::Manual => Runner::Manual(Box::new(server)),
+        // Wasm doesn't support threads and force feedback
+        FfThreading::Thread if cfg!(target_arch = "wasm32") => Runner::None,
+        FfThreading::Thread => Runner::Thread(
+            std::thread::Builder::new()
+                .name("gilrs".to_owned())
+                .spawn(move || server.run())
+                .expect("failed to spawn thread"),
+        ),
+    };
 
-    (tx, rx2)
+    (tx, rx2, runner)
 }
 
 fn combine_and_play(
     effects: &mut VecMap<Effect>,
     devices: &mut VecMap<Device>,
+    mixer: &Mixer,
     tick: Ticks,
+    now: Instant,
+    tick_duration: Duration,
     completion_events: &mut Vec<Event>,
 ) {
+    let mut voices = Vec::new();
     for (dev_id, dev) in devices {
-        let mut magnitude = Magnitude::zero();
-        for (_, ref mut effect) in effects.iter_mut() {
+        voices.clear();
+        for (id, ref mut effect) in effects.iter_mut() {
             if effect.devices.contains_key(dev_id) {
-                magnitude += effect.combine_base_effects(tick, dev.position);
-                completion_events.extend(effect.flush_completion_events());
+                voices.push(Voice {
+                    priority: effect.priority,
+                    order: id,
+                    magnitude: effect.combine_base_effects(tick, dev.position),
+                });
             }
         }
-        trace!(
-            "({:?}) Setting ff state of {:?} to {:?}",
-            tick,
-            dev,
-            magnitude
+        match dev.rumble {
+            Some((magnitude, end)) if tick < end => voices.push(Voice {
+                priority: 0,
+                order: usize::MAX,
+                magnitude,
+            }),
+            Some(_) => dev.rumble = None,
+            None => (),
+        }
+
+        let magnitude = mixer.mix(dev_id, &mut voices);
+        if dev.update(magnitude, now, tick_duration) {
+            trace!(
+                "({:?}) Set ff state of {:?} to {:?}",
+                tick,
+                dev.inner,
+                magnitude
+            );
+        }
+    }
+
+    for (id, effect) in effects.iter_mut() {
+        if !effect.source.completed {
+            continue;
+        }
+
+        effect.source.completed = false;
+        let effect_id = EffectId(id);
+        completion_events.extend(effect.devices.keys().map(|dev| {
+            Event::new(
+                GamepadId(dev),
+                EventType::ForceFeedbackEffectCompleted(effect_id),
+            )
+        }));
+        effect
+            .completion_senders
+            .retain(|sender| sender.send(effect_id).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ff::{BaseEffect, BaseEffectType, MixingPolicy, Replay};
+
+    #[test]
+    fn manual_rumble() {
+        let (tx, _rx, runner) = init(Duration::from_millis(50), FfThreading::Manual);
+        let mut server = match runner {
+            Runner::Manual(server) => server,
+            _ => unreachable!(),
+        };
+        server.devices.insert(
+            0,
+            Device {
+                inner: None,
+                position: [0.0, 0.0, 0.0],
+                magnitude: None,
+                expires: Instant::now(),
+                rumble: None,
+            },
+        );
+
+        let start = Instant::now();
+        let mut update = |ms, msgs: Vec<Message>| {
+            for msg in msgs {
+                tx.send(msg).unwrap();
+            }
+            server.update(start + Duration::from_millis(ms));
+            server.devices[0].magnitude.unwrap()
+        };
+        let rumble = |strong, weak, ms| Message::Rumble {
+            id: 0,
+            strong,
+            weak,
+            duration: Duration::from_millis(ms),
+        };
+        let mag = |strong, weak| Magnitude { strong, weak };
+
+        assert_eq!(update(0, vec![rumble(1000, 0, 1000)]), mag(1000, 0));
+        // New rumble replaces previous one.
+        assert_eq!(update(50, vec![rumble(0, 2000, 1000)]), mag(0, 2000));
+        // Zero duration stops rumble.
+        assert_eq!(update(100, vec![rumble(500, 500, 0)]), Magnitude::zero());
+
+        let mut devices = VecMap::new();
+        devices.insert(0, ());
+        let mut effect = EffectSource::new(
+            vec![BaseEffect {
+                kind: BaseEffectType::Strong { magnitude: 3000 },
+                scheduling: Replay {
+                    play_for: Ticks::from_ms(1000),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }],
+            devices,
+            Repeat::Infinitely,
+            DistanceModel::None,
+            [0.0, 0.0, 0.0],
+            1.0,
         );
-        dev.inner.set_ff_state(
-            magnitude.strong,
-            magnitude.weak,
-            Duration::from_millis(u64::from(TICK_DURATION) * 2),
+        effect.priority = 1;
+        let create = Message::Create {
+            id: 0,
+            effect: Box::new(effect),
+            shared: Arc::new(SharedState::new()),
+        };
+
+        // Rumble is mixed with playing effect.
+        assert_eq!(
+            update(
+                150,
+                vec![create, Message::Play { id: 0 }, rumble(0, 2000, 1000)]
+            ),
+            mag(3000, 2000)
+        );
+
+        // Rumble has priority 0, so it's dropped when effect with higher priority plays.
+        let mixing = Mixing {
+            policy: MixingPolicy::HighestPriority,
+            max_effects: None,
+        };
+        assert_eq!(
+            update(200, vec![Message::SetMixing { id: 0, mixing }]),
+            mag(3000, 0)
         );
     }
 }
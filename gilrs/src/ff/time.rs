@@ -5,18 +5,23 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+#[cfg(feature = "serde-serialize")]
+use serde::{Deserialize, Serialize};
 use std::ops::{Add, AddAssign, Mul, MulAssign, Rem, Sub, SubAssign};
 use std::time::Duration;
 
-use crate::utils;
-
+/// Default duration of one iteration of force feedback thread in milliseconds.
 pub(crate) const TICK_DURATION: u32 = 50;
 
-/// Represents duration.
+/// Represents duration with millisecond precision.
 ///
 /// This type is only useful as input parameter for other functions in force feedback module. To
-/// create it, use `from_ms()` method. Keep in mind that `Ticks` **is not precise** representation
-/// of time.
+/// create it, use `from_ms()` method.
+///
+/// Keep in mind that effects are evaluated only once per iteration of force feedback thread
+/// (every 50ms by default, see
+/// [`GilrsBuilder::ff_tick_duration()`](../struct.GilrsBuilder.html#method.ff_tick_duration)), so
+/// changes shorter than that may not be noticeable.
 ///
 /// # Example
 ///
@@ -25,21 +30,17 @@ pub(crate) const TICK_DURATION: u32 = 50;
 /// use std::time::Duration;
 ///
 /// let t1 = Ticks::from_ms(110);
-/// let t2 = Ticks::from(Duration::from_millis(130));
+/// let t2 = Ticks::from(Duration::from_micros(110_400));
 ///
-/// /// `Ticks` is not precise.
 /// assert_eq!(t1, t2);
 /// ```
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Default)]
-pub struct Ticks(pub(super) u32);
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct Ticks(pub(super) u64);
 
 impl Ticks {
     pub fn from_ms(dur: u32) -> Self {
-        Ticks(utils::ceil_div(dur, TICK_DURATION))
-    }
-
-    pub(super) fn inc(&mut self) {
-        self.0 += 1
+        Ticks(dur.into())
     }
 
     pub(super) fn checked_sub(self, rhs: Ticks) -> Option<Ticks> {
@@ -49,7 +50,7 @@ impl Ticks {
 
 impl From<Duration> for Ticks {
     fn from(dur: Duration) -> Self {
-        Ticks::from_ms(dur.as_secs() as u32 * 1000 + dur.subsec_millis())
+        Ticks(dur.as_millis() as u64)
     }
 }
 
@@ -85,13 +86,13 @@ impl Mul<u32> for Ticks {
     type Output = Ticks;
 
     fn mul(self, rhs: u32) -> Self::Output {
-        Ticks(self.0 * rhs)
+        Ticks(self.0 * u64::from(rhs))
     }
 }
 
 impl MulAssign<u32> for Ticks {
     fn mul_assign(&mut self, rhs: u32) {
-        self.0 *= rhs;
+        self.0 *= u64::from(rhs);
     }
 }
 
@@ -105,10 +106,14 @@ impl Rem for Ticks {
 
 /// Describes how long effect should be played.
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
 pub enum Repeat {
     /// Play effect until stop() is called.
     #[default]
     Infinitely,
     /// Play effect for specified time.
     For(Ticks),
+    /// Play every base effect once. Effect ends when last of base effects ends its first
+    /// playback.
+    Once,
 }
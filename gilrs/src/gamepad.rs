@@ -6,31 +6,36 @@
 // copied, modified, or distributed except according to those terms.
 
 use crate::{
+    calibration::{Calibration, CalibrationStep, Calibrator},
     ev::{
+        filter::{self, EventFilter, FilterList},
         state::{AxisData, ButtonData, GamepadState},
         Axis, AxisOrBtn, Button, Code, Event, EventType,
     },
     ff::{
-        server::{self, FfMessage, Message},
-        Error as FfError,
+        server::{self, FfMessage, Message, Runner},
+        Error as FfError, FfThreading, Mixing, TICK_DURATION,
     },
     mapping::{Mapping, MappingData, MappingDb},
+    record::{Record, Replay, ReplayMode, ReplayedGamepad},
     utils, MappingError,
 };
 
 use gilrs_core::{
-    self, AxisInfo, Error as PlatformError, Event as RawEvent, EventType as RawEventType,
+    self, AxisInfo, Error as PlatformError, EvCode, Event as RawEvent, EventType as RawEventType,
+    FfDevice,
 };
 
 use uuid::Uuid;
 
 use std::cmp::Ordering;
 use std::{
-    collections::VecDeque,
+    collections::{HashMap, VecDeque},
     error,
     fmt::{self, Display},
+    mem,
     sync::mpsc::{Receiver, Sender},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 pub use gilrs_core::PowerInfo;
@@ -39,6 +44,10 @@ pub use gilrs_core::PowerInfo;
 use serde::{Deserialize, Serialize};
 
 const DEFAULT_DEADZONE: f32 = 0.1;
+const DEFAULT_AXIS_TO_BTN: AxisToBtn = AxisToBtn::Threshold {
+    pressed: 0.75,
+    released: 0.65,
+};
 
 /// Main object responsible of managing gamepads.
 ///
@@ -47,6 +56,9 @@ const DEFAULT_DEADZONE: f32 = 0.1;
 /// disconnected. However, both functions will return `None` if gamepad with given id has never
 /// existed.
 ///
+/// Dropping `Gilrs` stops and joins its background threads and closes all gamepads. Effects that
+/// are still alive are stopped and their methods return `ff::Error::SendFailed`.
+///
 /// # Event loop
 ///
 /// All interesting actions like button was pressed or new controller was connected are represented
@@ -78,8 +90,10 @@ const DEFAULT_DEADZONE: f32 = 0.1;
 /// # Cached gamepad state
 ///
 /// `Gilrs` also menage cached gamepad state. Updating state is done automatically, unless it's
-///  disabled by `GilrsBuilder::set_update_state(false)`. However, if you are using custom filters,
-/// you still have to update state manually – to do this call `update()` method.
+///  disabled by `GilrsBuilder::set_update_state(false)`. State is updated after events pass
+/// filters installed with `GilrsBuilder::filters()`. However, if you are filtering events returned
+/// from `next_event()` yourself, you have to update state manually – to do this call `update()`
+/// method.
 ///
 /// To access state you can use `Gamepad::state()` function. Gamepad also implement some state
 /// related functions directly, see [`Gamepad`](struct.Gamepad.html) for more.
@@ -131,16 +145,22 @@ const DEFAULT_DEADZONE: f32 = 0.1;
 ///
 #[derive(Debug)]
 pub struct Gilrs {
-    inner: gilrs_core::Gilrs,
+    inner: Backend,
     next_id: usize,
     tx: Sender<Message>,
     rx: Receiver<FfMessage>,
+    ff_runner: Runner,
     counter: u64,
     mappings: MappingDb,
-    default_filters: bool,
+    calibrations: HashMap<[u8; 16], Calibration>,
+    device_calibrations: HashMap<String, Calibration>,
+    filters: FilterList,
+    filtered: VecDeque<Event>,
     events: VecDeque<Event>,
-    axis_to_btn_pressed: f32,
-    axis_to_btn_released: f32,
+    raw_events: VecDeque<RawEvent>,
+    axis_to_btn: AxisToBtnSettings,
+    coalesce_events: bool,
+    filter_replay: bool,
     pub(crate) update_state: bool,
     pub(crate) gamepads_data: Vec<GamepadData>,
 }
@@ -148,6 +168,7 @@ pub struct Gilrs {
 impl Gilrs {
     /// Creates new `Gilrs` with default settings. See [`GilrsBuilder`](struct.GilrsBuilder.html)
     /// for more details.
+    #[allow(clippy::result_large_err)]
     pub fn new() -> Result<Self, Error> {
         GilrsBuilder::new().build()
     }
@@ -175,23 +196,31 @@ impl Gilrs {
         is_blocking: bool,
         blocking_timeout: Option<Duration>,
     ) -> Option<Event> {
-        use crate::ev::filter::{axis_dpad_to_button, deadzone, Filter, Jitter};
-
-        let ev = if self.default_filters {
-            let jitter_filter = Jitter::new();
-            loop {
-                let ev = self
-                    .next_event_priv(is_blocking, blocking_timeout)
-                    .filter_ev(&axis_dpad_to_button, self)
-                    .filter_ev(&jitter_filter, self)
-                    .filter_ev(&deadzone, self);
-
-                // Skip all dropped events, there is no reason to return them
-                match ev {
-                    Some(ev) if ev.is_dropped() => (),
-                    _ => break ev,
+        // Replayed events were already filtered when they were recorded, unless user asked to
+        // filter them again with `GilrsBuilder::filter_replay()`.
+        let ev = if let Some(ev) = self.filtered.pop_front() {
+            Some(ev)
+        } else if !self.filters.is_empty() && (self.filter_replay || !self.inner.is_replay()) {
+            // Filters need `&mut Gilrs`, so they can't be borrowed from it while running.
+            let mut filters = mem::take(&mut self.filters);
+            let ev = loop {
+                let ev = self.next_event_priv(is_blocking, blocking_timeout);
+                let mut events = filters.apply(ev, self).into_iter();
+
+                match events.next() {
+                    Some(first) => {
+                        // Remaining events already went through all filters.
+                        self.filtered.extend(events);
+                        break Some(first);
+                    }
+                    None if ev.is_none() => break None,
+                    // Skip all dropped events, there is no reason to return them
+                    None => (),
                 }
-            }
+            };
+            self.filters = filters;
+
+            ev
         } else {
             self.next_event_priv(is_blocking, blocking_timeout)
         };
@@ -205,167 +234,295 @@ impl Gilrs {
         ev
     }
 
-    /// Returns next pending event.
+    /// Returns next pending event. If events are coalesced, redundant events from gamepads are
+    /// skipped. Events added with `insert_event()` are never skipped.
     fn next_event_priv(
         &mut self,
         is_blocking: bool,
         blocking_timeout: Option<Duration>,
     ) -> Option<Event> {
-        if let Some(msg) = self.rx.try_recv().ok() {
+        if let Ok(msg) = self.rx.try_recv() {
             match msg {
                 FfMessage::EffectCompleted { event } => return Some(event),
             }
         }
-        if let Some(ev) = self.events.pop_front() {
-            Some(ev)
-        } else {
-            let event = if is_blocking {
-                self.inner.next_event_blocking(blocking_timeout)
+
+        loop {
+            if let Some(ev) = self.events.pop_front() {
+                return Some(ev);
+            }
+
+            let ev = match self.inner {
+                Backend::Platform(_) => self.next_platform_event(is_blocking, blocking_timeout)?,
+                Backend::Replay(ref mut replay) => {
+                    let ev = replay.next_event(is_blocking, blocking_timeout);
+                    if let Some(ref ev) = ev {
+                        self.replayed_event(ev);
+                    }
+
+                    return ev;
+                }
+            };
+
+            if !self.coalesce_events {
+                return Some(ev);
+            }
+
+            let redundant = self.is_redundant(&ev);
+            // Remember values of `ButtonChanged` events that were queued with press or release.
+            for i in 0..self.events.len() {
+                let queued = self.events[i];
+                self.is_redundant(&queued);
+            }
+
+            if !redundant {
+                return Some(ev);
+            }
+        }
+    }
+
+    /// Returns true if `ev` changes value of button or axis to the same value as previous event.
+    /// Remembers value from `ev` otherwise.
+    fn is_redundant(&mut self, ev: &Event) -> bool {
+        let (val, nec) = match ev.event {
+            EventType::ButtonChanged(_, val, nec) | EventType::AxisChanged(_, val, nec) => {
+                (val, nec)
+            }
+            _ => return false,
+        };
+
+        match self.gamepads_data.get_mut(ev.id.0) {
+            Some(data) => data.last_values.insert(nec, val) == Some(val),
+            None => false,
+        }
+    }
+
+    /// Returns next raw event from backend. If events are coalesced, all available events are
+    /// read at once and changes of the same axis are merged.
+    fn next_raw_event(
+        &mut self,
+        is_blocking: bool,
+        blocking_timeout: Option<Duration>,
+    ) -> Option<RawEvent> {
+        let inner = match self.inner {
+            Backend::Platform(ref mut inner) => inner,
+            Backend::Replay(_) => return None,
+        };
+
+        if !self.coalesce_events && self.raw_events.is_empty() {
+            return if is_blocking {
+                inner.next_event_blocking(blocking_timeout)
             } else {
-                self.inner.next_event()
+                inner.next_event()
             };
+        }
 
-            match event {
-                Some(RawEvent { id, event, time }) => {
-                    trace!("Original event: {:?}", RawEvent { id, event, time });
-                    let id = GamepadId(id);
+        if self.raw_events.is_empty() {
+            let mut next = if is_blocking {
+                inner.next_event_blocking(blocking_timeout)
+            } else {
+                inner.next_event()
+            };
+            let gamepads_data = &self.gamepads_data;
+            // D-pad axes are turned into button events, so they are treated as buttons.
+            let is_button = |id: usize, nec: EvCode| {
+                gamepads_data.get(id).map_or(true, |data| {
+                    !matches!(
+                        data.axis_or_btn_name(Code(nec)),
+                        Some(AxisOrBtn::Axis(axis)) if axis != Axis::DPadX && axis != Axis::DPadY
+                    )
+                })
+            };
 
-                    let event = match event {
-                        RawEventType::ButtonPressed(nec) => {
-                            let nec = Code(nec);
-                            match self.gamepad(id).axis_or_btn_name(nec) {
-                                Some(AxisOrBtn::Btn(b)) => {
-                                    self.events.push_back(Event {
-                                        id,
-                                        time,
-                                        event: EventType::ButtonChanged(b, 1.0, nec),
-                                    });
+            while let Some(ev) = next {
+                coalesce_raw_event(&mut self.raw_events, ev, is_button);
+                next = inner.next_event();
+            }
+        }
 
-                                    EventType::ButtonPressed(b, nec)
-                                }
-                                Some(AxisOrBtn::Axis(a)) => EventType::AxisChanged(a, 1.0, nec),
-                                None => {
-                                    self.events.push_back(Event {
-                                        id,
-                                        time,
-                                        event: EventType::ButtonChanged(Button::Unknown, 1.0, nec),
-                                    });
+        self.raw_events.pop_front()
+    }
 
-                                    EventType::ButtonPressed(Button::Unknown, nec)
-                                }
+    /// Returns next event from platform backend. Additional events created from the same raw
+    /// event are added to `events` queue.
+    fn next_platform_event(
+        &mut self,
+        is_blocking: bool,
+        blocking_timeout: Option<Duration>,
+    ) -> Option<Event> {
+        let event = self.next_raw_event(is_blocking, blocking_timeout);
+
+        match event {
+            Some(RawEvent { id, event, time }) => {
+                trace!("Original event: {:?}", RawEvent { id, event, time });
+                let id = GamepadId(id);
+
+                let event = match event {
+                    RawEventType::ButtonPressed(nec) => {
+                        let nec = Code(nec);
+                        match self.gamepad(id).axis_or_btn_name(nec) {
+                            Some(AxisOrBtn::Btn(b)) => {
+                                self.events.push_back(Event {
+                                    id,
+                                    time,
+                                    event: EventType::ButtonChanged(b, 1.0, nec),
+                                });
+
+                                EventType::ButtonPressed(b, nec)
+                            }
+                            Some(AxisOrBtn::Axis(a)) => EventType::AxisChanged(a, 1.0, nec),
+                            None => {
+                                self.events.push_back(Event {
+                                    id,
+                                    time,
+                                    event: EventType::ButtonChanged(Button::Unknown, 1.0, nec),
+                                });
+
+                                EventType::ButtonPressed(Button::Unknown, nec)
+                            }
+                        }
+                    }
+                    RawEventType::ButtonReleased(nec) => {
+                        let nec = Code(nec);
+                        match self.gamepad(id).axis_or_btn_name(nec) {
+                            Some(AxisOrBtn::Btn(b)) => {
+                                self.events.push_back(Event {
+                                    id,
+                                    time,
+                                    event: EventType::ButtonChanged(b, 0.0, nec),
+                                });
+
+                                EventType::ButtonReleased(b, nec)
                             }
+                            Some(AxisOrBtn::Axis(a)) => EventType::AxisChanged(a, 0.0, nec),
+                            None => {
+                                self.events.push_back(Event {
+                                    id,
+                                    time,
+                                    event: EventType::ButtonChanged(Button::Unknown, 0.0, nec),
+                                });
+
+                                EventType::ButtonReleased(Button::Unknown, nec)
+                            }
+                        }
+                    }
+                    RawEventType::AxisValueChanged(val, nec) => {
+                        // Let's trust at least our backend code
+                        let axis_info = *self.gamepad(id).inner.axis_info(nec).unwrap();
+                        let nec = Code(nec);
+
+                        if let Some(ref mut calibrator) = self.gamepads_data[id.0].calibrator {
+                            calibrator.observe(nec, val);
                         }
-                        RawEventType::ButtonReleased(nec) => {
-                            let nec = Code(nec);
-                            match self.gamepad(id).axis_or_btn_name(nec) {
-                                Some(AxisOrBtn::Btn(b)) => {
+                        let calibration = self
+                            .gamepad_calibration(id)
+                            .and_then(|c| c.axis(nec))
+                            .copied();
+                        let calibrated = |use_center| match calibration {
+                            Some(c) => c.apply(val, &axis_info, use_center),
+                            None => val,
+                        };
+
+                        match self.gamepad(id).axis_or_btn_name(nec) {
+                            Some(AxisOrBtn::Btn(b)) => {
+                                let val = btn_value(&axis_info, calibrated(false));
+                                let mode = self.axis_to_btn_mode(id, b);
+                                let is_pressed = self.gamepad(id).state().is_pressed(nec);
+                                let extreme = self.gamepads_data[id.0]
+                                    .btn_extremes
+                                    .entry(nec)
+                                    .or_insert(val);
+
+                                let pressed = mode.update(val, is_pressed, extreme);
+                                if pressed == Some(true) {
                                     self.events.push_back(Event {
                                         id,
                                         time,
-                                        event: EventType::ButtonChanged(b, 0.0, nec),
+                                        event: EventType::ButtonChanged(b, val, nec),
                                     });
 
-                                    EventType::ButtonReleased(b, nec)
-                                }
-                                Some(AxisOrBtn::Axis(a)) => EventType::AxisChanged(a, 0.0, nec),
-                                None => {
+                                    EventType::ButtonPressed(b, nec)
+                                } else if pressed == Some(false) {
                                     self.events.push_back(Event {
                                         id,
                                         time,
-                                        event: EventType::ButtonChanged(Button::Unknown, 0.0, nec),
+                                        event: EventType::ButtonChanged(b, val, nec),
                                     });
 
-                                    EventType::ButtonReleased(Button::Unknown, nec)
-                                }
-                            }
-                        }
-                        RawEventType::AxisValueChanged(val, nec) => {
-                            // Let's trust at least our backend code
-                            let axis_info = *self.gamepad(id).inner.axis_info(nec).unwrap();
-                            let nec = Code(nec);
-
-                            match self.gamepad(id).axis_or_btn_name(nec) {
-                                Some(AxisOrBtn::Btn(b)) => {
-                                    let val = btn_value(&axis_info, val);
-
-                                    if val >= self.axis_to_btn_pressed
-                                        && !self.gamepad(id).state().is_pressed(nec)
-                                    {
-                                        self.events.push_back(Event {
-                                            id,
-                                            time,
-                                            event: EventType::ButtonChanged(b, val, nec),
-                                        });
-
-                                        EventType::ButtonPressed(b, nec)
-                                    } else if val <= self.axis_to_btn_released
-                                        && self.gamepad(id).state().is_pressed(nec)
-                                    {
-                                        self.events.push_back(Event {
-                                            id,
-                                            time,
-                                            event: EventType::ButtonChanged(b, val, nec),
-                                        });
-
-                                        EventType::ButtonReleased(b, nec)
-                                    } else {
-                                        EventType::ButtonChanged(b, val, nec)
-                                    }
-                                }
-                                Some(AxisOrBtn::Axis(a)) => {
-                                    EventType::AxisChanged(a, axis_value(&axis_info, val, a), nec)
+                                    EventType::ButtonReleased(b, nec)
+                                } else {
+                                    EventType::ButtonChanged(b, val, nec)
                                 }
-                                None => EventType::AxisChanged(
-                                    Axis::Unknown,
-                                    axis_value(&axis_info, val, Axis::Unknown),
-                                    nec,
-                                ),
                             }
+                            Some(AxisOrBtn::Axis(a)) => EventType::AxisChanged(
+                                a,
+                                axis_value(&axis_info, calibrated(true), a),
+                                nec,
+                            ),
+                            None => EventType::AxisChanged(
+                                Axis::Unknown,
+                                axis_value(&axis_info, calibrated(true), Axis::Unknown),
+                                nec,
+                            ),
                         }
-                        RawEventType::Connected => {
-                            match id.0.cmp(&self.gamepads_data.len()) {
-                                Ordering::Equal => {
-                                    self.gamepads_data.push(GamepadData::new(
-                                        id,
-                                        self.tx.clone(),
-                                        self.inner.gamepad(id.0).unwrap(),
-                                        &self.mappings,
-                                    ));
-                                }
-                                Ordering::Less => {
-                                    self.gamepads_data[id.0] = GamepadData::new(
-                                        id,
-                                        self.tx.clone(),
-                                        self.inner.gamepad(id.0).unwrap(),
-                                        &self.mappings,
-                                    );
-                                }
-                                Ordering::Greater => {
-                                    error!(
-                                        "Platform implementation error: got Connected event with \
-                                         id {}, when expected id {}",
-                                        id.0,
-                                        self.gamepads_data.len()
-                                    );
-                                }
-                            }
+                    }
+                    RawEventType::Connected => {
+                        self.gamepad_connected(id);
 
-                            EventType::Connected
-                        }
-                        RawEventType::Disconnected => {
-                            let _ = self.tx.send(Message::Close { id: id.0 });
+                        EventType::Connected
+                    }
+                    RawEventType::Disconnected => {
+                        let _ = self.tx.send(Message::Close { id: id.0 });
 
-                            EventType::Disconnected
-                        }
-                    };
+                        EventType::Disconnected
+                    }
+                };
 
-                    Some(Event { id, event, time })
-                }
-                None => None,
+                Some(Event { id, event, time })
+            }
+            None => None,
+        }
+    }
+
+    fn gamepad_connected(&mut self, id: GamepadId) {
+        match id.0.cmp(&self.gamepads_data.len()) {
+            Ordering::Equal => {
+                self.gamepads_data.push(GamepadData::new(
+                    id,
+                    self.tx.clone(),
+                    self.inner.gamepad(id.0).unwrap(),
+                    &self.mappings,
+                ));
+            }
+            Ordering::Less => {
+                self.gamepads_data[id.0] = GamepadData::new(
+                    id,
+                    self.tx.clone(),
+                    self.inner.gamepad(id.0).unwrap(),
+                    &self.mappings,
+                );
+            }
+            Ordering::Greater => {
+                error!(
+                    "Platform implementation error: got Connected event with id {}, when \
+                     expected id {}",
+                    id.0,
+                    self.gamepads_data.len()
+                );
             }
         }
     }
 
+    fn replayed_event(&mut self, ev: &Event) {
+        // Gamepads can be described in recording long before their first event.
+        let known = self.gamepads_data.len();
+        self.finish_gamepads_creation();
+
+        if ev.event == EventType::Connected && ev.id.0 < known {
+            self.gamepad_connected(ev.id);
+        }
+    }
+
     /// Updates internal state according to `event`.
     ///
     /// Please note, that it's not necessary to call this function unless you modify events by using
@@ -381,23 +538,32 @@ impl Gilrs {
         };
 
         match event.event {
-            ButtonPressed(_, nec) => {
-                data.state.set_btn_pressed(nec, true, counter, event.time);
+            ButtonPressed(btn, nec) => {
+                data.state
+                    .set_btn_pressed(nec, btn, true, counter, event.time);
             }
-            ButtonReleased(_, nec) => {
-                data.state.set_btn_pressed(nec, false, counter, event.time);
+            ButtonReleased(btn, nec) => {
+                data.state
+                    .set_btn_pressed(nec, btn, false, counter, event.time);
             }
-            ButtonRepeated(_, nec) => {
-                data.state.set_btn_repeating(nec, counter, event.time);
+            ButtonRepeated(btn, nec) => {
+                data.state.set_btn_repeating(nec, btn, counter, event.time);
             }
-            ButtonChanged(_, value, nec) => {
-                data.state.set_btn_value(nec, value, counter, event.time);
+            ButtonChanged(btn, value, nec) => {
+                data.state
+                    .set_btn_value(nec, btn, value, counter, event.time);
             }
-            AxisChanged(_, value, nec) => {
+            AxisChanged(axis, value, nec) => {
                 data.state
-                    .update_axis(nec, AxisData::new(value, counter, event.time));
+                    .update_axis(nec, axis, AxisData::new(value, counter, event.time));
             }
-            Disconnected | Connected | Dropped | ForceFeedbackEffectCompleted => (),
+            Disconnected
+            | Connected
+            | Dropped
+            | ForceFeedbackEffectCompleted(_)
+            | ButtonDoubleTapped(..)
+            | ButtonLongPressed(..)
+            | ChordPressed(_) => (),
         }
     }
 
@@ -424,9 +590,19 @@ impl Gilrs {
         self.counter = 0;
     }
 
+    /// Returns `true` if this `Gilrs` was created with
+    /// [`GilrsBuilder::replay()`](struct.GilrsBuilder.html#method.replay) and all recorded events
+    /// were already returned. Always returns `false` for real gamepads.
+    pub fn is_replay_finished(&mut self) -> bool {
+        match self.inner {
+            Backend::Replay(ref mut replay) => replay.is_finished() && self.events.is_empty(),
+            Backend::Platform(_) => false,
+        }
+    }
+
     fn finish_gamepads_creation(&mut self) {
         let tx = self.tx.clone();
-        for id in 0..self.inner.last_gamepad_hint() {
+        for id in self.gamepads_data.len()..self.inner.last_gamepad_hint() {
             let gamepad = self.inner.gamepad(id).unwrap();
             self.gamepads_data.push(GamepadData::new(
                 GamepadId(id),
@@ -459,7 +635,7 @@ impl Gilrs {
     ///     # break;
     /// }
     /// ```
-    pub fn gamepad(&self, id: GamepadId) -> Gamepad {
+    pub fn gamepad(&self, id: GamepadId) -> Gamepad<'_> {
         Gamepad {
             inner: self.inner.gamepad(id.0).unwrap(),
             data: &self.gamepads_data[id.0],
@@ -502,6 +678,152 @@ impl Gilrs {
         self.events.push_back(ev);
     }
 
+    /// Sets calibration used for all gamepads with given UUID. Only new events are affected, cached
+    /// state is not changed.
+    ///
+    /// See [`calibration`](calibration/index.html) module for more info.
+    pub fn set_calibration(&mut self, uuid: [u8; 16], calibration: Calibration) {
+        self.calibrations.insert(uuid, calibration);
+    }
+
+    /// Returns calibration used for gamepads with given UUID.
+    pub fn calibration(&self, uuid: [u8; 16]) -> Option<&Calibration> {
+        self.calibrations.get(&uuid)
+    }
+
+    /// Removes calibration for gamepads with given UUID and returns it.
+    pub fn remove_calibration(&mut self, uuid: [u8; 16]) -> Option<Calibration> {
+        self.calibrations.remove(&uuid)
+    }
+
+    /// Sets calibration used for single device with given
+    /// [`uniq`](struct.Gamepad.html#method.uniq). It takes precedence over calibration set for
+    /// device's UUID.
+    pub fn set_device_calibration(&mut self, uniq: &str, calibration: Calibration) {
+        self.device_calibrations
+            .insert(uniq.to_owned(), calibration);
+    }
+
+    /// Returns calibration used for device with given `uniq`.
+    pub fn device_calibration(&self, uniq: &str) -> Option<&Calibration> {
+        self.device_calibrations.get(uniq)
+    }
+
+    /// Removes calibration for device with given `uniq` and returns it.
+    pub fn remove_device_calibration(&mut self, uniq: &str) -> Option<Calibration> {
+        self.device_calibrations.remove(uniq)
+    }
+
+    fn gamepad_calibration(&self, id: GamepadId) -> Option<&Calibration> {
+        let gamepad = self.gamepad(id);
+
+        gamepad
+            .uniq()
+            .and_then(|uniq| self.device_calibrations.get(uniq))
+            .or_else(|| self.calibrations.get(&gamepad.uuid()))
+    }
+
+    /// Starts calibration session for gamepad. Raw axes values will be observed until
+    /// `finish_calibration()` is called. If session was already started, it is restarted.
+    pub fn start_calibration(&mut self, id: GamepadId, step: CalibrationStep) {
+        if let Some(data) = self.gamepads_data.get_mut(id.0) {
+            data.calibrator = Some(Calibrator::new(step));
+        }
+    }
+
+    /// Changes step of calibration session started with `start_calibration()`.
+    pub fn set_calibration_step(&mut self, id: GamepadId, step: CalibrationStep) {
+        if let Some(calibrator) = self
+            .gamepads_data
+            .get_mut(id.0)
+            .and_then(|data| data.calibrator.as_mut())
+        {
+            calibrator.set_step(step);
+        }
+    }
+
+    /// Ends calibration session, sets created calibration for gamepad and returns it. Calibration is
+    /// set for gamepad's [`uniq`](struct.Gamepad.html#method.uniq) if it has one, otherwise for its
+    /// UUID. Returns `None` if there was no session for this gamepad.
+    ///
+    /// Only axes that moved during the session are calibrated.
+    pub fn finish_calibration(&mut self, id: GamepadId) -> Option<Calibration> {
+        let calibration = self
+            .gamepads_data
+            .get_mut(id.0)?
+            .calibrator
+            .take()?
+            .finish();
+        let gamepad = self.gamepad(id);
+        match gamepad.uniq().map(ToOwned::to_owned) {
+            Some(uniq) => self.device_calibrations.insert(uniq, calibration.clone()),
+            None => self
+                .calibrations
+                .insert(gamepad.uuid(), calibration.clone()),
+        };
+
+        Some(calibration)
+    }
+
+    /// Enables or disables coalescing of events.
+    ///
+    /// When enabled, all events that are available from gamepads are read at once. Changes of the
+    /// same axis are merged into one event with the latest value, as long as there was no other
+    /// kind of event from that gamepad in between. `ButtonChanged` and `AxisChanged` events with
+    /// the same value as previous event for that element are skipped. Button presses and releases
+    /// are never merged or skipped, including presses of analog buttons. Events added with
+    /// `insert_event()` are never skipped.
+    pub fn set_coalesce_events(&mut self, enabled: bool) {
+        self.coalesce_events = enabled;
+        // Values are remembered only while coalescing, so they may be outdated now.
+        for data in &mut self.gamepads_data {
+            data.last_values.clear();
+        }
+    }
+
+    /// Returns true if events are coalesced.
+    pub fn coalesces_events(&self) -> bool {
+        self.coalesce_events
+    }
+
+    /// Sets how analog `button` (or all buttons if `None`) of gamepads in `scope` is turned into
+    /// `ButtonPressed` and `ButtonReleased` events.
+    ///
+    /// Most specific settings are used: first for gamepad's ID, then for its UUID and then for
+    /// all gamepads. In every scope, settings for given button are preferred.
+    ///
+    /// Returns `Error::InvalidAxisToBtn` if `mode` is invalid.
+    #[allow(clippy::result_large_err)]
+    pub fn set_axis_to_btn_mode(
+        &mut self,
+        scope: AxisToBtnScope,
+        button: Option<Button>,
+        mode: AxisToBtn,
+    ) -> Result<(), Error> {
+        if !mode.is_valid() {
+            return Err(Error::InvalidAxisToBtn);
+        }
+
+        self.axis_to_btn.set(scope, button, mode);
+
+        Ok(())
+    }
+
+    /// Removes settings set with `set_axis_to_btn_mode()`. Settings for all buttons of all gamepads
+    /// can't be removed.
+    pub fn remove_axis_to_btn_mode(&mut self, scope: AxisToBtnScope, button: Option<Button>) {
+        if scope != AxisToBtnScope::All || button.is_some() {
+            self.axis_to_btn.0.remove(&(scope, button));
+        }
+    }
+
+    /// Returns settings used for `button` of gamepad with `id`.
+    pub fn axis_to_btn_mode(&self, id: GamepadId, button: Button) -> AxisToBtn {
+        let uuid = self.gamepad(id).uuid();
+
+        self.axis_to_btn.get(id, uuid, button)
+    }
+
     pub(crate) fn ff_sender(&self) -> &Sender<Message> {
         &self.tx
     }
@@ -598,6 +920,27 @@ impl Gilrs {
         }
     }
 
+    /// Changes gain of force feedback on all gamepads. It's applied together with gain set by
+    /// [`Gamepad::set_ff_gain()`](struct.Gamepad.html#method.set_ff_gain). Zero turns force
+    /// feedback off. `gain` will be clamped to \[0.0, f32::MAX\].
+    pub fn set_ff_gain(&self, gain: f32) -> Result<(), FfError> {
+        self.tx.send(Message::SetGlobalGain {
+            gain: utils::clamp(gain, 0.0, f32::MAX),
+        })?;
+
+        Ok(())
+    }
+
+    /// Handles pending force feedback commands and sends state of effects at `now` to gamepads.
+    /// Does nothing unless `Gilrs` was created with `FfThreading::Manual`.
+    ///
+    /// Time of effects is measured from the first call to this function.
+    pub fn update_ff(&mut self, now: Instant) {
+        if let Runner::Manual(ref mut server) = self.ff_runner {
+            server.update(now);
+        }
+    }
+
     pub(crate) fn next_ff_id(&mut self) -> usize {
         // TODO: reuse free ids
         let id = self.next_id;
@@ -609,12 +952,29 @@ impl Gilrs {
     }
 }
 
+impl Drop for Gilrs {
+    fn drop(&mut self) {
+        if let Runner::Thread(ff_thread) = mem::replace(&mut self.ff_runner, Runner::None) {
+            let _ = self.tx.send(Message::Shutdown);
+            if ff_thread.join().is_err() {
+                error!("Force feedback thread panicked.");
+            }
+        }
+    }
+}
+
 /// Allow to create `Gilrs ` with customized behaviour.
 pub struct GilrsBuilder {
+    replay: Option<Replay>,
+    filter_replay: bool,
     mappings: MappingDb,
-    default_filters: bool,
-    axis_to_btn_pressed: f32,
-    axis_to_btn_released: f32,
+    calibrations: HashMap<[u8; 16], Calibration>,
+    device_calibrations: HashMap<String, Calibration>,
+    filters: FilterList,
+    axis_to_btn: AxisToBtnSettings,
+    coalesce_events: bool,
+    ff_tick_duration: Duration,
+    ff_threading: FfThreading,
     update_state: bool,
     env_mappings: bool,
     included_mappings: bool,
@@ -624,21 +984,86 @@ impl GilrsBuilder {
     /// Create builder with default settings. Use `build()` to create `Gilrs`.
     pub fn new() -> Self {
         GilrsBuilder {
+            replay: None,
+            filter_replay: false,
             mappings: MappingDb::new(),
-            default_filters: true,
-            axis_to_btn_pressed: 0.75,
-            axis_to_btn_released: 0.65,
+            calibrations: HashMap::new(),
+            device_calibrations: HashMap::new(),
+            filters: filter::default_filters().into_iter().collect(),
+            axis_to_btn: AxisToBtnSettings::default(),
+            coalesce_events: false,
+            ff_tick_duration: Duration::from_millis(TICK_DURATION.into()),
+            ff_threading: FfThreading::Thread,
             update_state: true,
             env_mappings: true,
             included_mappings: true,
         }
     }
 
-    /// If `true`, use [`axis_dpad_to_button`](ev/filter/fn.axis_dpad_to_button.html),
-    /// [`Jitter`](ev/filter/struct.Jitter.html) and [`deadzone`](ev/filter/fn.deadzone.html)
+    /// If `true`, use [`AxisDpadToButton`](ev/filter/struct.AxisDpadToButton.html),
+    /// [`Jitter`](ev/filter/struct.Jitter.html) and [`Deadzone`](ev/filter/struct.Deadzone.html)
     /// filters with default parameters. Defaults to `true`.
+    ///
+    /// This replaces filters set by previous calls to `filters()` and `add_filter()`. If `false`,
+    /// there will be no filters at all.
     pub fn with_default_filters(mut self, default_filters: bool) -> Self {
-        self.default_filters = default_filters;
+        self.filters = if default_filters {
+            filter::default_filters().into_iter().collect()
+        } else {
+            FilterList::default()
+        };
+
+        self
+    }
+
+    /// Sets filters that will be applied, in order, to every event before it is returned from
+    /// `next_event()` and before cached state is updated. Dropped events are skipped.
+    ///
+    /// Default filters are not added automatically. To keep some of them, include them in
+    /// `filters`, for example start from [`default_filters()`](ev/filter/fn.default_filters.html)
+    /// and remove the ones you don't want.
+    ///
+    /// ```
+    /// use gilrs::GilrsBuilder;
+    /// use gilrs::ev::filter::{AxisDpadToButton, Deadzone, EventFilter, Jitter, Repeat};
+    ///
+    /// let filters: Vec<Box<dyn EventFilter + Send>> = vec![
+    ///     Box::new(AxisDpadToButton),
+    ///     Box::new(Jitter { threshold: 0.05 }),
+    ///     Box::new(Deadzone::new()),
+    ///     Box::new(Repeat::new()),
+    /// ];
+    /// let gilrs = GilrsBuilder::new().filters(filters).build().unwrap();
+    /// ```
+    pub fn filters<I>(mut self, filters: I) -> Self
+    where
+        I: IntoIterator<Item = Box<dyn EventFilter + Send>>,
+    {
+        self.filters = filters.into_iter().collect();
+
+        self
+    }
+
+    /// Adds `filter` at the end of filter list. See [`filters()`](#method.filters).
+    pub fn add_filter<F: EventFilter + Send + 'static>(mut self, filter: F) -> Self {
+        self.filters.push(Box::new(filter));
+
+        self
+    }
+
+    /// Sets calibration used for all gamepads with given UUID. See
+    /// [`Gilrs::set_calibration()`](struct.Gilrs.html#method.set_calibration).
+    pub fn add_calibration(mut self, uuid: [u8; 16], calibration: Calibration) -> Self {
+        self.calibrations.insert(uuid, calibration);
+
+        self
+    }
+
+    /// Sets calibration used for single device with given `uniq`. See
+    /// [`Gilrs::set_device_calibration()`](struct.Gilrs.html#method.set_device_calibration).
+    pub fn add_device_calibration(mut self, uniq: &str, calibration: Calibration) -> Self {
+        self.device_calibrations
+            .insert(uniq.to_owned(), calibration);
 
         self
     }
@@ -670,9 +1095,57 @@ impl GilrsBuilder {
     /// will return error if `pressed ≤ released` or if one of values is outside [0.0, 1.0].
     ///
     /// Defaults to 0.75 for `pressed` and 0.65 for `released`.
+    ///
+    /// This is the same as `axis_to_btn(AxisToBtnScope::All, None, AxisToBtn::Threshold { .. })`.
     pub fn set_axis_to_btn(mut self, pressed: f32, released: f32) -> Self {
-        self.axis_to_btn_pressed = pressed;
-        self.axis_to_btn_released = released;
+        self.axis_to_btn.set(
+            AxisToBtnScope::All,
+            None,
+            AxisToBtn::Threshold { pressed, released },
+        );
+
+        self
+    }
+
+    /// Sets how analog `button` (or all buttons if `None`) of gamepads in `scope` is turned into
+    /// `ButtonPressed` and `ButtonReleased` events. `build()` will return error if `mode` is
+    /// invalid. Settings can be also changed later with
+    /// [`Gilrs::set_axis_to_btn_mode()`](struct.Gilrs.html#method.set_axis_to_btn_mode).
+    pub fn axis_to_btn(
+        mut self,
+        scope: AxisToBtnScope,
+        button: Option<Button>,
+        mode: AxisToBtn,
+    ) -> Self {
+        self.axis_to_btn.set(scope, button, mode);
+
+        self
+    }
+
+    /// If true, events from gamepads that report at high rate are coalesced. See
+    /// [`Gilrs::set_coalesce_events()`](struct.Gilrs.html#method.set_coalesce_events). Defaults to
+    /// false.
+    pub fn coalesce_events(mut self, enabled: bool) -> Self {
+        self.coalesce_events = enabled;
+
+        self
+    }
+
+    /// Sets how often force feedback thread updates state of gamepads. Shorter duration allows
+    /// shorter effects and smoother envelopes at the cost of more CPU time. Duration shorter than
+    /// 1ms is treated as 1ms. Defaults to 50ms.
+    pub fn ff_tick_duration(mut self, duration: Duration) -> Self {
+        self.ff_tick_duration = duration.max(Duration::from_millis(1));
+
+        self
+    }
+
+    /// Sets how force feedback effects are updated. With `FfThreading::Manual` no thread is
+    /// spawned and [`Gilrs::update_ff()`](struct.Gilrs.html#method.update_ff) has to be called
+    /// regularly, at least once per [`ff_tick_duration()`](#method.ff_tick_duration). Defaults to
+    /// `FfThreading::Thread`.
+    pub fn ff_threading(mut self, threading: FfThreading) -> Self {
+        self.ff_threading = threading;
 
         self
     }
@@ -685,7 +1158,34 @@ impl GilrsBuilder {
         self
     }
 
+    /// Replays `records` instead of using real gamepads. Gamepads described at the beginning of
+    /// recording are available right after `build()`, as if they were connected. By default,
+    /// filters are not applied to replayed events, because they were applied before events were
+    /// recorded. Use [`filter_replay()`](#method.filter_replay) to change it.
+    ///
+    /// See [`record`](record/index.html) module for more info.
+    pub fn replay<I>(mut self, records: I, mode: ReplayMode) -> Self
+    where
+        I: IntoIterator<Item = Record>,
+        I::IntoIter: Send + 'static,
+    {
+        self.replay = Some(Replay::new(records, mode));
+
+        self
+    }
+
+    /// If `true`, replayed events go through filters like events from real gamepads. Useful when
+    /// recording was made without filters (for example with `with_default_filters(false)`) and
+    /// you want to test filters on it. Has no effect if `replay()` is not used. Defaults to
+    /// `false`.
+    pub fn filter_replay(mut self, enabled: bool) -> Self {
+        self.filter_replay = enabled;
+
+        self
+    }
+
     /// Creates `Gilrs`.
+    #[allow(clippy::result_large_err)]
     pub fn build(mut self) -> Result<Gilrs, Error> {
         if self.included_mappings {
             self.mappings.add_included_mappings();
@@ -697,39 +1197,43 @@ impl GilrsBuilder {
 
         debug!("Loaded {} mappings.", self.mappings.len());
 
-        if self.axis_to_btn_pressed <= self.axis_to_btn_released
-            || self.axis_to_btn_pressed < 0.0
-            || self.axis_to_btn_pressed > 1.0
-            || self.axis_to_btn_released < 0.0
-            || self.axis_to_btn_released > 1.0
-        {
+        if !self.axis_to_btn.is_valid() {
             return Err(Error::InvalidAxisToBtn);
         }
 
         let mut is_dummy = false;
-        let inner = match gilrs_core::Gilrs::new() {
-            Ok(g) => g,
-            Err(PlatformError::NotImplemented(g)) => {
-                is_dummy = true;
-
-                g
-            }
-            Err(PlatformError::Other(e)) => return Err(Error::Other(e)),
+        let inner = match self.replay {
+            Some(replay) => Backend::Replay(replay),
+            None => match gilrs_core::Gilrs::new() {
+                Ok(g) => Backend::Platform(g),
+                Err(PlatformError::NotImplemented(g)) => {
+                    is_dummy = true;
+
+                    Backend::Platform(g)
+                }
+                Err(PlatformError::Other(e)) => return Err(Error::Other(e)),
+            },
         };
 
-        let (tx, rx) = server::init();
+        let (tx, rx, ff_runner) = server::init(self.ff_tick_duration, self.ff_threading);
 
         let mut gilrs = Gilrs {
             inner,
             next_id: 0,
             tx,
             rx,
+            ff_runner,
             counter: 0,
             mappings: self.mappings,
-            default_filters: self.default_filters,
+            calibrations: self.calibrations,
+            device_calibrations: self.device_calibrations,
+            filters: self.filters,
+            filtered: VecDeque::new(),
             events: VecDeque::new(),
-            axis_to_btn_pressed: self.axis_to_btn_pressed,
-            axis_to_btn_released: self.axis_to_btn_released,
+            raw_events: VecDeque::new(),
+            axis_to_btn: self.axis_to_btn,
+            coalesce_events: self.coalesce_events,
+            filter_replay: self.filter_replay,
             update_state: self.update_state,
             gamepads_data: Vec::new(),
         };
@@ -749,6 +1253,133 @@ impl Default for GilrsBuilder {
     }
 }
 
+/// Source of gamepads and raw events.
+#[derive(Debug)]
+enum Backend {
+    Platform(gilrs_core::Gilrs),
+    Replay(Replay),
+}
+
+impl Backend {
+    fn gamepad(&self, id: usize) -> Option<GamepadInner<'_>> {
+        match self {
+            Backend::Platform(g) => g.gamepad(id).map(GamepadInner::Platform),
+            Backend::Replay(r) => r.gamepad(id).map(GamepadInner::Replay),
+        }
+    }
+
+    fn last_gamepad_hint(&self) -> usize {
+        match self {
+            Backend::Platform(g) => g.last_gamepad_hint(),
+            Backend::Replay(r) => r.last_gamepad_hint(),
+        }
+    }
+
+    fn is_replay(&self) -> bool {
+        matches!(self, Backend::Replay(_))
+    }
+}
+
+/// Real or replayed gamepad.
+#[derive(Debug, Copy, Clone)]
+pub(crate) enum GamepadInner<'a> {
+    Platform(&'a gilrs_core::Gamepad),
+    Replay(&'a ReplayedGamepad),
+}
+
+impl<'a> GamepadInner<'a> {
+    pub fn name(self) -> &'a str {
+        match self {
+            GamepadInner::Platform(g) => g.name(),
+            GamepadInner::Replay(g) => g.name(),
+        }
+    }
+
+    pub fn uuid(self) -> [u8; 16] {
+        match self {
+            GamepadInner::Platform(g) => g.uuid(),
+            GamepadInner::Replay(g) => g.uuid(),
+        }
+    }
+
+    pub fn uniq(self) -> Option<&'a str> {
+        match self {
+            GamepadInner::Platform(g) => g.uniq(),
+            GamepadInner::Replay(g) => g.uniq(),
+        }
+    }
+
+    pub fn vendor_id(self) -> Option<u16> {
+        match self {
+            GamepadInner::Platform(g) => g.vendor_id(),
+            GamepadInner::Replay(g) => g.vendor_id(),
+        }
+    }
+
+    pub fn product_id(self) -> Option<u16> {
+        match self {
+            GamepadInner::Platform(g) => g.product_id(),
+            GamepadInner::Replay(g) => g.product_id(),
+        }
+    }
+
+    pub fn is_connected(self) -> bool {
+        match self {
+            GamepadInner::Platform(g) => g.is_connected(),
+            GamepadInner::Replay(g) => g.is_connected(),
+        }
+    }
+
+    pub fn power_info(self) -> PowerInfo {
+        match self {
+            GamepadInner::Platform(g) => g.power_info(),
+            GamepadInner::Replay(g) => g.power_info(),
+        }
+    }
+
+    pub fn is_ff_supported(self) -> bool {
+        match self {
+            GamepadInner::Platform(g) => g.is_ff_supported(),
+            GamepadInner::Replay(_) => false,
+        }
+    }
+
+    pub fn set_player_leds(self, player: Option<u8>) -> bool {
+        match self {
+            GamepadInner::Platform(g) => g.set_player_leds(player),
+            GamepadInner::Replay(_) => false,
+        }
+    }
+
+    pub fn ff_device(self) -> Option<FfDevice> {
+        match self {
+            GamepadInner::Platform(g) => g.ff_device(),
+            GamepadInner::Replay(_) => None,
+        }
+    }
+
+    pub fn buttons(self) -> &'a [EvCode] {
+        match self {
+            GamepadInner::Platform(g) => g.buttons(),
+            GamepadInner::Replay(g) => g.buttons(),
+        }
+    }
+
+    pub fn axes(self) -> &'a [EvCode] {
+        match self {
+            GamepadInner::Platform(g) => g.axes(),
+            GamepadInner::Replay(g) => g.axes(),
+        }
+    }
+
+    pub fn axis_info(self, nec: EvCode) -> Option<&'a AxisInfo> {
+        match self {
+            GamepadInner::Platform(g) => g.axis_info(nec),
+            GamepadInner::Replay(g) => g.axis_info(nec),
+        }
+    }
+}
+
 /// Iterator over all connected gamepads.
 pub struct ConnectedGamepadsIterator<'a>(&'a Gilrs, usize);
 
@@ -779,7 +1410,7 @@ impl<'a> Iterator for ConnectedGamepadsIterator<'a> {
 #[derive(Debug, Copy, Clone)]
 pub struct Gamepad<'a> {
     data: &'a GamepadData,
-    inner: &'a gilrs_core::Gamepad,
+    inner: GamepadInner<'a>,
 }
 
 impl<'a> Gamepad<'a> {
@@ -811,6 +1442,14 @@ impl<'a> Gamepad<'a> {
         self.inner.uuid()
     }
 
+    /// Returns unique identifier of this device (for example serial number or MAC address), if
+    /// OS reports it. Unlike UUID, it's different for two gamepads of the same model.
+    ///
+    /// Currently only available on Linux.
+    pub fn uniq(&self) -> Option<&str> {
+        self.inner.uniq()
+    }
+
     /// Returns the vendor ID, as assigned by the USB-IF, when available.
     pub fn vendor_id(&self) -> Option<u16> {
         self.inner.vendor_id()
@@ -831,39 +1470,28 @@ impl<'a> Gamepad<'a> {
         self.inner.is_connected()
     }
 
+    /// Returns true if calibration session was started for this gamepad.
+    pub fn is_calibrating(&self) -> bool {
+        self.data.calibrator.is_some()
+    }
+
     /// Examines cached gamepad state to check if given button is pressed. Panics if `btn` is
     /// `Unknown`.
-    ///
-    /// If you know `Code` of the element that you want to examine, it's recommended to use methods
-    /// directly on `State`, because this version have to check which `Code` is mapped to element of
-    /// gamepad.
     pub fn is_pressed(&self, btn: Button) -> bool {
         self.data.is_pressed(btn)
     }
 
     /// Examines cached gamepad state to check axis's value. Panics if `axis` is `Unknown`.
-    ///
-    /// If you know `Code` of the element that you want to examine, it's recommended to use methods
-    /// directly on `State`, because this version have to check which `Code` is mapped to element of
-    /// gamepad.
     pub fn value(&self, axis: Axis) -> f32 {
         self.data.value(axis)
     }
 
     /// Returns button state and when it changed.
-    ///
-    /// If you know `Code` of the element that you want to examine, it's recommended to use methods
-    /// directly on `State`, because this version have to check which `Code` is mapped to element of
-    /// gamepad.
     pub fn button_data(&self, btn: Button) -> Option<&ButtonData> {
         self.data.button_data(btn)
     }
 
     /// Returns axis state and when it changed.
-    ///
-    /// If you know `Code` of the element that you want to examine, it's recommended to use methods
-    /// directly on `State`, because this version have to check which `Code` is mapped to element of
-    /// gamepad.
     pub fn axis_data(&self, axis: Axis) -> Option<&AxisData> {
         self.data.axis_data(axis)
     }
@@ -900,6 +1528,15 @@ impl<'a> Gamepad<'a> {
         self.inner.is_ff_supported()
     }
 
+    /// Lights player indicator LED number `player` (counted from 1) and turns off the others, or
+    /// turns off all of them if `player` is `None`. Returns `false` if gamepad doesn't have player
+    /// LEDs or they couldn't be changed.
+    ///
+    /// See also [`PlayerSlots`](players/struct.PlayerSlots.html).
+    pub fn set_player_leds(&self, player: Option<u8>) -> bool {
+        self.inner.set_player_leds(player)
+    }
+
     /// Change gamepad position used by force feedback effects.
     pub fn set_listener_position<Vec3: Into<[f32; 3]>>(
         &self,
@@ -918,6 +1555,64 @@ impl<'a> Gamepad<'a> {
         }
     }
 
+    /// Changes how force feedback effects playing on this gamepad are combined. Settings are kept
+    /// when gamepad reconnects.
+    pub fn set_ff_mixing(&self, mixing: Mixing) -> Result<(), FfError> {
+        if !self.is_connected() {
+            Err(FfError::Disconnected(self.id()))
+        } else if !self.is_ff_supported() {
+            Err(FfError::FfNotSupported(self.id()))
+        } else {
+            self.data.tx.send(Message::SetMixing {
+                id: self.data.id.0,
+                mixing,
+            })?;
+            Ok(())
+        }
+    }
+
+    /// Changes gain of all force feedback effects and rumble on this gamepad. Zero turns force
+    /// feedback off. `gain` will be clamped to \[0.0, f32::MAX\]. Settings are kept when gamepad
+    /// reconnects.
+    pub fn set_ff_gain(&self, gain: f32) -> Result<(), FfError> {
+        if !self.is_connected() {
+            Err(FfError::Disconnected(self.id()))
+        } else if !self.is_ff_supported() {
+            Err(FfError::FfNotSupported(self.id()))
+        } else {
+            self.data.tx.send(Message::SetGamepadGain {
+                id: self.data.id.0,
+                gain: utils::clamp(gain, 0.0, f32::MAX),
+            })?;
+            Ok(())
+        }
+    }
+
+    /// Rumbles strong and weak motor with given magnitudes for `duration`, without creating
+    /// [`Effect`](ff/struct.Effect.html). Rumble starts immediately and is mixed with playing
+    /// effects as the newest effect with priority 0. Calling this function again replaces previous
+    /// rumble; zero `duration` stops it.
+    ///
+    /// With [`MixingPolicy::HighestPriority`](ff/enum.MixingPolicy.html) rumble is silent while
+    /// any effect with priority higher than 0 is playing.
+    ///
+    /// With `FfThreading::Manual` rumble starts on next call to `Gilrs::update_ff()`.
+    pub fn rumble(&self, strong: u16, weak: u16, duration: Duration) -> Result<(), FfError> {
+        if !self.is_connected() {
+            Err(FfError::Disconnected(self.id()))
+        } else if !self.is_ff_supported() {
+            Err(FfError::FfNotSupported(self.id()))
+        } else {
+            self.data.tx.send(Message::Rumble {
+                id: self.data.id.0,
+                strong,
+                weak,
+                duration,
+            })?;
+            Ok(())
+        }
+    }
+
     /// Returns `AxisOrBtn` mapped to `Code`.
     pub fn axis_or_btn_name(&self, ec: Code) -> Option<AxisOrBtn> {
         self.data.axis_or_btn_name(ec)
@@ -956,6 +1651,10 @@ impl<'a> Gamepad<'a> {
     pub(crate) fn mapping(&self) -> &Mapping {
         &self.data.mapping
     }
+
+    pub(crate) fn inner(&self) -> GamepadInner<'a> {
+        self.inner
+    }
 }
 
 #[derive(Debug)]
@@ -966,32 +1665,40 @@ pub(crate) struct GamepadData {
     id: GamepadId,
     // Flags used by the deadzone filter.
     pub(crate) have_sent_nonzero_for_axis: [bool; 6],
+    calibrator: Option<Calibrator>,
+    // Highest value since press or lowest value since release, used by rapid trigger.
+    btn_extremes: HashMap<Code, f32>,
+    // Values of last `ButtonChanged` and `AxisChanged` events, used when events are coalesced.
+    last_values: HashMap<Code, f32>,
 }
 
 impl GamepadData {
-    fn new(
-        id: GamepadId,
-        tx: Sender<Message>,
-        gamepad: &gilrs_core::Gamepad,
-        db: &MappingDb,
-    ) -> Self {
-        let mapping = db
-            .get(Uuid::from_bytes(gamepad.uuid()))
-            .map(
-                |s| match Mapping::parse_sdl_mapping(s, gamepad.buttons(), gamepad.axes()) {
-                    Ok(result) => result,
-                    Err(e) => {
-                        warn!(
+    fn new(id: GamepadId, tx: Sender<Message>, gamepad: GamepadInner<'_>, db: &MappingDb) -> Self {
+        let mapping = if let GamepadInner::Replay(gamepad) = gamepad {
+            let info = gamepad.mapping();
+            Mapping::from_entries(
+                info.name.as_deref(),
+                info.entries.iter().map(|&(code, el)| (code.0, el)),
+                info.hats_mapped,
+            )
+        } else {
+            db.get(Uuid::from_bytes(gamepad.uuid()))
+                .map(
+                    |s| match Mapping::parse_sdl_mapping(s, gamepad.buttons(), gamepad.axes()) {
+                        Ok(result) => result,
+                        Err(e) => {
+                            warn!(
                             "Unable to parse SDL mapping for UUID {}\n\t{:?}\n\tDefault mapping \
                              will be used.",
                             Uuid::from_bytes(gamepad.uuid()),
                             e
                         );
-                        Mapping::default(gamepad)
-                    }
-                },
-            )
-            .unwrap_or_else(|| Mapping::default(gamepad));
+                            Mapping::default(gamepad.buttons(), gamepad.axes())
+                        }
+                    },
+                )
+                .unwrap_or_else(|| Mapping::default(gamepad.buttons(), gamepad.axes()))
+        };
 
         if gamepad.is_ff_supported() && gamepad.is_connected() {
             if let Some(device) = gamepad.ff_device() {
@@ -1005,6 +1712,9 @@ impl GamepadData {
             tx,
             id,
             have_sent_nonzero_for_axis: Default::default(),
+            calibrator: None,
+            btn_extremes: HashMap::new(),
+            last_values: HashMap::new(),
         }
     }
 
@@ -1023,50 +1733,33 @@ impl GamepadData {
 
     /// Examines cached gamepad state to check if given button is pressed. Panics if `btn` is
     /// `Unknown`.
-    ///
-    /// If you know `Code` of the element that you want to examine, it's recommended to use methods
-    /// directly on `State`, because this version have to check which `Code` is mapped to element of
-    /// gamepad.
     pub fn is_pressed(&self, btn: Button) -> bool {
         assert_ne!(btn, Button::Unknown);
 
-        self.button_code(btn)
-            .or_else(|| btn.to_nec())
-            .map(|nec| self.state.is_pressed(nec))
+        self.state
+            .named_button_data(btn)
+            .map(|data| data.is_pressed())
             .unwrap_or(false)
     }
 
     /// Examines cached gamepad state to check axis's value. Panics if `axis` is `Unknown`.
-    ///
-    /// If you know `Code` of the element that you want to examine, it's recommended to use methods
-    /// directly on `State`, because this version have to check which `Code` is mapped to element of
-    /// gamepad.
     pub fn value(&self, axis: Axis) -> f32 {
         assert_ne!(axis, Axis::Unknown);
 
-        self.axis_code(axis)
-            .map(|nec| self.state.value(nec))
+        self.state
+            .named_axis_data(axis)
+            .map(|data| data.value())
             .unwrap_or(0.0)
     }
 
     /// Returns button state and when it changed.
-    ///
-    /// If you know `Code` of the element that you want to examine, it's recommended to use methods
-    /// directly on `State`, because this version have to check which `Code` is mapped to element of
-    /// gamepad.
     pub fn button_data(&self, btn: Button) -> Option<&ButtonData> {
-        self.button_code(btn)
-            .and_then(|nec| self.state.button_data(nec))
+        self.state.named_button_data(btn)
     }
 
     /// Returns axis state and when it changed.
-    ///
-    /// If you know `Code` of the element that you want to examine, it's recommended to use methods
-    /// directly on `State`, because this version have to check which `Code` is mapped to element of
-    /// gamepad.
     pub fn axis_data(&self, axis: Axis) -> Option<&AxisData> {
-        self.axis_code(axis)
-            .and_then(|nec| self.state.axis_data(nec))
+        self.state.named_axis_data(axis)
     }
 
     /// Returns `AxisOrBtn` mapped to `Code`.
@@ -1151,13 +1844,160 @@ fn btn_value(info: &AxisInfo, val: i32) -> f32 {
     utils::clamp(val, 0.0, 1.0)
 }
 
+/// Adds `ev` to `queue`. If `ev` changes value of axis that already has pending change and there
+/// was no other kind of event from the same gamepad since then, pending change is updated instead.
+fn coalesce_raw_event<F>(queue: &mut VecDeque<RawEvent>, ev: RawEvent, is_button: F)
+where
+    F: Fn(usize, EvCode) -> bool,
+{
+    if let RawEventType::AxisValueChanged(val, nec) = ev.event {
+        // Changes of analog buttons and d-pad are never merged, they could press or release
+        // button.
+        if !is_button(ev.id, nec) {
+            for pending in queue.iter_mut().rev().filter(|p| p.id == ev.id) {
+                match pending.event {
+                    RawEventType::AxisValueChanged(ref mut pending_val, pending_nec)
+                        if pending_nec == nec =>
+                    {
+                        *pending_val = val;
+                        pending.time = ev.time;
+                        return;
+                    }
+                    RawEventType::AxisValueChanged(..) => (),
+                    _ => break,
+                }
+            }
+        }
+    }
+
+    queue.push_back(ev);
+}
+
+/// How analog button is turned into `ButtonPressed` and `ButtonReleased` events.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum AxisToBtn {
+    /// Button is pressed when its value is at least `pressed` and released when it is at most
+    /// `released`. `pressed` must be larger than `released`.
+    Threshold { pressed: f32, released: f32 },
+    /// Rapid trigger. Above `actuation`, button is pressed when its value increases by
+    /// `sensitivity` from the lowest value since release and released when it decreases by
+    /// `sensitivity` from the highest value since press. Button is always released at or below
+    /// `actuation`.
+    Rapid { actuation: f32, sensitivity: f32 },
+}
+
+impl AxisToBtn {
+    fn is_valid(&self) -> bool {
+        let in_range = |v: f32| (0.0..=1.0).contains(&v);
+
+        match *self {
+            AxisToBtn::Threshold { pressed, released } => {
+                pressed > released && in_range(pressed) && in_range(released)
+            }
+            AxisToBtn::Rapid {
+                actuation,
+                sensitivity,
+            } => sensitivity > 0.0 && in_range(actuation) && in_range(sensitivity),
+        }
+    }
+
+    /// Returns `Some(true)` if button should be pressed and `Some(false)` if it should be
+    /// released. `extreme` is highest value since press or lowest since release.
+    fn update(&self, val: f32, is_pressed: bool, extreme: &mut f32) -> Option<bool> {
+        let pressed = match *self {
+            AxisToBtn::Threshold { pressed, released } => {
+                if val >= pressed && !is_pressed {
+                    Some(true)
+                } else if val <= released && is_pressed {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+            AxisToBtn::Rapid {
+                actuation,
+                sensitivity,
+            } => {
+                if is_pressed {
+                    *extreme = extreme.max(val);
+                    if val <= actuation || *extreme - val >= sensitivity {
+                        Some(false)
+                    } else {
+                        None
+                    }
+                } else {
+                    *extreme = extreme.min(val);
+                    if val > actuation && val - *extreme >= sensitivity {
+                        Some(true)
+                    } else {
+                        None
+                    }
+                }
+            }
+        };
+
+        if pressed.is_some() {
+            *extreme = val;
+        }
+
+        pressed
+    }
+}
+
+/// Gamepads that `AxisToBtn` settings apply to.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum AxisToBtnScope {
+    All,
+    Gamepad(GamepadId),
+    /// All gamepads with given UUID.
+    Uuid([u8; 16]),
+}
+
+#[derive(Clone, Debug)]
+struct AxisToBtnSettings(HashMap<(AxisToBtnScope, Option<Button>), AxisToBtn>);
+
+impl AxisToBtnSettings {
+    fn set(&mut self, scope: AxisToBtnScope, button: Option<Button>, mode: AxisToBtn) {
+        self.0.insert((scope, button), mode);
+    }
+
+    fn get(&self, id: GamepadId, uuid: [u8; 16], button: Button) -> AxisToBtn {
+        [
+            AxisToBtnScope::Gamepad(id),
+            AxisToBtnScope::Uuid(uuid),
+            AxisToBtnScope::All,
+        ]
+        .iter()
+        .flat_map(|&scope| [(scope, Some(button)), (scope, None)])
+        .find_map(|key| self.0.get(&key))
+        .copied()
+        .unwrap_or(DEFAULT_AXIS_TO_BTN)
+    }
+
+    fn is_valid(&self) -> bool {
+        self.0.values().all(AxisToBtn::is_valid)
+    }
+}
+
+impl Default for AxisToBtnSettings {
+    fn default() -> Self {
+        let mut settings = AxisToBtnSettings(HashMap::new());
+        settings.set(AxisToBtnScope::All, None, DEFAULT_AXIS_TO_BTN);
+
+        settings
+    }
+}
+
 /// Error type which can be returned when creating `Gilrs`.
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum Error {
     /// Gilrs does not support current platform, but you can use dummy context from this error if
     /// gamepad input is not essential.
     NotImplemented(Gilrs),
-    /// Either `pressed ≤ released` or one of values is outside [0.0, 1.0] range.
+    /// Invalid `AxisToBtn` settings: either `pressed ≤ released`, rapid trigger sensitivity is
+    /// not positive or one of values is outside [0.0, 1.0] range.
     InvalidAxisToBtn,
     /// Platform specific error.
     Other(Box<dyn error::Error + Send + Sync + 'static>),
@@ -1168,7 +2008,8 @@ impl Display for Error {
         match self {
             Error::NotImplemented(_) => f.write_str("Gilrs does not support current platform."),
             Error::InvalidAxisToBtn => f.write_str(
-                "Either `pressed ≤ released` or one of values is outside [0.0, 1.0] range.",
+                "Either `pressed ≤ released`, rapid trigger sensitivity is not positive or one of \
+                 values is outside [0.0, 1.0] range.",
             ),
             Error::Other(ref e) => e.fmt(f),
         }
@@ -1193,7 +2034,30 @@ const _: () = {
 
 #[cfg(test)]
 mod tests {
-    use super::{axis_value, Axis, AxisInfo};
+    use super::{
+        axis_value, Axis, AxisInfo, AxisToBtn, AxisToBtnScope, Button, Code, Event, EventType,
+        GamepadId, Gilrs, Message,
+    };
+    use crate::calibration::{AxisCalibration, Calibration, CalibrationStep};
+    use crate::ev::filter::{smallvec, Compat, FilterResult};
+    use crate::record::{GamepadInfo, MappingInfo, Record, ReplayMode};
+    use crate::GilrsBuilder;
+    use gilrs_core::native_ev_codes as nec;
+    use gilrs_core::PowerInfo;
+
+    #[test]
+    fn drop_stops_ff_thread() {
+        let gilrs = match GilrsBuilder::new().build() {
+            Ok(gilrs) => gilrs,
+            Err(crate::Error::NotImplemented(gilrs)) => gilrs,
+            Err(e) => panic!("{:?}", e),
+        };
+        let tx = gilrs.ff_sender().clone();
+        assert!(tx.send(Message::Play { id: 0 }).is_ok());
+
+        drop(gilrs);
+        assert!(tx.send(Message::Play { id: 0 }).is_err());
+    }
 
     #[test]
     fn axis_value_documented_case() {
@@ -1208,8 +2072,8 @@ mod tests {
     #[test]
     fn axis_value_overflow() {
         let info = AxisInfo {
-            min: std::i32::MIN,
-            max: std::i32::MAX,
+            min: i32::MIN,
+            max: i32::MAX,
             deadzone: None,
         };
         let axis = Axis::LeftStickY;
@@ -1218,4 +2082,317 @@ mod tests {
         assert_eq!(0., axis_value(&info, 0, axis));
         assert_eq!(0., axis_value(&info, 1, axis));
     }
+
+    #[test]
+    fn filters_run_in_order() {
+        fn drop_pressed(ev: Event, _: &mut Gilrs) -> FilterResult {
+            match ev.event {
+                EventType::ButtonPressed(..) => FilterResult::Drop,
+                _ => FilterResult::Pass(ev),
+            }
+        }
+
+        fn released_to_north(ev: Option<Event>, _: &mut Gilrs) -> Option<Event> {
+            match ev {
+                Some(Event {
+                    event: EventType::ButtonReleased(_, code),
+                    id,
+                    time,
+                }) => Some(Event {
+                    event: EventType::ButtonReleased(Button::North, code),
+                    id,
+                    time,
+                }),
+                _ => ev,
+            }
+        }
+
+        fn duplicate(ev: Event, _: &mut Gilrs) -> FilterResult {
+            FilterResult::Emit(smallvec![ev, ev])
+        }
+
+        let mut gilrs = match GilrsBuilder::new()
+            .with_default_filters(false)
+            .add_filter(drop_pressed)
+            .add_filter(Compat(released_to_north))
+            .add_filter(duplicate)
+            .build()
+        {
+            Ok(gilrs) => gilrs,
+            Err(crate::Error::NotImplemented(gilrs)) => gilrs,
+            Err(e) => panic!("{}", e),
+        };
+
+        let id = GamepadId(0);
+        let code = Code(nec::BTN_SOUTH);
+        gilrs.insert_event(Event::new(
+            id,
+            EventType::ButtonPressed(Button::South, code),
+        ));
+        gilrs.insert_event(Event::new(
+            id,
+            EventType::ButtonReleased(Button::South, code),
+        ));
+
+        for _ in 0..2 {
+            assert_eq!(
+                gilrs.next_event().map(|ev| ev.event),
+                Some(EventType::ButtonReleased(Button::North, code))
+            );
+        }
+        assert_eq!(gilrs.next_event(), None);
+    }
+
+    #[test]
+    fn rapid_trigger() {
+        let mode = AxisToBtn::Rapid {
+            actuation: 0.2,
+            sensitivity: 0.1,
+        };
+        let mut extreme = 0.0;
+        let mut pressed = false;
+        let mut update = |val| {
+            let changed = mode.update(val, pressed, &mut extreme);
+            if let Some(p) = changed {
+                pressed = p;
+            }
+            changed
+        };
+
+        // Below actuation point.
+        assert_eq!(update(0.15), None);
+        assert_eq!(update(0.25), Some(true));
+        assert_eq!(update(0.6), None);
+        assert_eq!(update(0.52), None);
+        assert_eq!(update(0.45), Some(false));
+        assert_eq!(update(0.4), None);
+        assert_eq!(update(0.55), Some(true));
+        assert_eq!(update(0.1), Some(false));
+    }
+
+    #[test]
+    fn axis_to_btn_overrides() {
+        let threshold = |pressed, released| AxisToBtn::Threshold { pressed, released };
+        let lt2 = Some(Button::LeftTrigger2);
+        let (pad0, pad1) = (GamepadId(0), GamepadId(1));
+
+        let mut gilrs = GilrsBuilder::new()
+            .replay(
+                vec![info(0, None), info(1, None)],
+                ReplayMode::AsFastAsPossible,
+            )
+            .set_axis_to_btn(0.8, 0.7)
+            .axis_to_btn(AxisToBtnScope::Uuid([1; 16]), lt2, threshold(0.6, 0.5))
+            .axis_to_btn(AxisToBtnScope::Gamepad(pad0), lt2, threshold(0.3, 0.2))
+            .build()
+            .unwrap();
+
+        let mode = |gilrs: &Gilrs, id, btn| gilrs.axis_to_btn_mode(id, btn);
+        assert_eq!(
+            mode(&gilrs, pad0, Button::LeftTrigger2),
+            threshold(0.3, 0.2)
+        );
+        assert_eq!(
+            mode(&gilrs, pad1, Button::LeftTrigger2),
+            threshold(0.6, 0.5)
+        );
+        assert_eq!(
+            mode(&gilrs, pad0, Button::RightTrigger2),
+            threshold(0.8, 0.7)
+        );
+
+        // Gamepad scope is more specific than UUID scope, even for all buttons.
+        gilrs
+            .set_axis_to_btn_mode(AxisToBtnScope::Gamepad(pad1), None, threshold(0.4, 0.1))
+            .unwrap();
+        assert_eq!(
+            mode(&gilrs, pad1, Button::LeftTrigger2),
+            threshold(0.4, 0.1)
+        );
+        assert_eq!(
+            mode(&gilrs, pad1, Button::RightTrigger2),
+            threshold(0.4, 0.1)
+        );
+
+        gilrs
+            .set_axis_to_btn_mode(AxisToBtnScope::Gamepad(pad1), lt2, threshold(0.9, 0.8))
+            .unwrap();
+        assert_eq!(
+            mode(&gilrs, pad1, Button::LeftTrigger2),
+            threshold(0.9, 0.8)
+        );
+        assert!(gilrs
+            .set_axis_to_btn_mode(AxisToBtnScope::Gamepad(pad1), lt2, threshold(0.1, 0.8))
+            .is_err());
+        assert_eq!(
+            mode(&gilrs, pad1, Button::LeftTrigger2),
+            threshold(0.9, 0.8)
+        );
+
+        gilrs.remove_axis_to_btn_mode(AxisToBtnScope::Gamepad(pad0), lt2);
+        assert_eq!(
+            mode(&gilrs, pad0, Button::LeftTrigger2),
+            threshold(0.6, 0.5)
+        );
+
+        // Global setting can be changed, but not removed.
+        gilrs
+            .set_axis_to_btn_mode(AxisToBtnScope::All, None, threshold(0.95, 0.05))
+            .unwrap();
+        gilrs.remove_axis_to_btn_mode(AxisToBtnScope::All, None);
+        assert_eq!(
+            mode(&gilrs, pad0, Button::RightTrigger2),
+            threshold(0.95, 0.05)
+        );
+
+        // Same value presses LT2 of one gamepad, but not the other.
+        let mut extreme = 0.0;
+        assert_eq!(
+            mode(&gilrs, pad0, Button::LeftTrigger2).update(0.7, false, &mut extreme),
+            Some(true)
+        );
+        assert_eq!(
+            mode(&gilrs, pad1, Button::LeftTrigger2).update(0.7, false, &mut extreme),
+            None
+        );
+    }
+
+    #[test]
+    fn coalescing_skips_only_backend_events() {
+        let mut gilrs = GilrsBuilder::new()
+            .with_default_filters(false)
+            .replay(vec![info(0, None)], ReplayMode::AsFastAsPossible)
+            .build()
+            .unwrap();
+        let x = Code(nec::AXIS_LSTICKX);
+        gilrs.gamepads_data[0].last_values.insert(x, 0.5);
+
+        gilrs.set_coalesce_events(true);
+        assert!(gilrs.gamepads_data[0].last_values.is_empty());
+
+        let ev = Event::new(
+            GamepadId(0),
+            EventType::AxisChanged(Axis::LeftStickX, 0.5, x),
+        );
+        gilrs.insert_event(ev);
+        gilrs.insert_event(ev);
+        assert_eq!(gilrs.next_event().map(|ev| ev.event), Some(ev.event));
+        assert_eq!(gilrs.next_event().map(|ev| ev.event), Some(ev.event));
+        assert!(gilrs.gamepads_data[0].last_values.is_empty());
+    }
+
+    #[test]
+    fn coalesce_raw_events() {
+        use super::coalesce_raw_event;
+        use gilrs_core::{native_ev_codes as nec, Event as RawEvent, EventType as RawEventType};
+        use std::collections::VecDeque;
+
+        let raw = |id, event| RawEvent::new(id, event);
+        let x = nec::AXIS_LSTICKX;
+        let y = nec::AXIS_LSTICKY;
+        let trigger = nec::AXIS_RT2;
+        let hat = nec::AXIS_DPADX;
+        let is_button = |_, nec| nec == trigger || nec == hat;
+
+        let mut queue = VecDeque::new();
+        for ev in [
+            raw(0, RawEventType::AxisValueChanged(1, x)),
+            raw(0, RawEventType::AxisValueChanged(1, y)),
+            raw(1, RawEventType::AxisValueChanged(1, x)),
+            raw(0, RawEventType::AxisValueChanged(2, x)),
+            raw(0, RawEventType::AxisValueChanged(10, trigger)),
+            raw(0, RawEventType::AxisValueChanged(20, trigger)),
+            raw(0, RawEventType::ButtonPressed(nec::BTN_SOUTH)),
+            raw(0, RawEventType::AxisValueChanged(3, x)),
+            raw(0, RawEventType::AxisValueChanged(4, x)),
+            // Hat tap within one poll.
+            raw(0, RawEventType::AxisValueChanged(1, hat)),
+            raw(0, RawEventType::AxisValueChanged(0, hat)),
+        ] {
+            coalesce_raw_event(&mut queue, ev, is_button);
+        }
+
+        let events: Vec<_> = queue.into_iter().map(|ev| (ev.id, ev.event)).collect();
+        assert_eq!(
+            events,
+            [
+                (0, RawEventType::AxisValueChanged(2, x)),
+                (0, RawEventType::AxisValueChanged(1, y)),
+                (1, RawEventType::AxisValueChanged(1, x)),
+                (0, RawEventType::AxisValueChanged(10, trigger)),
+                (0, RawEventType::AxisValueChanged(20, trigger)),
+                (0, RawEventType::ButtonPressed(nec::BTN_SOUTH)),
+                (0, RawEventType::AxisValueChanged(4, x)),
+                (0, RawEventType::AxisValueChanged(1, hat)),
+                (0, RawEventType::AxisValueChanged(0, hat)),
+            ]
+        );
+    }
+
+    fn info(id: usize, uniq: Option<&str>) -> Record {
+        Record::Gamepad(GamepadInfo {
+            id: GamepadId(id),
+            name: "Replayed".to_owned(),
+            uuid: [1; 16],
+            uniq: uniq.map(ToOwned::to_owned),
+            vendor_id: None,
+            product_id: None,
+            is_connected: true,
+            power_info: PowerInfo::Wired,
+            buttons: vec![],
+            axes: vec![],
+            mapping: MappingInfo {
+                name: None,
+                entries: vec![],
+                hats_mapped: 0,
+            },
+        })
+    }
+
+    #[test]
+    fn device_calibration_before_uuid() {
+        let calibration = |min| {
+            let mut c = Calibration::new();
+            c.set_axis(AxisCalibration {
+                code: Code(nec::AXIS_LSTICKX),
+                min,
+                max: 100,
+                center: None,
+            });
+            c
+        };
+
+        let mut gilrs = GilrsBuilder::new()
+            .replay(
+                vec![
+                    info(0, Some("pad-0")),
+                    info(1, Some("pad-1")),
+                    info(2, None),
+                ],
+                ReplayMode::AsFastAsPossible,
+            )
+            .add_calibration([1; 16], calibration(-100))
+            .add_device_calibration("pad-0", calibration(-50))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            gilrs.gamepad_calibration(GamepadId(0)),
+            Some(&calibration(-50))
+        );
+        assert_eq!(
+            gilrs.gamepad_calibration(GamepadId(1)),
+            Some(&calibration(-100))
+        );
+        assert_eq!(
+            gilrs.gamepad_calibration(GamepadId(2)),
+            Some(&calibration(-100))
+        );
+
+        // Calibration session of device with uniq doesn't change other gamepads of the same model.
+        gilrs.start_calibration(GamepadId(1), CalibrationStep::Range);
+        let finished = gilrs.finish_calibration(GamepadId(1)).unwrap();
+        assert_eq!(gilrs.device_calibration("pad-1"), Some(&finished));
+        assert_eq!(gilrs.calibration([1; 16]), Some(&calibration(-100)));
+    }
 }
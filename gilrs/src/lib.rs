@@ -99,6 +99,13 @@
 //! To build GilRs, you will need pkg-config and libudev .pc file. On some distributions this file
 //! is packaged in separate archive (e.g., `libudev-dev` in Debian, `libudev-devd` in FreeBSD).
 //!
+//! To debug problems with specific device, set `GILRS_EVDEV_CAPTURE` environment variable to a
+//! directory. Every opened gamepad will be captured there, in format similar to `evemu-record`,
+//! with its capabilities, axes ranges and all raw events. Captured device can be later recreated
+//! without hardware by setting `GILRS_EVDEV_REPLAY` to list of capture files separated by `:`. In
+//! this case, real gamepads are not used and all captured events are returned as fast as
+//! possible.
+//!
 //! Wasm
 //! ----
 //!
@@ -116,13 +123,17 @@ mod gamepad;
 mod mapping;
 mod utils;
 
+pub mod calibration;
 pub mod ev;
 pub mod ff;
+pub mod motion;
+pub mod players;
+pub mod record;
 
 pub use crate::ev::filter::Filter;
 pub use crate::ev::{Axis, Button, Event, EventType};
 pub use crate::gamepad::{
-    ConnectedGamepadsIterator, Error, Gamepad, GamepadId, Gilrs, GilrsBuilder, MappingSource,
-    PowerInfo,
+    AxisToBtn, AxisToBtnScope, ConnectedGamepadsIterator, Error, Gamepad, GamepadId, Gilrs,
+    GilrsBuilder, MappingSource, PowerInfo,
 };
 pub use crate::mapping::{MappingData as Mapping, MappingError};
@@ -60,7 +60,7 @@ impl Mapping {
         }
     }
 
-    pub fn default(gamepad: &gilrs_core::Gamepad) -> Self {
+    pub fn default(buttons: &[EvCode], axes: &[EvCode]) -> Self {
         use self::Axis as Ax;
         use self::AxisOrBtn::*;
 
@@ -114,7 +114,7 @@ impl Mapping {
         ];
 
         // Remove all mappings that don't have corresponding element in gamepad. Partial fix to #83
-        let axes = [
+        let default_axes = [
             nec::AXIS_DPADX,
             nec::AXIS_DPADY,
             nec::AXIS_LEFTZ,
@@ -128,7 +128,7 @@ impl Mapping {
             nec::AXIS_RT2,
             nec::AXIS_RIGHTZ,
         ];
-        let btns = [
+        let default_btns = [
             nec::BTN_SOUTH,
             nec::BTN_NORTH,
             nec::BTN_WEST,
@@ -150,14 +150,14 @@ impl Mapping {
             nec::BTN_DPAD_UP,
         ];
 
-        for axis in &axes {
-            if !gamepad.axes().contains(axis) {
+        for axis in &default_axes {
+            if !axes.contains(axis) {
                 mappings.remove(axis);
             }
         }
 
-        for btn in &btns {
-            if !gamepad.buttons().contains(btn) {
+        for btn in &default_btns {
+            if !buttons.contains(btn) {
                 mappings.remove(btn);
             }
         }
@@ -170,6 +170,20 @@ impl Mapping {
         }
     }
 
+    /// Recreates mapping from its entries, for example from recording. If `name` is `None`,
+    /// mapping is treated as default one.
+    pub fn from_entries<I>(name: Option<&str>, entries: I, hats_mapped: u8) -> Self
+    where
+        I: IntoIterator<Item = (EvCode, AxisOrBtn)>,
+    {
+        Mapping {
+            mappings: entries.into_iter().collect(),
+            name: name.unwrap_or_default().to_owned(),
+            default: name.is_none(),
+            hats_mapped,
+        }
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -417,6 +431,10 @@ impl Mapping {
         self.mappings.iter().find(|x| x.1 == el).map(|x| *x.0)
     }
 
+    pub fn entries(&self) -> impl Iterator<Item = (EvCode, AxisOrBtn)> + '_ {
+        self.mappings.iter().map(|(&code, &el)| (code, el))
+    }
+
     pub fn is_default(&self) -> bool {
         self.default
     }
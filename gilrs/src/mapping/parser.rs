@@ -283,6 +283,8 @@ impl<'a> Parser<'a> {
     }
 }
 
+// Not every parsed field is used yet, but they are part of the SDL mapping format.
+#[allow(dead_code)]
 #[derive(Debug)]
 pub enum Token<'a> {
     Uuid(Uuid),
@@ -0,0 +1,521 @@
+// Copyright 2016-2018 Mateusz Sieczko and other GilRs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Recognizing motion inputs like quarter-circle-forward or charge moves.
+//!
+//! [`MotionRecognizer`] reads cached state of a gamepad once per frame, converts d-pad and left
+//! stick to one of 8 directions (or neutral) and buffers changes of direction. Frames are counted
+//! with [`Gilrs::counter()`](../struct.Gilrs.html#method.counter), so `update()` should be called
+//! after all events for given frame were processed and before `Gilrs::inc()`.
+//!
+//! Directions in motions are relative to the side character is facing, so `Forward` means right
+//! when facing right and left when facing left.
+//!
+//! # Example
+//!
+//! ```
+//! use gilrs::{Button, Gilrs};
+//! use gilrs::motion::{Facing, Motion, MotionRecognizer};
+//!
+//! let mut gilrs = Gilrs::new().unwrap();
+//! let mut recognizer = None;
+//!
+//! loop {
+//!     while let Some(ev) = gilrs.next_event() {
+//!         if recognizer.is_none() {
+//!             let mut r = MotionRecognizer::new(ev.id);
+//!             r.add_motion(Motion::dragon_punch(Button::West));
+//!             r.add_motion(Motion::quarter_circle_forward(Button::West));
+//!             recognizer = Some(r);
+//!         }
+//!     }
+//!
+//!     if let Some(ref mut recognizer) = recognizer {
+//!         recognizer.set_facing(Facing::Left);
+//!         // First registered motion is the most important one.
+//!         match recognizer.update(&gilrs).first().map(|id| id.index()) {
+//!             Some(0) => println!("Shoryuken!"),
+//!             Some(1) => println!("Hadouken!"),
+//!             _ => (),
+//!         }
+//!     }
+//!
+//!     gilrs.inc();
+//!     # break;
+//! }
+//! ```
+
+use crate::ev::{Axis, Button};
+use crate::gamepad::{GamepadId, Gilrs};
+use crate::utils;
+
+#[cfg(feature = "serde-serialize")]
+use serde::{Deserialize, Serialize};
+
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime};
+
+const HISTORY_LEN: usize = 64;
+
+/// Side that character is facing.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum Facing {
+    #[default]
+    Right,
+    Left,
+}
+
+/// One of 8 directions or neutral, relative to facing.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum Direction {
+    UpBack,
+    Up,
+    UpForward,
+    Back,
+    Neutral,
+    Forward,
+    DownBack,
+    Down,
+    DownForward,
+}
+
+impl Direction {
+    fn to_xy(self, facing: Facing) -> (i8, i8) {
+        use Direction::*;
+
+        let (x, y) = match self {
+            UpBack => (-1, 1),
+            Up => (0, 1),
+            UpForward => (1, 1),
+            Back => (-1, 0),
+            Neutral => (0, 0),
+            Forward => (1, 0),
+            DownBack => (-1, -1),
+            Down => (0, -1),
+            DownForward => (1, -1),
+        };
+
+        match facing {
+            Facing::Right => (x, y),
+            Facing::Left => (-x, y),
+        }
+    }
+
+    fn from_xy(x: i8, y: i8, facing: Facing) -> Self {
+        use Direction::*;
+
+        let x = match facing {
+            Facing::Right => x,
+            Facing::Left => -x,
+        };
+
+        match (x, y) {
+            (-1, 1) => UpBack,
+            (0, 1) => Up,
+            (1, 1) => UpForward,
+            (-1, 0) => Back,
+            (1, 0) => Forward,
+            (-1, -1) => DownBack,
+            (0, -1) => Down,
+            (1, -1) => DownForward,
+            _ => Neutral,
+        }
+    }
+}
+
+/// Length of time, in frames or as duration.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum Window {
+    Frames(u64),
+    Time(Duration),
+}
+
+/// Single step of motion.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum Step {
+    /// Direction has to be entered.
+    Dir(Direction),
+    /// Direction has to be held for at least given time. Holding diagonal also counts as holding
+    /// both of its cardinal directions, so `Back` can be charged by holding `DownBack`.
+    Charge(Direction, Window),
+}
+
+/// Sequence of directions, optionally finished with a button press.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct Motion {
+    pub steps: Vec<Step>,
+    /// Button that has to be pressed after last step. If `None`, motion is performed as soon as
+    /// last direction is entered.
+    pub button: Option<Button>,
+    /// All steps have to be entered at most this long before motion is finished.
+    pub window: Window,
+}
+
+impl Motion {
+    /// Creates new motion.
+    pub fn new(steps: Vec<Step>, button: Option<Button>, window: Window) -> Self {
+        Motion {
+            steps,
+            button,
+            window,
+        }
+    }
+
+    /// Down, down-forward, forward and `button` in 15 frames.
+    pub fn quarter_circle_forward(button: Button) -> Self {
+        use Direction::*;
+
+        Motion::new(
+            vec![Step::Dir(Down), Step::Dir(DownForward), Step::Dir(Forward)],
+            Some(button),
+            Window::Frames(15),
+        )
+    }
+
+    /// Down, down-back, back and `button` in 15 frames.
+    pub fn quarter_circle_back(button: Button) -> Self {
+        use Direction::*;
+
+        Motion::new(
+            vec![Step::Dir(Down), Step::Dir(DownBack), Step::Dir(Back)],
+            Some(button),
+            Window::Frames(15),
+        )
+    }
+
+    /// Forward, down, down-forward and `button` in 15 frames.
+    pub fn dragon_punch(button: Button) -> Self {
+        use Direction::*;
+
+        Motion::new(
+            vec![Step::Dir(Forward), Step::Dir(Down), Step::Dir(DownForward)],
+            Some(button),
+            Window::Frames(15),
+        )
+    }
+
+    /// `charge` held for 40 frames, then `release` and `button` in 10 frames.
+    pub fn charge(charge: Direction, release: Direction, button: Button) -> Self {
+        Motion::new(
+            vec![Step::Charge(charge, Window::Frames(40)), Step::Dir(release)],
+            Some(button),
+            Window::Frames(10),
+        )
+    }
+}
+
+/// Identifies motion added to `MotionRecognizer`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct MotionId(usize);
+
+impl MotionId {
+    /// Returns index of motion, in order in which motions were added.
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Entry {
+    x: i8,
+    y: i8,
+    frame: u64,
+    time: SystemTime,
+}
+
+/// Buffers directions and button presses of a gamepad and recognizes motions.
+///
+/// See module level documentation for more info.
+#[derive(Clone, Debug)]
+pub struct MotionRecognizer {
+    id: GamepadId,
+    /// Stick axis has to move this far from center to become part of direction.
+    pub stick_press: f32,
+    /// Stick axis stays part of direction until it moves closer to center than this.
+    pub stick_release: f32,
+    facing: Facing,
+    motions: Vec<Motion>,
+    history: VecDeque<Entry>,
+    stick: (i8, i8),
+    performed: Vec<MotionId>,
+}
+
+impl MotionRecognizer {
+    /// Creates recognizer for gamepad with `id`, with `stick_press` set to 0.5 and
+    /// `stick_release` set to 0.35.
+    pub fn new(id: GamepadId) -> Self {
+        MotionRecognizer {
+            id,
+            stick_press: 0.5,
+            stick_release: 0.35,
+            facing: Facing::Right,
+            motions: Vec::new(),
+            history: VecDeque::with_capacity(HISTORY_LEN),
+            stick: (0, 0),
+            performed: Vec::new(),
+        }
+    }
+
+    /// Adds motion to recognize.
+    pub fn add_motion(&mut self, motion: Motion) -> MotionId {
+        self.motions.push(motion);
+
+        MotionId(self.motions.len() - 1)
+    }
+
+    /// Returns motion with `id`.
+    pub fn motion(&self, id: MotionId) -> &Motion {
+        &self.motions[id.0]
+    }
+
+    /// Sets side that character is facing. Already buffered directions are interpreted using new
+    /// facing.
+    pub fn set_facing(&mut self, facing: Facing) {
+        self.facing = facing;
+    }
+
+    /// Returns side that character is facing.
+    pub fn facing(&self) -> Facing {
+        self.facing
+    }
+
+    /// Returns current direction.
+    pub fn direction(&self) -> Direction {
+        self.history
+            .back()
+            .map(|e| Direction::from_xy(e.x, e.y, self.facing))
+            .unwrap_or(Direction::Neutral)
+    }
+
+    /// Returns true if motion was performed in last `update()`.
+    pub fn is_performed(&self, id: MotionId) -> bool {
+        self.performed.contains(&id)
+    }
+
+    /// Forgets all buffered input.
+    pub fn clear(&mut self) {
+        self.history.clear();
+        self.performed.clear();
+    }
+
+    /// Reads state of gamepad and returns motions performed in current frame, in order in which
+    /// they were added. Should be called once per frame.
+    ///
+    /// Button is considered pressed in current frame if it is pressed and its counter is equal to
+    /// `gilrs.counter()`.
+    pub fn update(&mut self, gilrs: &Gilrs) -> &[MotionId] {
+        let frame = gilrs.counter();
+        let time = utils::time_now();
+
+        let gamepad = match gilrs.connected_gamepad(self.id) {
+            Some(gamepad) => gamepad,
+            None => {
+                self.performed.clear();
+                return &self.performed;
+            }
+        };
+
+        let dpad = |neg, pos| gamepad.is_pressed(pos) as i8 - gamepad.is_pressed(neg) as i8;
+        let dpad_x = dpad(Button::DPadLeft, Button::DPadRight);
+        let dpad_y = dpad(Button::DPadDown, Button::DPadUp);
+        let stick_x = self.stick_component(self.stick.0, gamepad.value(Axis::LeftStickX));
+        let stick_y = self.stick_component(self.stick.1, gamepad.value(Axis::LeftStickY));
+        self.stick = (stick_x, stick_y);
+
+        let x = if dpad_x != 0 { dpad_x } else { stick_x };
+        let y = if dpad_y != 0 { dpad_y } else { stick_y };
+
+        let pressed = |btn: Button| {
+            gamepad
+                .button_data(btn)
+                .map_or(false, |d| d.is_pressed() && d.counter() == frame)
+        };
+
+        self.step(x, y, pressed, frame, time)
+    }
+
+    fn stick_component(&self, current: i8, val: f32) -> i8 {
+        if val >= self.stick_press || (current == 1 && val > self.stick_release) {
+            1
+        } else if val <= -self.stick_press || (current == -1 && val < -self.stick_release) {
+            -1
+        } else {
+            0
+        }
+    }
+
+    fn step<F>(&mut self, x: i8, y: i8, pressed: F, frame: u64, time: SystemTime) -> &[MotionId]
+    where
+        F: Fn(Button) -> bool,
+    {
+        let changed = self.history.back().map_or(true, |e| (e.x, e.y) != (x, y));
+        if changed {
+            if self.history.len() == HISTORY_LEN {
+                self.history.pop_front();
+            }
+            self.history.push_back(Entry { x, y, frame, time });
+        }
+
+        self.performed.clear();
+        for (i, motion) in self.motions.iter().enumerate() {
+            let triggered = match motion.button {
+                Some(btn) => pressed(btn),
+                None => changed,
+            };
+
+            if triggered && self.matches(motion, frame, time) {
+                self.performed.push(MotionId(i));
+            }
+        }
+
+        &self.performed
+    }
+
+    fn matches(&self, motion: &Motion, frame: u64, time: SystemTime) -> bool {
+        let last = self.history.len();
+        // End of entry is start of next one.
+        let end = |idx: usize| {
+            self.history
+                .get(idx + 1)
+                .map_or((frame, time), |e| (e.frame, e.time))
+        };
+        let is_dir = |idx: usize, dir: Direction| {
+            let e = &self.history[idx];
+            (e.x, e.y) == dir.to_xy(self.facing)
+        };
+        let is_charged = |idx: usize, dir: Direction| {
+            let e = &self.history[idx];
+            let (x, y) = dir.to_xy(self.facing);
+            (x == 0 || x == e.x) && (y == 0 || y == e.y)
+        };
+
+        // Last entry that still can be used.
+        let mut idx = last;
+        for step in motion.steps.iter().rev() {
+            loop {
+                if idx == 0 {
+                    return false;
+                }
+                idx -= 1;
+
+                let (end_frame, end_time) = end(idx);
+                if !within(motion.window, (end_frame, end_time), (frame, time)) {
+                    return false;
+                }
+
+                match *step {
+                    Step::Dir(dir) if is_dir(idx, dir) => break,
+                    Step::Charge(dir, min) if is_charged(idx, dir) => {
+                        let mut start = idx;
+                        while start > 0 && is_charged(start - 1, dir) {
+                            start -= 1;
+                        }
+                        idx = start;
+
+                        let e = &self.history[start];
+                        if !within(min, (e.frame, e.time), (end_frame, end_time)) {
+                            break;
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        true
+    }
+}
+
+/// Returns true if `from` is at most `window` before `to`. For charge, returns false when charge
+/// is long enough.
+fn within(window: Window, from: (u64, SystemTime), to: (u64, SystemTime)) -> bool {
+    match window {
+        Window::Frames(n) => to.0.saturating_sub(from.0) <= n,
+        Window::Time(dur) => to.1.duration_since(from.1).map_or(true, |d| d <= dur),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Direction, Facing, Motion, MotionRecognizer};
+    use crate::{Button, GamepadId};
+    use std::time::SystemTime;
+
+    fn feed(r: &mut MotionRecognizer, inputs: &[(i8, i8, bool)]) -> Vec<usize> {
+        let mut performed = Vec::new();
+        for (frame, &(x, y, punch)) in inputs.iter().enumerate() {
+            let ids = r.step(
+                x,
+                y,
+                |b| punch && b == Button::West,
+                frame as u64,
+                SystemTime::now(),
+            );
+            performed.extend(ids.iter().map(|id| id.index()));
+        }
+
+        performed
+    }
+
+    #[test]
+    fn quarter_circle() {
+        let mut r = MotionRecognizer::new(GamepadId(0));
+        r.add_motion(Motion::dragon_punch(Button::West));
+        r.add_motion(Motion::quarter_circle_forward(Button::West));
+
+        assert_eq!(
+            feed(&mut r, &[(0, -1, false), (1, -1, false), (1, 0, true)]),
+            [1]
+        );
+        r.clear();
+        assert_eq!(
+            feed(&mut r, &[(1, 0, false), (0, -1, false), (1, -1, true)]),
+            [0]
+        );
+
+        // Too slow.
+        r.clear();
+        let mut inputs = vec![(0, -1, false), (1, -1, false)];
+        inputs.extend([(1, 0, false); 20]);
+        inputs.push((1, 0, true));
+        assert!(feed(&mut r, &inputs).is_empty());
+
+        // Facing left.
+        r.clear();
+        r.set_facing(Facing::Left);
+        assert_eq!(
+            feed(&mut r, &[(0, -1, false), (-1, -1, false), (-1, 0, true)]),
+            [1]
+        );
+        assert_eq!(r.direction(), Direction::Forward);
+    }
+
+    #[test]
+    fn charge() {
+        let mut r = MotionRecognizer::new(GamepadId(0));
+        r.add_motion(Motion::charge(
+            Direction::Back,
+            Direction::Forward,
+            Button::West,
+        ));
+
+        let mut inputs = vec![(-1, 0, false); 20];
+        inputs.extend([(-1, -1, false); 25]);
+        inputs.extend([(1, 0, false), (1, 0, true)]);
+        assert_eq!(feed(&mut r, &inputs), [0]);
+
+        r.clear();
+        let mut inputs = vec![(-1, 0, false); 20];
+        inputs.extend([(1, 0, false), (1, 0, true)]);
+        assert!(feed(&mut r, &inputs).is_empty());
+    }
+}
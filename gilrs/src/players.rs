@@ -0,0 +1,488 @@
+// Copyright 2016-2018 Mateusz Sieczko and other GilRs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Assigning gamepads to player slots.
+//!
+//! [`PlayerSlots`] keeps fixed number of "player 1…N" slots and assigns gamepads to them based on
+//! `Connected`, `Disconnected` and (optionally) button events. Slot of disconnected gamepad is
+//! reserved for it, so when gamepad reconnects – with the same `GamepadId` or, with
+//! [`AssignmentPolicy::Sticky`], as the same device – it gets back the same slot.
+//!
+//! # Example
+//!
+//! ```
+//! use gilrs::{Button, Gilrs};
+//! use gilrs::players::{AssignmentPolicy, PlayerSlots, SlotEvent};
+//!
+//! let mut gilrs = Gilrs::new().unwrap();
+//! let mut players = PlayerSlots::new(4, AssignmentPolicy::ByPress(Button::Start));
+//! players.set_player_leds(true);
+//!
+//! loop {
+//!     while let Some(ev) = gilrs.next_event() {
+//!         match players.handle_event(&gilrs, &ev) {
+//!             Some(SlotEvent::Joined { slot, .. }) => println!("Player {} joined", slot + 1),
+//!             Some(SlotEvent::Left { slot, .. }) => println!("Player {} disconnected", slot + 1),
+//!             _ => (),
+//!         }
+//!     }
+//!
+//!     for (id, _) in gilrs.gamepads() {
+//!         if players.slot_of(id).is_none() {
+//!             // Show "press Start to join"
+//!         }
+//!     }
+//!     # break;
+//! }
+//! ```
+
+use crate::ev::{Button, Event, EventType};
+use crate::gamepad::{GamepadId, Gilrs};
+
+/// Decides when gamepad without a slot gets one.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AssignmentPolicy {
+    /// Gamepad takes first free slot as soon as it connects.
+    FirstCome,
+    /// Gamepad takes first free slot when given button is pressed ("press Start to join").
+    ByPress(Button),
+    /// Like `FirstCome`, but reconnected gamepad will also get back reserved slot if it's the same
+    /// device that left the slot, even if it got a different `GamepadId`.
+    ///
+    /// Devices are matched by [`Gamepad::uniq()`](crate::Gamepad::uniq) (serial number) when both
+    /// gamepads have it. Otherwise they are matched by UUID, which is the same for all gamepads of
+    /// the same model.
+    Sticky,
+}
+
+/// State of single player slot.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Slot {
+    /// No gamepad is assigned to this slot.
+    Free,
+    /// Slot is used by connected gamepad.
+    Occupied(GamepadId),
+    /// Gamepad assigned to this slot is disconnected. The slot is kept for it until it reconnects
+    /// or [`PlayerSlots::free()`] is called.
+    Reserved(GamepadId),
+}
+
+/// Change of slot assignment returned by [`PlayerSlots::handle_event()`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SlotEvent {
+    /// Gamepad took free slot.
+    Joined { slot: usize, id: GamepadId },
+    /// Gamepad got back slot reserved for it. `id` may be different than ID of gamepad that left
+    /// the slot.
+    Rejoined { slot: usize, id: GamepadId },
+    /// Gamepad assigned to slot disconnected. The slot is now reserved.
+    Left { slot: usize, id: GamepadId },
+}
+
+/// Fixed number of player slots managed by gamepad events.
+///
+/// See [module level documentation](index.html) for more info.
+#[derive(Clone, Debug)]
+pub struct PlayerSlots {
+    slots: Vec<Slot>,
+    // Gamepad that left the slot, used by `AssignmentPolicy::Sticky`.
+    devices: Vec<Device>,
+    policy: AssignmentPolicy,
+    player_leds: bool,
+}
+
+impl PlayerSlots {
+    /// Creates `count` free slots that will be assigned according to `policy`.
+    pub fn new(count: usize, policy: AssignmentPolicy) -> Self {
+        PlayerSlots {
+            slots: vec![Slot::Free; count],
+            devices: vec![Device::default(); count],
+            policy,
+            player_leds: false,
+        }
+    }
+
+    /// If `true`, player LEDs of gamepads will be set to their slot number, if gamepad have them.
+    /// See [`Gamepad::set_player_leds()`](crate::Gamepad::set_player_leds). Defaults to `false`.
+    pub fn set_player_leds(&mut self, enabled: bool) {
+        self.player_leds = enabled;
+    }
+
+    /// Changes assignment policy. It only affects gamepads that don't have slot yet.
+    pub fn set_policy(&mut self, policy: AssignmentPolicy) {
+        self.policy = policy;
+    }
+
+    /// Returns current assignment policy.
+    pub fn policy(&self) -> AssignmentPolicy {
+        self.policy
+    }
+
+    /// Assigns slots to gamepads that are already connected. `Gilrs` doesn't emit `Connected`
+    /// event for gamepads that were connected when it was created, so call this once after
+    /// creating `PlayerSlots` when using `FirstCome` or `Sticky` policy.
+    pub fn assign_connected(&mut self, gilrs: &Gilrs) -> Vec<SlotEvent> {
+        let mut events = Vec::new();
+        if let AssignmentPolicy::ByPress(_) = self.policy {
+            return events;
+        }
+
+        for (id, _) in gilrs.gamepads() {
+            if let Some(ev) = self.connected(id, Device::new(gilrs, id)) {
+                self.apply_leds(gilrs, ev);
+                events.push(ev);
+            }
+        }
+
+        events
+    }
+
+    /// Updates slots according to `event` and returns change of assignment, if any.
+    ///
+    /// Call it for every event returned by `Gilrs::next_event()`.
+    pub fn handle_event(&mut self, gilrs: &Gilrs, event: &Event) -> Option<SlotEvent> {
+        let ev = match event.event {
+            EventType::Connected => self.connected(event.id, Device::new(gilrs, event.id)),
+            EventType::Disconnected => self.disconnected(event.id, Device::new(gilrs, event.id)),
+            EventType::ButtonPressed(btn, _) => self.pressed(event.id, btn),
+            _ => None,
+        };
+
+        if let Some(ev) = ev {
+            self.apply_leds(gilrs, ev);
+        }
+
+        ev
+    }
+
+    /// Returns slot assigned to gamepad, if any. Disconnected gamepads keep their slots.
+    pub fn slot_of(&self, id: GamepadId) -> Option<usize> {
+        self.slots.iter().position(|slot| match *slot {
+            Slot::Occupied(slot_id) | Slot::Reserved(slot_id) => slot_id == id,
+            Slot::Free => false,
+        })
+    }
+
+    /// Returns ID of connected gamepad in `slot`.
+    pub fn gamepad_in(&self, slot: usize) -> Option<GamepadId> {
+        match self.slots.get(slot) {
+            Some(Slot::Occupied(id)) => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// Returns state of `slot`. Panics if `slot` is out of bounds.
+    pub fn slot(&self, slot: usize) -> Slot {
+        self.slots[slot]
+    }
+
+    /// Returns iterator over all slots.
+    pub fn slots(&self) -> impl Iterator<Item = (usize, Slot)> + '_ {
+        self.slots.iter().cloned().enumerate()
+    }
+
+    /// Returns number of slots.
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns true if there are no slots.
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Returns true if there is no free slot.
+    pub fn is_full(&self) -> bool {
+        !self.slots.contains(&Slot::Free)
+    }
+
+    /// Frees `slot`, for example when player left the game or you don't want to wait for
+    /// disconnected gamepad anymore. Returns ID of gamepad that was assigned to the slot.
+    pub fn free(&mut self, slot: usize) -> Option<GamepadId> {
+        match std::mem::replace(self.slots.get_mut(slot)?, Slot::Free) {
+            Slot::Occupied(id) | Slot::Reserved(id) => Some(id),
+            Slot::Free => None,
+        }
+    }
+
+    /// Frees `slot` and turns off player LEDs of gamepad that used it.
+    pub fn free_with_leds(&mut self, gilrs: &Gilrs, slot: usize) -> Option<GamepadId> {
+        let id = self.free(slot)?;
+        if self.player_leds {
+            if let Some(gamepad) = gilrs.connected_gamepad(id) {
+                gamepad.set_player_leds(None);
+            }
+        }
+
+        Some(id)
+    }
+
+    fn connected(&mut self, id: GamepadId, device: Device) -> Option<SlotEvent> {
+        // Same ID always gets its slot back. On most platforms ID is reused when gamepad with the
+        // same UUID reconnects.
+        if let Some(slot) = self.slot_of(id) {
+            self.slots[slot] = Slot::Occupied(id);
+            return Some(SlotEvent::Rejoined { slot, id });
+        }
+
+        match self.policy {
+            AssignmentPolicy::FirstCome => self.join(id),
+            AssignmentPolicy::ByPress(_) => None,
+            AssignmentPolicy::Sticky => {
+                let find = |by_uniq| {
+                    self.slots
+                        .iter()
+                        .zip(self.devices.iter())
+                        .position(|(slot, left)| {
+                            matches!(slot, Slot::Reserved(_)) && left.matches(&device, by_uniq)
+                        })
+                };
+                let reserved = find(true).or_else(|| find(false));
+
+                match reserved {
+                    Some(slot) => {
+                        self.slots[slot] = Slot::Occupied(id);
+                        Some(SlotEvent::Rejoined { slot, id })
+                    }
+                    None => self.join(id),
+                }
+            }
+        }
+    }
+
+    fn disconnected(&mut self, id: GamepadId, device: Device) -> Option<SlotEvent> {
+        let slot = self
+            .slots
+            .iter()
+            .position(|slot| *slot == Slot::Occupied(id))?;
+        self.slots[slot] = Slot::Reserved(id);
+        self.devices[slot] = device;
+
+        Some(SlotEvent::Left { slot, id })
+    }
+
+    fn pressed(&mut self, id: GamepadId, btn: Button) -> Option<SlotEvent> {
+        match self.policy {
+            AssignmentPolicy::ByPress(join_btn)
+                if join_btn == btn && self.slot_of(id).is_none() =>
+            {
+                self.join(id)
+            }
+            _ => None,
+        }
+    }
+
+    fn join(&mut self, id: GamepadId) -> Option<SlotEvent> {
+        let slot = self.slots.iter().position(|slot| *slot == Slot::Free)?;
+        self.slots[slot] = Slot::Occupied(id);
+
+        Some(SlotEvent::Joined { slot, id })
+    }
+
+    fn apply_leds(&self, gilrs: &Gilrs, ev: SlotEvent) {
+        if !self.player_leds {
+            return;
+        }
+
+        if let SlotEvent::Joined { slot, id } | SlotEvent::Rejoined { slot, id } = ev {
+            if let Some(gamepad) = gilrs.connected_gamepad(id) {
+                gamepad.set_player_leds(u8::try_from(slot + 1).ok());
+            }
+        }
+    }
+}
+
+/// Identity of device used to match reconnected gamepads.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Device {
+    uuid: [u8; 16],
+    uniq: Option<String>,
+}
+
+impl Device {
+    fn new(gilrs: &Gilrs, id: GamepadId) -> Self {
+        let gamepad = gilrs.gamepad(id);
+
+        Device {
+            uuid: gamepad.uuid(),
+            uniq: gamepad.uniq().map(ToOwned::to_owned),
+        }
+    }
+
+    /// If `by_uniq` is true, devices match only if both have the same uniq. Otherwise they match
+    /// if at least one of them doesn't have uniq and their UUIDs are the same.
+    fn matches(&self, other: &Device, by_uniq: bool) -> bool {
+        match (&self.uniq, &other.uniq) {
+            (Some(a), Some(b)) => by_uniq && a == b,
+            _ => !by_uniq && self.uuid != [0; 16] && self.uuid == other.uuid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAD_A: Device = Device {
+        uuid: [1; 16],
+        uniq: None,
+    };
+    const PAD_B: Device = Device {
+        uuid: [2; 16],
+        uniq: None,
+    };
+
+    fn with_uniq(uuid: u8, uniq: &str) -> Device {
+        Device {
+            uuid: [uuid; 16],
+            uniq: Some(uniq.to_owned()),
+        }
+    }
+
+    #[test]
+    fn first_come() {
+        let mut slots = PlayerSlots::new(2, AssignmentPolicy::FirstCome);
+
+        assert_eq!(
+            slots.connected(GamepadId(3), PAD_A),
+            Some(SlotEvent::Joined {
+                slot: 0,
+                id: GamepadId(3)
+            })
+        );
+        assert_eq!(
+            slots.connected(GamepadId(1), PAD_B),
+            Some(SlotEvent::Joined {
+                slot: 1,
+                id: GamepadId(1)
+            })
+        );
+        assert!(slots.is_full());
+        assert_eq!(slots.connected(GamepadId(5), PAD_B), None);
+    }
+
+    #[test]
+    fn reconnect_with_same_id() {
+        let mut slots = PlayerSlots::new(2, AssignmentPolicy::FirstCome);
+        slots.connected(GamepadId(0), PAD_A);
+        slots.connected(GamepadId(1), PAD_A);
+
+        assert_eq!(
+            slots.disconnected(GamepadId(0), PAD_A),
+            Some(SlotEvent::Left {
+                slot: 0,
+                id: GamepadId(0)
+            })
+        );
+        assert_eq!(slots.slot(0), Slot::Reserved(GamepadId(0)));
+        assert_eq!(slots.gamepad_in(0), None);
+        assert_eq!(slots.slot_of(GamepadId(0)), Some(0));
+
+        assert_eq!(
+            slots.connected(GamepadId(0), PAD_A),
+            Some(SlotEvent::Rejoined {
+                slot: 0,
+                id: GamepadId(0)
+            })
+        );
+        assert_eq!(slots.gamepad_in(0), Some(GamepadId(0)));
+    }
+
+    #[test]
+    fn sticky_by_uuid() {
+        let mut slots = PlayerSlots::new(3, AssignmentPolicy::Sticky);
+        slots.connected(GamepadId(0), PAD_A);
+        slots.connected(GamepadId(1), PAD_B);
+        slots.disconnected(GamepadId(1), PAD_B);
+
+        // Different gamepad doesn't take reserved slot.
+        assert_eq!(
+            slots.connected(GamepadId(2), PAD_A),
+            Some(SlotEvent::Joined {
+                slot: 2,
+                id: GamepadId(2)
+            })
+        );
+        assert_eq!(
+            slots.connected(GamepadId(3), PAD_B),
+            Some(SlotEvent::Rejoined {
+                slot: 1,
+                id: GamepadId(3)
+            })
+        );
+    }
+
+    #[test]
+    fn sticky_by_uniq() {
+        let mut slots = PlayerSlots::new(4, AssignmentPolicy::Sticky);
+        slots.connected(GamepadId(0), with_uniq(1, "pad-0"));
+        slots.connected(GamepadId(1), with_uniq(1, "pad-1"));
+        slots.connected(GamepadId(2), PAD_B);
+        slots.disconnected(GamepadId(0), with_uniq(1, "pad-0"));
+        slots.disconnected(GamepadId(1), with_uniq(1, "pad-1"));
+        slots.disconnected(GamepadId(2), PAD_B);
+
+        // Same model, but second device gets its own slot back.
+        assert_eq!(
+            slots.connected(GamepadId(3), with_uniq(1, "pad-1")),
+            Some(SlotEvent::Rejoined {
+                slot: 1,
+                id: GamepadId(3)
+            })
+        );
+        // Different serial of the same model is a different device.
+        assert_eq!(
+            slots.connected(GamepadId(4), with_uniq(1, "pad-2")),
+            Some(SlotEvent::Joined {
+                slot: 3,
+                id: GamepadId(4)
+            })
+        );
+        // Falls back to UUID when one of devices doesn't have uniq.
+        assert_eq!(
+            slots.connected(GamepadId(5), with_uniq(2, "pad-3")),
+            Some(SlotEvent::Rejoined {
+                slot: 2,
+                id: GamepadId(5)
+            })
+        );
+    }
+
+    #[test]
+    fn first_come_does_not_match_uuid() {
+        let mut slots = PlayerSlots::new(2, AssignmentPolicy::FirstCome);
+        slots.connected(GamepadId(0), PAD_A);
+        slots.disconnected(GamepadId(0), PAD_A);
+
+        assert_eq!(
+            slots.connected(GamepadId(1), PAD_A),
+            Some(SlotEvent::Joined {
+                slot: 1,
+                id: GamepadId(1)
+            })
+        );
+    }
+
+    #[test]
+    fn by_press() {
+        let mut slots = PlayerSlots::new(2, AssignmentPolicy::ByPress(Button::Start));
+
+        assert_eq!(slots.connected(GamepadId(0), PAD_A), None);
+        assert_eq!(slots.pressed(GamepadId(0), Button::South), None);
+        assert_eq!(
+            slots.pressed(GamepadId(0), Button::Start),
+            Some(SlotEvent::Joined {
+                slot: 0,
+                id: GamepadId(0)
+            })
+        );
+        assert_eq!(slots.pressed(GamepadId(0), Button::Start), None);
+
+        assert_eq!(slots.free(0), Some(GamepadId(0)));
+        assert_eq!(slots.slot_of(GamepadId(0)), None);
+    }
+}
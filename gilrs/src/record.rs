@@ -0,0 +1,675 @@
+// Copyright 2016-2018 Mateusz Sieczko and other GilRs Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Recording and replaying event streams.
+//!
+//! Recording is a sequence of [`Record`]s. `Record::Gamepad` describes gamepad (name, UUID,
+//! mapping, …) and is always stored before first event from that gamepad, `Record::Event` stores
+//! single [`Event`] with its timestamp.
+//!
+//! With `serde-serialize` feature enabled, [`Recorder`] writes records to a file, one JSON object
+//! per line, and [`Reader`] reads them back.
+//!
+//! To replay recording, create `Gilrs` with
+//! [`GilrsBuilder::replay()`](crate::GilrsBuilder::replay). Such `Gilrs` doesn't use any real
+//! gamepads. Instead, it returns recorded events from `next_event()` and replayed gamepads are
+//! available as normal [`Gamepad`](crate::Gamepad)s, so cached state works as usual.
+//!
+//! Recorded events were already filtered, so by default filters are not applied to them again. If
+//! you record with filters disabled, use
+//! [`GilrsBuilder::filter_replay()`](crate::GilrsBuilder::filter_replay) to run replayed events
+//! through filters.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # #[cfg(feature = "serde-serialize")] {
+//! use gilrs::{Gilrs, GilrsBuilder};
+//! use gilrs::record::{Reader, Recorder, ReplayMode};
+//! use std::fs::File;
+//! use std::io::BufReader;
+//!
+//! // Record
+//! let mut gilrs = Gilrs::new().unwrap();
+//! let file = File::create("events.jsonl").unwrap();
+//! let mut recorder = Recorder::new(&gilrs, file).unwrap();
+//! # for _ in 0..1 {
+//! while let Some(ev) = gilrs.next_event() {
+//!     recorder.record(&gilrs, &ev).unwrap();
+//! }
+//! # }
+//! drop(recorder);
+//!
+//! // Replay
+//! let file = BufReader::new(File::open("events.jsonl").unwrap());
+//! let records = Reader::new(file).map(Result::unwrap);
+//! let mut gilrs = GilrsBuilder::new()
+//!     .replay(records, ReplayMode::AsFastAsPossible)
+//!     .build()
+//!     .unwrap();
+//!
+//! while let Some(ev) = gilrs.next_event() {
+//!     println!("{:?}, is South pressed: {}", ev, gilrs.gamepad(ev.id).is_pressed(gilrs::Button::South));
+//! }
+//! # }
+//! ```
+
+use crate::ev::{AxisOrBtn, Code, Event, EventType};
+use crate::gamepad::{Gamepad, GamepadId};
+use crate::utils;
+
+use gilrs_core::{AxisInfo, EvCode, PowerInfo};
+
+use std::fmt::{self, Debug, Formatter};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+#[cfg(feature = "serde-serialize")]
+use serde::{Deserialize, Serialize};
+
+/// Single entry of recording.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub enum Record {
+    /// Information about gamepad. It's recorded before first event of gamepad and again when
+    /// gamepad is connected.
+    Gamepad(GamepadInfo),
+    /// Event returned from `Gilrs::next_event()`.
+    Event(Event),
+}
+
+/// Information about recorded gamepad.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct GamepadInfo {
+    pub id: GamepadId,
+    /// Name reported by OS.
+    pub name: String,
+    pub uuid: [u8; 16],
+    /// Unique identifier of device, if OS reported it.
+    #[cfg_attr(feature = "serde-serialize", serde(default))]
+    pub uniq: Option<String>,
+    pub vendor_id: Option<u16>,
+    pub product_id: Option<u16>,
+    pub is_connected: bool,
+    pub power_info: PowerInfo,
+    pub buttons: Vec<Code>,
+    pub axes: Vec<(Code, Option<AxisInfo>)>,
+    pub mapping: MappingInfo,
+}
+
+impl GamepadInfo {
+    /// Collects information about `gamepad`.
+    pub fn new(gamepad: &Gamepad<'_>) -> Self {
+        let inner = gamepad.inner();
+
+        GamepadInfo {
+            id: gamepad.id(),
+            name: gamepad.os_name().to_owned(),
+            uuid: gamepad.uuid(),
+            uniq: gamepad.uniq().map(ToOwned::to_owned),
+            vendor_id: gamepad.vendor_id(),
+            product_id: gamepad.product_id(),
+            is_connected: gamepad.is_connected(),
+            power_info: gamepad.power_info(),
+            buttons: inner.buttons().iter().cloned().map(Code).collect(),
+            axes: inner
+                .axes()
+                .iter()
+                .map(|&nec| (Code(nec), inner.axis_info(nec).cloned()))
+                .collect(),
+            mapping: MappingInfo {
+                name: gamepad.map_name().map(ToOwned::to_owned),
+                entries: gamepad
+                    .mapping()
+                    .entries()
+                    .map(|(c, e)| (Code(c), e))
+                    .collect(),
+                hats_mapped: gamepad.mapping().hats_mapped(),
+            },
+        }
+    }
+}
+
+/// Mapping used by recorded gamepad.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde-serialize", derive(Serialize, Deserialize))]
+pub struct MappingInfo {
+    /// Name of SDL mapping, `None` if gamepad used default mapping.
+    pub name: Option<String>,
+    pub entries: Vec<(Code, AxisOrBtn)>,
+    pub hats_mapped: u8,
+}
+
+/// Controls speed of replay.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ReplayMode {
+    /// Events are returned with the same delays between them as when they were recorded.
+    RealTime,
+    /// Events are returned as soon as they are requested.
+    AsFastAsPossible,
+}
+
+/// Gamepad recreated from `GamepadInfo`.
+#[derive(Debug)]
+pub(crate) struct ReplayedGamepad {
+    info: GamepadInfo,
+    buttons: Vec<EvCode>,
+    axes: Vec<EvCode>,
+}
+
+impl ReplayedGamepad {
+    fn new(info: GamepadInfo) -> Self {
+        ReplayedGamepad {
+            buttons: info.buttons.iter().map(|c| c.0).collect(),
+            axes: info.axes.iter().map(|(c, _)| c.0).collect(),
+            info,
+        }
+    }
+
+    // Placeholder for ID that appeared in recording before its description.
+    fn unknown(id: GamepadId) -> Self {
+        ReplayedGamepad::new(GamepadInfo {
+            id,
+            name: String::new(),
+            uuid: [0; 16],
+            uniq: None,
+            vendor_id: None,
+            product_id: None,
+            is_connected: false,
+            power_info: PowerInfo::Unknown,
+            buttons: Vec::new(),
+            axes: Vec::new(),
+            mapping: MappingInfo {
+                name: None,
+                entries: Vec::new(),
+                hats_mapped: 0,
+            },
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.info.name
+    }
+
+    pub fn uuid(&self) -> [u8; 16] {
+        self.info.uuid
+    }
+
+    pub fn uniq(&self) -> Option<&str> {
+        self.info.uniq.as_deref()
+    }
+
+    pub fn vendor_id(&self) -> Option<u16> {
+        self.info.vendor_id
+    }
+
+    pub fn product_id(&self) -> Option<u16> {
+        self.info.product_id
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.info.is_connected
+    }
+
+    pub fn power_info(&self) -> PowerInfo {
+        self.info.power_info
+    }
+
+    pub fn buttons(&self) -> &[EvCode] {
+        &self.buttons
+    }
+
+    pub fn axes(&self) -> &[EvCode] {
+        &self.axes
+    }
+
+    pub fn axis_info(&self, nec: EvCode) -> Option<&AxisInfo> {
+        self.info
+            .axes
+            .iter()
+            .find(|(c, _)| c.0 == nec)
+            .and_then(|(_, info)| info.as_ref())
+    }
+
+    pub fn mapping(&self) -> &MappingInfo {
+        &self.info.mapping
+    }
+}
+
+/// Source of events for `Gilrs` created with `GilrsBuilder::replay()`.
+pub(crate) struct Replay {
+    records: Box<dyn Iterator<Item = Record> + Send>,
+    next: Option<Event>,
+    gamepads: Vec<ReplayedGamepad>,
+    mode: ReplayMode,
+    // (When replay started, same in system time, time of first recorded event)
+    start: Option<(Instant, SystemTime, SystemTime)>,
+}
+
+impl Replay {
+    pub fn new<I>(records: I, mode: ReplayMode) -> Self
+    where
+        I: IntoIterator<Item = Record>,
+        I::IntoIter: Send + 'static,
+    {
+        let mut replay = Replay {
+            records: Box::new(records.into_iter()),
+            next: None,
+            gamepads: Vec::new(),
+            mode,
+            start: None,
+        };
+        // Gamepads described before first event are treated like gamepads that were connected
+        // before `Gilrs` was created.
+        replay.fetch();
+
+        replay
+    }
+
+    /// Reads records until next event, updating gamepads on the way.
+    fn fetch(&mut self) {
+        while self.next.is_none() {
+            match self.records.next() {
+                Some(Record::Gamepad(info)) => {
+                    let id = info.id.0;
+                    while self.gamepads.len() <= id {
+                        let placeholder = ReplayedGamepad::unknown(GamepadId(self.gamepads.len()));
+                        self.gamepads.push(placeholder);
+                    }
+                    self.gamepads[id] = ReplayedGamepad::new(info);
+                }
+                Some(Record::Event(ev)) => {
+                    if ev.id.0 >= self.gamepads.len() {
+                        warn!("Skipping recorded event for undescribed gamepad: {:?}", ev);
+                        continue;
+                    }
+                    self.next = Some(ev);
+                }
+                None => return,
+            }
+        }
+    }
+
+    pub fn next_event(&mut self, is_blocking: bool, timeout: Option<Duration>) -> Option<Event> {
+        self.fetch();
+        let ev = self.next?;
+
+        let (start, start_time, first_time) = *self
+            .start
+            .get_or_insert_with(|| (Instant::now(), utils::time_now(), ev.time));
+        let offset = ev.time.duration_since(first_time).unwrap_or_default();
+
+        if self.mode == ReplayMode::RealTime {
+            let now = Instant::now();
+            let due = start + offset;
+
+            if due > now {
+                let wait = due - now;
+                match (is_blocking, timeout) {
+                    (false, _) => return None,
+                    (true, Some(timeout)) if timeout < wait => {
+                        thread::sleep(timeout);
+                        return None;
+                    }
+                    (true, _) => thread::sleep(wait),
+                }
+            }
+        }
+
+        self.next = None;
+        let gamepad = &mut self.gamepads[ev.id.0];
+        match ev.event {
+            EventType::Connected => gamepad.info.is_connected = true,
+            EventType::Disconnected => gamepad.info.is_connected = false,
+            _ => (),
+        }
+
+        Some(Event {
+            time: start_time + offset,
+            ..ev
+        })
+    }
+
+    pub fn gamepad(&self, id: usize) -> Option<&ReplayedGamepad> {
+        self.gamepads.get(id)
+    }
+
+    pub fn last_gamepad_hint(&self) -> usize {
+        self.gamepads.len()
+    }
+
+    pub fn is_finished(&mut self) -> bool {
+        self.fetch();
+        self.next.is_none()
+    }
+}
+
+impl Debug for Replay {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Replay")
+            .field("next", &self.next)
+            .field("gamepads", &self.gamepads)
+            .field("mode", &self.mode)
+            .field("start", &self.start)
+            .finish()
+    }
+}
+
+#[cfg(feature = "serde-serialize")]
+pub use self::file::{Error, Reader, Recorder};
+
+#[cfg(feature = "serde-serialize")]
+mod file {
+    use super::{GamepadInfo, Record};
+    use crate::ev::{Event, EventType};
+    use crate::gamepad::{GamepadId, Gilrs};
+
+    use std::error::Error as StdError;
+    use std::fmt::{self, Display, Formatter};
+    use std::io::{self, BufRead, Lines, Write};
+
+    /// Writes events to line-delimited JSON file.
+    ///
+    /// See [module level documentation](index.html) for more info.
+    #[derive(Debug)]
+    pub struct Recorder<W: Write> {
+        out: W,
+        described: Vec<bool>,
+    }
+
+    impl<W: Write> Recorder<W> {
+        /// Creates new recorder and writes information about all gamepads known to `gilrs`.
+        pub fn new(gilrs: &Gilrs, out: W) -> Result<Self, Error> {
+            let mut recorder = Recorder {
+                out,
+                described: Vec::new(),
+            };
+
+            for id in 0..gilrs.gamepads_data.len() {
+                recorder.describe(gilrs, GamepadId(id))?;
+            }
+
+            Ok(recorder)
+        }
+
+        /// Writes `event`, preceded by information about its gamepad if it wasn't recorded yet or
+        /// if `event` is `Connected`.
+        pub fn record(&mut self, gilrs: &Gilrs, event: &Event) -> Result<(), Error> {
+            let described = self.described.get(event.id.0).cloned().unwrap_or(false);
+            if !described || event.event == EventType::Connected {
+                self.describe(gilrs, event.id)?;
+            }
+
+            self.write(&Record::Event(*event))
+        }
+
+        /// Writes single record.
+        pub fn write(&mut self, record: &Record) -> Result<(), Error> {
+            serde_json::to_writer(&mut self.out, record)?;
+            self.out.write_all(b"\n")?;
+
+            Ok(())
+        }
+
+        /// Flushes output.
+        pub fn flush(&mut self) -> Result<(), Error> {
+            self.out.flush()?;
+
+            Ok(())
+        }
+
+        /// Returns underlying writer.
+        pub fn into_inner(self) -> W {
+            self.out
+        }
+
+        fn describe(&mut self, gilrs: &Gilrs, id: GamepadId) -> Result<(), Error> {
+            if id.0 >= self.described.len() {
+                self.described.resize(id.0 + 1, false);
+            }
+            self.described[id.0] = true;
+
+            self.write(&Record::Gamepad(GamepadInfo::new(&gilrs.gamepad(id))))
+        }
+    }
+
+    /// Reads records from line-delimited JSON file created by [`Recorder`].
+    ///
+    /// Empty lines are skipped.
+    #[derive(Debug)]
+    pub struct Reader<R> {
+        lines: Lines<R>,
+    }
+
+    impl<R: BufRead> Reader<R> {
+        pub fn new(input: R) -> Self {
+            Reader {
+                lines: input.lines(),
+            }
+        }
+    }
+
+    impl<R: BufRead> Iterator for Reader<R> {
+        type Item = Result<Record, Error>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            loop {
+                let line = match self.lines.next()? {
+                    Ok(line) => line,
+                    Err(e) => return Some(Err(e.into())),
+                };
+
+                if !line.trim().is_empty() {
+                    return Some(serde_json::from_str(&line).map_err(Error::from));
+                }
+            }
+        }
+    }
+
+    /// Error that can be returned when reading or writing recordings.
+    #[derive(Debug)]
+    #[non_exhaustive]
+    pub enum Error {
+        /// I/O error.
+        Io(io::Error),
+        /// Record couldn't be serialized or deserialized.
+        Format(serde_json::Error),
+    }
+
+    impl Display for Error {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            match self {
+                Error::Io(_) => f.write_str("I/O error"),
+                Error::Format(_) => f.write_str("invalid record"),
+            }
+        }
+    }
+
+    impl StdError for Error {
+        fn source(&self) -> Option<&(dyn StdError + 'static)> {
+            match self {
+                Error::Io(e) => Some(e),
+                Error::Format(e) => Some(e),
+            }
+        }
+    }
+
+    impl From<io::Error> for Error {
+        fn from(f: io::Error) -> Self {
+            Error::Io(f)
+        }
+    }
+
+    impl From<serde_json::Error> for Error {
+        fn from(f: serde_json::Error) -> Self {
+            Error::Format(f)
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::ev::filter::FilterResult;
+    use crate::ev::{Button, EventType};
+    use crate::GilrsBuilder;
+    use gilrs_core::native_ev_codes as nec;
+
+    pub(crate) fn info(id: usize) -> GamepadInfo {
+        GamepadInfo {
+            id: GamepadId(id),
+            name: "Replayed".to_owned(),
+            uuid: [1; 16],
+            uniq: None,
+            vendor_id: Some(1),
+            product_id: Some(2),
+            is_connected: true,
+            power_info: PowerInfo::Wired,
+            buttons: vec![Code(nec::BTN_SOUTH)],
+            axes: vec![],
+            mapping: MappingInfo {
+                name: None,
+                entries: vec![(Code(nec::BTN_SOUTH), AxisOrBtn::Btn(Button::South))],
+                hats_mapped: 0,
+            },
+        }
+    }
+
+    fn event(id: usize, event: EventType, ms: u64) -> Record {
+        Record::Event(Event {
+            id: GamepadId(id),
+            event,
+            time: SystemTime::UNIX_EPOCH + Duration::from_millis(ms),
+        })
+    }
+
+    #[test]
+    fn replay_updates_state() {
+        let south = Code(nec::BTN_SOUTH);
+        let records = vec![
+            Record::Gamepad(info(0)),
+            event(0, EventType::ButtonPressed(Button::South, south), 0),
+            event(0, EventType::ButtonChanged(Button::South, 1.0, south), 0),
+            event(0, EventType::Disconnected, 10),
+        ];
+
+        let mut gilrs = GilrsBuilder::new()
+            .replay(records, ReplayMode::AsFastAsPossible)
+            .build()
+            .unwrap();
+
+        assert_eq!(gilrs.gamepads().count(), 1);
+        assert_eq!(gilrs.gamepad(GamepadId(0)).name(), "Replayed");
+
+        let ev = gilrs.next_event().unwrap();
+        assert_eq!(ev.event, EventType::ButtonPressed(Button::South, south));
+        assert!(gilrs.gamepad(GamepadId(0)).is_pressed(Button::South));
+        let ev = gilrs.next_event().unwrap();
+        assert_eq!(
+            ev.event,
+            EventType::ButtonChanged(Button::South, 1.0, south)
+        );
+
+        assert_eq!(gilrs.next_event().unwrap().event, EventType::Disconnected);
+        assert!(!gilrs.gamepad(GamepadId(0)).is_connected());
+        assert_eq!(gilrs.next_event(), None);
+        assert!(gilrs.is_replay_finished());
+    }
+
+    #[test]
+    fn filter_replay() {
+        fn drop_all(_: Event, _: &mut crate::Gilrs) -> FilterResult {
+            FilterResult::Drop
+        }
+
+        let records = || vec![Record::Gamepad(info(0)), event(0, EventType::Connected, 0)];
+
+        let mut gilrs = GilrsBuilder::new()
+            .add_filter(drop_all)
+            .replay(records(), ReplayMode::AsFastAsPossible)
+            .build()
+            .unwrap();
+        assert_eq!(gilrs.next_event().unwrap().event, EventType::Connected);
+
+        let mut gilrs = GilrsBuilder::new()
+            .add_filter(drop_all)
+            .replay(records(), ReplayMode::AsFastAsPossible)
+            .filter_replay(true)
+            .build()
+            .unwrap();
+        assert_eq!(gilrs.next_event(), None);
+        assert!(gilrs.is_replay_finished());
+    }
+
+    #[test]
+    fn replay_connects_new_gamepad() {
+        let records = vec![
+            Record::Gamepad(info(0)),
+            Record::Gamepad(info(1)),
+            event(1, EventType::Connected, 5),
+        ];
+
+        let mut gilrs = GilrsBuilder::new()
+            .replay(records, ReplayMode::AsFastAsPossible)
+            .build()
+            .unwrap();
+
+        assert_eq!(gilrs.next_event().unwrap().event, EventType::Connected);
+        assert_eq!(gilrs.gamepads().count(), 2);
+        assert_eq!(
+            gilrs.gamepad(GamepadId(1)).button_code(Button::South),
+            Some(Code(nec::BTN_SOUTH))
+        );
+    }
+
+    #[test]
+    fn real_time_replay_waits() {
+        let records = vec![
+            Record::Gamepad(info(0)),
+            event(0, EventType::Connected, 0),
+            event(0, EventType::Disconnected, 60_000),
+        ];
+
+        let mut gilrs = GilrsBuilder::new()
+            .replay(records, ReplayMode::RealTime)
+            .build()
+            .unwrap();
+
+        assert_eq!(gilrs.next_event().unwrap().event, EventType::Connected);
+        assert_eq!(gilrs.next_event(), None);
+        assert!(!gilrs.is_replay_finished());
+    }
+
+    #[cfg(feature = "serde-serialize")]
+    #[test]
+    fn file_round_trip() {
+        let records = vec![
+            Record::Gamepad(info(0)),
+            event(
+                0,
+                EventType::ButtonPressed(Button::South, Code(nec::BTN_SOUTH)),
+                3,
+            ),
+        ];
+
+        let gilrs = GilrsBuilder::new()
+            .replay(Vec::new(), ReplayMode::AsFastAsPossible)
+            .build()
+            .unwrap();
+        let mut recorder = Recorder::new(&gilrs, Vec::new()).unwrap();
+        for record in &records {
+            recorder.write(record).unwrap();
+        }
+        let out = recorder.into_inner();
+
+        let read = Reader::new(&out[..])
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(read, records);
+    }
+}
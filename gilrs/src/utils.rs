@@ -7,15 +7,6 @@
 
 pub use gilrs_core::utils::*;
 
-/// Like `(a: f32 / b).ceil()` but for integers.
-pub fn ceil_div(a: u32, b: u32) -> u32 {
-    if a == 0 {
-        0
-    } else {
-        1 + ((a - 1) / b)
-    }
-}
-
 pub fn clamp(x: f32, min: f32, max: f32) -> f32 {
     x.clamp(min, max)
 }